@@ -1,18 +1,88 @@
 use crate::error::*;
 use crate::terminal::*;
-use chargrid_app::{App, ControlFlow};
+use chargrid_app::{App, Clipboard};
 #[cfg(feature = "gamepad")]
 use chargrid_gamepad::GamepadContext;
 use chargrid_render::*;
-use std::thread;
+use std::cell::Cell;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::rc::Rc;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-const FRAME_DURATION: Duration = Duration::from_micros(1_000_000 / 60);
+const DEFAULT_FRAME_RATE: u32 = 60;
+
+/// Options which must be chosen before the terminal is initialised
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// Use the kitty keyboard protocol if the terminal supports it. This allows escape to be told
+    /// apart from the start of an escape sequence, and enables `Input::KeyRepeat` and
+    /// `Input::KeyRelease` events. Note that while the protocol is enabled, keys pressed with
+    /// ctrl (e.g. ctrl+c) are reported as `Input::ModifiedKeyboard` rather than as control
+    /// characters.
+    pub kitty_keyboard_protocol: bool,
+    /// Report mouse coordinates in pixels rather than cells if the terminal supports it (DEC
+    /// private mode 1016). Use `Context::mouse_pixel_coordinates` to find out whether it does.
+    pub mouse_pixel_coordinates: bool,
+    /// Draw to this many rows starting at the cursor's current row, rather than using the whole
+    /// terminal. The alternate screen isn't used, so the final frame remains in the terminal's
+    /// scrollback when the app exits. The number of rows can be changed while the app runs with
+    /// `Context::inline_height`.
+    pub inline_height: Option<u32>,
+    /// Report ctrl+z as input rather than suspending the process. By default, ctrl+z restores
+    /// the terminal and stops the process, as in other terminal programs, and the terminal is set
+    /// up again when the process is continued (e.g. with `fg`).
+    pub ctrl_z_as_input: bool,
+    /// Capabilities which replace those of the terminal's terminfo entry. When the terminfo
+    /// database is unavailable or lacks the terminal's entry, a built-in description of xterm,
+    /// screen/tmux or the linux console is used, and these override it in the same way.
+    pub term_info_overrides: TermInfoOverrides,
+}
+
+/// A handle for changing the number of rows drawn to in inline mode, which can be passed to an
+/// app. Changes take effect before the next frame, and are reported to the app as
+/// `Input::Resize`.
+#[derive(Clone, Debug, Default)]
+pub struct InlineHeight {
+    requested: Rc<Cell<Option<u32>>>,
+}
+
+impl InlineHeight {
+    /// Grow or shrink the region drawn to. It's limited to the height of the terminal.
+    pub fn set(&self, height: u32) {
+        self.requested.set(Some(height));
+    }
+
+    fn take_requested(&self) -> Option<u32> {
+        self.requested.take()
+    }
+}
+
+/// A handle for waking the loop in `Context::run_app`, which can be passed to an app or sent to
+/// another thread. In idle mode (see `Context::with_idle_mode`), requesting a wakeup causes a
+/// frame to be rendered.
+#[derive(Clone, Debug)]
+pub struct Wakeup {
+    sender: Arc<UnixStream>,
+}
+
+impl Wakeup {
+    pub fn request(&self) {
+        // This only fails if the stream is full, in which case a wakeup is already pending
+        let _ = (&*self.sender).write_all(&[0]);
+    }
+}
 
 /// An interface to a terminal for rendering `View`s, and getting input.
 pub struct Context {
     terminal: Terminal,
     buffer: Buffer,
+    clipboard: Clipboard,
+    inline_height: Option<InlineHeight>,
+    wakeup: Wakeup,
+    frame_duration: Duration,
+    idle_mode: bool,
     #[cfg(feature = "gamepad")]
     gamepad: GamepadContext,
 }
@@ -20,64 +90,284 @@ pub struct Context {
 impl Context {
     /// Initialise a new context using the current terminal.
     pub fn new() -> Result<Self> {
-        Terminal::new().and_then(Self::from_terminal)
+        Self::new_with_config(Config::default())
+    }
+
+    /// Initialise a new context using the current terminal, with the given options.
+    pub fn new_with_config(config: Config) -> Result<Self> {
+        Terminal::new(&config).and_then(Self::from_terminal)
+    }
+
+    /// Initialise a new context which communicates with a terminal over a pair of byte streams
+    /// (e.g. a socket or a pty), rather than the controlling tty. The terminal is expected to
+    /// already be in raw mode, and its size is taken from `streams.size`. Input is read on a
+    /// separate thread, which exits when the input stream ends. `run_app` stops with an error if
+    /// the input stream ends while the app is running.
+    pub fn new_with_streams<R, W>(streams: Streams<R, W>, config: Config) -> Result<Self>
+    where
+        R: Read + Send + 'static,
+        W: Write + 'static,
+    {
+        Terminal::new_with_streams(streams, &config).and_then(Self::from_terminal)
+    }
+
+    /// Override whether styled (e.g. curly) and coloured underlines are emitted. By default
+    /// they are only emitted for terminals which report the "Smulx" or "Su" terminfo capability
+    /// when asked, or whose terminfo overrides include it. When disabled, all underlines are drawn
+    /// as plain underlines.
+    pub fn with_styled_underlines(mut self, styled_underlines: bool) -> Self {
+        self.terminal.set_styled_underlines(styled_underlines);
+        self
+    }
+
+    /// Override whether frames are drawn as synchronized updates (DEC private mode 2026), which
+    /// prevents the terminal from displaying partially-drawn frames. By default this is enabled
+    /// iff the terminal reports that it supports the mode.
+    pub fn with_synchronized_output(mut self, synchronized_output: bool) -> Self {
+        self.terminal.set_synchronized_output(synchronized_output);
+        self
+    }
+
+    /// Draw cells which the app leaves blank in the terminal's default colours, rather than black,
+    /// so the user's terminal theme shows through. See also `Style::with_default_foreground` and
+    /// `Style::with_default_background`, which draw individual cells in the default colours.
+    pub fn with_blank_default_colours(mut self, default_colours: bool) -> Self {
+        self.buffer.set_blank_default_colours(default_colours);
+        self
+    }
+
+    /// Override how images are drawn, or disable them with `None`. By default, the kitty graphics
+    /// protocol is used if the terminal supports it, and sixel otherwise if the terminal supports
+    /// it.
+    pub fn with_image_protocol(mut self, image_protocol: Option<ImageProtocol>) -> Self {
+        self.terminal.set_image_protocol(image_protocol);
+        self
+    }
+
+    /// Override how characters are sent to the terminal. By default, characters are sent as
+    /// UTF-8 unless the locale is set and its character encoding isn't UTF-8, or the terminal is
+    /// the linux console. Otherwise box-drawing characters are
+    /// drawn with the terminal's alternate character set (DEC special graphics) if it has one,
+    /// and other non-ascii characters are transliterated to ascii.
+    pub fn with_charset(mut self, charset: Charset) -> Self {
+        self.terminal.set_charset(charset);
+        self
+    }
+
+    /// Replace the table of ascii characters drawn in place of non-ascii characters when the
+    /// terminal can't display them (see `with_charset`)
+    pub fn with_transliteration(mut self, transliteration: Transliteration) -> Self {
+        self.terminal.set_transliteration(transliteration);
+        self
+    }
+
+    /// Override whether the cursor is moved between changed cells using the cheapest available
+    /// motion (relative moves, carriage returns, or redrawing unchanged cells), and whether runs
+    /// of the same character are sent as a single repeat sequence. Enabled by default. When
+    /// disabled, the cursor is always moved with absolute positioning.
+    pub fn with_optimised_cursor_motion(mut self, optimise_cursor_motion: bool) -> Self {
+        self.terminal
+            .set_optimise_cursor_motion(optimise_cursor_motion);
+        self
+    }
+
+    /// Override whether background colours are dithered when drawing with a colour encoding which
+    /// approximates colours using a limited palette, which reduces banding in gradients. Disabled
+    /// by default.
+    pub fn with_dithering(mut self, dithering: bool) -> Self {
+        self.terminal.set_dithering(dithering);
+        self
+    }
+
+    /// Set the number of frames to render per second. Defaults to 60.
+    pub fn with_target_frame_rate(mut self, frames_per_second: u32) -> Self {
+        self.frame_duration = Duration::from_secs(1) / frames_per_second.max(1);
+        self
+    }
+
+    /// Override whether frames are only rendered after input, a resize, or a wakeup requested
+    /// with a `Wakeup` handle, rather than continuously at the target frame rate. Frames are still
+    /// rendered at most once per frame period. Disabled by default.
+    pub fn with_idle_mode(mut self, idle_mode: bool) -> Self {
+        self.idle_mode = idle_mode;
+        self
+    }
+
+    /// Returns a handle for waking the loop in `run_app`
+    pub fn wakeup(&self) -> Wakeup {
+        self.wakeup.clone()
+    }
+
+    /// The way images are drawn, or `None` if images aren't drawn
+    pub fn image_protocol(&self) -> Option<ImageProtocol> {
+        self.terminal.image_protocol()
+    }
+
+    /// How characters are sent to the terminal
+    pub fn charset(&self) -> Charset {
+        self.terminal.charset()
+    }
+
+    /// The default foreground and background colours and the palette of the terminal, as reported
+    /// by the terminal when the context was created. Colours the terminal didn't report are `None`.
+    /// `TerminalColours::is_background_dark` tells light themes apart from dark ones.
+    pub fn terminal_colours(&self) -> &TerminalColours {
+        self.terminal.terminal_colours()
+    }
+
+    /// True iff the coordinates of mouse inputs are in pixels rather than cells. This is only
+    /// the case if it was requested with `Config::mouse_pixel_coordinates` and the terminal
+    /// supports it.
+    pub fn mouse_pixel_coordinates(&self) -> bool {
+        self.terminal.mouse_pixel_coordinates()
+    }
+
+    /// The colour encoding best suited to the terminal, which can be passed to `run_app`. It's
+    /// chosen based on the NO_COLOR and COLORTERM environment variables, the terminfo database,
+    /// and, if these don't indicate true colour support, by asking the terminal whether it has
+    /// the "RGB" or "Tc" capabilities.
+    pub fn col_encode(&self) -> col_encode::Dynamic {
+        self.terminal.col_encode()
+    }
+
+    /// Returns a handle for writing to the system clipboard, which can be passed to an app. The
+    /// clipboard is written with OSC 52, which some terminals ignore or only support when enabled
+    /// in their configuration.
+    pub fn clipboard(&self) -> Clipboard {
+        self.clipboard.clone()
+    }
+
+    /// Returns a handle for changing the number of rows drawn to, if the context was created in
+    /// inline mode (see `Config::inline_height`)
+    pub fn inline_height(&self) -> Option<InlineHeight> {
+        self.inline_height.clone()
     }
 
     fn from_terminal(mut terminal: Terminal) -> Result<Self> {
         let size = terminal.resize_if_necessary()?;
         let buffer = Buffer::new(size);
+        let inline_height = terminal.inline_height().map(|_| InlineHeight::default());
+        let wakeup = Wakeup {
+            sender: Arc::new(terminal.wake_sender()?),
+        };
         Ok(Self {
             terminal,
             buffer,
+            clipboard: Clipboard::new(),
+            inline_height,
+            wakeup,
+            frame_duration: Duration::from_secs(1) / DEFAULT_FRAME_RATE,
+            idle_mode: false,
             #[cfg(feature = "gamepad")]
             gamepad: GamepadContext::new(),
         })
     }
 
-    fn resize_if_necessary(&mut self) -> Result<()> {
-        let size = self.terminal.resize_if_necessary()?;
-        if size != self.buffer.size() {
-            self.buffer.resize(size);
+    /// Returns the new size if the terminal or inline region was resized
+    fn resize_if_necessary(&mut self) -> Result<Option<Size>> {
+        let mut size = self.buffer.size();
+        if self.terminal.take_resized() {
+            size = self.terminal.resize_if_necessary()?;
+        }
+        if let Some(height) = self
+            .inline_height
+            .as_ref()
+            .and_then(InlineHeight::take_requested)
+        {
+            size = self.terminal.set_inline_height(height)?;
         }
-        Ok(())
+        if size == self.buffer.size() {
+            return Ok(None);
+        }
+        self.buffer.resize(size);
+        Ok(Some(size))
     }
 
     fn drain_input(&mut self) -> Result<DrainInput> {
         self.terminal.drain_input()
     }
 
-    pub fn run_app<A, E>(mut self, mut app: A, col_encode: E)
+    /// Run the app until it exits. Rather than sleeping between frames, the loop waits for input,
+    /// signals and wakeups, so input is handled as soon as it arrives. The time passed to
+    /// `App::on_frame` is the time since the previous frame was rendered. Panics if the terminal
+    /// can't be used or the app exits with an error. See `try_run_app` for a version which
+    /// returns errors instead.
+    pub fn run_app<A, E>(self, app: A, col_encode: E)
     where
         A: App + 'static,
         E: ColEncode,
     {
-        let _ = col_encode;
+        if let Err(error) = self.try_run_app(app, col_encode) {
+            panic!("{}", error);
+        }
+    }
+
+    /// Run the app until it exits, returning an error if the terminal can't be used or the app
+    /// exits with `ControlFlow::ExitWithError`. The terminal is restored before this returns.
+    pub fn try_run_app<A, E>(mut self, mut app: A, col_encode: E) -> chargrid_app::Result<()>
+    where
+        A: App + 'static,
+        E: ColEncode,
+    {
+        let mut last_frame: Option<Instant> = None;
+        let mut render_requested = true;
         loop {
-            let frame_start = Instant::now();
-            for input in self.drain_input().unwrap() {
-                if let Some(ControlFlow::Exit) = app.on_input(input) {
-                    return;
+            let now = Instant::now();
+            let frame_due = last_frame.map_or(now, |last_frame| last_frame + self.frame_duration);
+            let timeout = if self.idle_mode && !render_requested {
+                // gamepads can't be waited on, so they are polled once per frame period
+                if cfg!(feature = "gamepad") {
+                    Some(self.frame_duration)
+                } else {
+                    None
+                }
+            } else {
+                Some(frame_due.saturating_duration_since(now))
+            };
+            if self.terminal.wait(timeout)? {
+                render_requested = true;
+            }
+            for input in self.drain_input()? {
+                render_requested = true;
+                if let Some(control_flow) = app.on_input(input) {
+                    return control_flow.into_result();
                 }
             }
             #[cfg(feature = "gamepad")]
             for input in self.gamepad.drain_input() {
-                if let Some(ControlFlow::Exit) = app.on_input(chargrid_input::Input::Gamepad(input))
-                {
-                    return;
+                render_requested = true;
+                if let Some(control_flow) = app.on_input(chargrid_input::Input::Gamepad(input)) {
+                    return control_flow.into_result();
                 }
             }
-            self.resize_if_necessary().unwrap();
+            if self.terminal.redraw_pending() {
+                render_requested = true;
+            }
+            if let Some(size) = self.resize_if_necessary()? {
+                render_requested = true;
+                if let Some(control_flow) = app.on_input(chargrid_input::Input::Resize(size)) {
+                    return control_flow.into_result();
+                }
+            }
+            let now = Instant::now();
+            if now < frame_due || (self.idle_mode && !render_requested) {
+                continue;
+            }
+            let since_last_frame = last_frame.map_or(Duration::from_secs(0), |last_frame| {
+                now.saturating_duration_since(last_frame)
+            });
+            last_frame = Some(now);
+            render_requested = false;
             self.buffer.clear();
-            let view_context = ViewContext::default_with_size(self.size().unwrap());
-            if let Some(ControlFlow::Exit) =
-                app.on_frame(FRAME_DURATION, view_context, &mut self.buffer)
+            let view_context = ViewContext::default_with_size(self.buffer.size());
+            if let Some(control_flow) =
+                app.on_frame(since_last_frame, view_context, &mut self.buffer)
             {
-                return;
+                return control_flow.into_result();
             }
-            self.terminal.draw_frame::<E>(&mut self.buffer).unwrap();
-            let since_frame_start = frame_start.elapsed();
-            if let Some(until_next_frame) = FRAME_DURATION.checked_sub(since_frame_start) {
-                thread::sleep(until_next_frame);
+            self.terminal.draw_frame(&mut self.buffer, &col_encode)?;
+            if let Some(text) = self.clipboard.take_text() {
+                self.terminal.set_clipboard(&text)?;
             }
         }
     }
@@ -86,3 +376,111 @@ impl Context {
         self.terminal.size()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chargrid_app::{ColModify, ControlFlow, Frame};
+    use chargrid_input::{Input, KeyboardInput};
+    use std::io::Read;
+    use std::os::unix::net::UnixStream;
+
+    // the response to the primary device attributes query which ends initialisation
+    const DEVICE_ATTRIBUTES: &[u8] = b"\x1b[?62c";
+
+    /// Draws the characters typed so far, and resizes the terminal once "hi" has been typed
+    struct Echo {
+        size: StreamSize,
+        text: String,
+        resized_to: Option<Size>,
+    }
+
+    impl App for Echo {
+        fn on_input(&mut self, input: Input) -> Option<ControlFlow> {
+            match input {
+                Input::Keyboard(KeyboardInput::Char(ch)) => self.text.push(ch),
+                Input::Resize(size) => self.resized_to = Some(size),
+                _ => (),
+            }
+            None
+        }
+        fn on_frame<F, C>(
+            &mut self,
+            _since_last_frame: Duration,
+            view_context: ViewContext<C>,
+            frame: &mut F,
+        ) -> Option<ControlFlow>
+        where
+            F: Frame,
+            C: ColModify,
+        {
+            if self.resized_to.is_some() {
+                return Some(ControlFlow::Exit);
+            }
+            for (x, ch) in self.text.chars().enumerate() {
+                let view_cell = ViewCell::new().with_character(ch);
+                frame.set_cell_relative(Coord::new(x as i32, 0), 0, view_cell, view_context);
+            }
+            if self.text == "hi" {
+                self.size.set(Size::new(20, 4));
+            }
+            None
+        }
+    }
+
+    fn context_with_streams(input: UnixStream, output: UnixStream, size: StreamSize) -> Context {
+        let streams = Streams {
+            input,
+            output,
+            size,
+            term: None,
+        };
+        Terminal::new_with_streams_for_tests(streams, &Config::default())
+            .and_then(Context::from_terminal)
+            .unwrap()
+    }
+
+    #[test]
+    fn run_over_streams() {
+        let (input, mut terminal_input) = UnixStream::pair().unwrap();
+        let (output, mut terminal_output) = UnixStream::pair().unwrap();
+        terminal_input.write_all(DEVICE_ATTRIBUTES).unwrap();
+        terminal_input.write_all(b"hi").unwrap();
+        let size = StreamSize::new(Size::new(10, 2));
+        let context = context_with_streams(input, output, size.clone());
+        assert_eq!(context.size().unwrap(), Size::new(10, 2));
+        let app = Echo {
+            size: size.clone(),
+            text: String::new(),
+            resized_to: None,
+        };
+        let col_encode = context.col_encode();
+        context.try_run_app(app, col_encode).unwrap();
+        assert_eq!(size.get(), Size::new(20, 4));
+        let mut sent = String::new();
+        terminal_output.read_to_string(&mut sent).unwrap();
+        // queries are sent during initialisation
+        assert!(sent.contains("\x1b[c"));
+        assert!(sent.contains("hi"));
+        // the cursor is shown again when the context is dropped
+        assert!(sent.ends_with("\x1b[?12l\x1b[?25h\x1b(B\x1b[m"));
+    }
+
+    #[test]
+    fn input_stream_closed() {
+        let (input, mut terminal_input) = UnixStream::pair().unwrap();
+        let (output, _terminal_output) = UnixStream::pair().unwrap();
+        terminal_input.write_all(DEVICE_ATTRIBUTES).unwrap();
+        let size = StreamSize::new(Size::new(10, 2));
+        let context = context_with_streams(input, output, size.clone());
+        drop(terminal_input);
+        let app = Echo {
+            size,
+            text: String::new(),
+            resized_to: None,
+        };
+        let col_encode = context.col_encode();
+        let result = context.try_run_app(app, col_encode);
+        assert!(matches!(result, Err(chargrid_app::Error::Frontend(_))));
+    }
+}
@@ -1,3 +1,5 @@
+use std::error;
+use std::fmt;
 use std::io::Error as IoError;
 use std::str::Utf8Error;
 use term::terminfo::parm::Error as ParamError;
@@ -22,6 +24,42 @@ impl Error {
     }
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::IoError(e) => write!(f, "io error: {}", e),
+            Error::MissingCap(cap) => write!(f, "terminal lacks capability: {}", cap),
+            Error::UnrecognizedEscapeSequence(bytes) => {
+                write!(f, "unrecognized escape sequence: {:?}", bytes)
+            }
+            Error::Utf8Error(e) => write!(f, "invalid utf-8: {}", e),
+            Error::NoSuchMenuPlace(place) => write!(f, "no such menu place: {}", place),
+            Error::TermError(e) => write!(f, "terminfo error: {}", e),
+            Error::ParamError(e) => write!(f, "terminfo parameter error: {}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::IoError(e) => Some(e),
+            Error::Utf8Error(e) => Some(e),
+            Error::TermError(e) => Some(e),
+            Error::ParamError(e) => Some(e),
+            Error::MissingCap(_)
+            | Error::UnrecognizedEscapeSequence(_)
+            | Error::NoSuchMenuPlace(_) => None,
+        }
+    }
+}
+
+impl From<Error> for chargrid_app::Error {
+    fn from(e: Error) -> Self {
+        chargrid_app::Error::frontend(e)
+    }
+}
+
 impl From<IoError> for Error {
     fn from(e: IoError) -> Self {
         Error::IoError(e)
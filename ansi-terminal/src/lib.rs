@@ -6,6 +6,10 @@ pub use self::context::*;
 pub use self::error::*;
 pub use self::terminal::col_encode;
 pub use self::terminal::ColEncode;
+pub use self::terminal::{
+    Charset, ImageProtocol, StreamSize, Streams, TermInfoOverrides, TerminalColours,
+    Transliteration,
+};
 pub use chargrid_input;
 pub use chargrid_render;
 pub use chargrid_render::{Coord, Rgb24, Size};
@@ -1,4 +1,10 @@
+//! Choosing the colour in a terminal's palette which looks most similar to a given colour.
+//! Colours are compared in the OKLab colour space, in which distances correspond to how different
+//! colours look.
+
 use chargrid_render::Rgb24;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::OnceLock;
 
 const RGB_START: u8 = 16;
 const RGB_MAX_FIELD: u8 = 5;
@@ -7,84 +13,175 @@ const RGB_COUNT: u8 = RGB_FIELD_RANGE * RGB_FIELD_RANGE * RGB_FIELD_RANGE;
 const RGB_END: u8 = RGB_START + RGB_COUNT - 1;
 const GREY_SCALE_START: u8 = RGB_END + 1;
 const GREY_SCALE_MAX_LEVEL: u8 = 23;
+// levels of each channel in the colour cube, as used by xterm and most other terminals
+const CUBE_LEVELS: [u8; RGB_FIELD_RANGE as usize] = [0, 95, 135, 175, 215, 255];
+// xterm's default values for the 16 ansi colours. Most terminals let users change these, so they
+// are only a rough guide.
+const ANSI_COLOURS: [Rgb24; 16] = [
+    Rgb24::new(0, 0, 0),
+    Rgb24::new(205, 0, 0),
+    Rgb24::new(0, 205, 0),
+    Rgb24::new(205, 205, 0),
+    Rgb24::new(0, 0, 238),
+    Rgb24::new(205, 0, 205),
+    Rgb24::new(0, 205, 205),
+    Rgb24::new(229, 229, 229),
+    Rgb24::new(127, 127, 127),
+    Rgb24::new(255, 0, 0),
+    Rgb24::new(0, 255, 0),
+    Rgb24::new(255, 255, 0),
+    Rgb24::new(92, 92, 255),
+    Rgb24::new(255, 0, 255),
+    Rgb24::new(0, 255, 255),
+    Rgb24::new(255, 255, 255),
+];
+// The cache of nearest colours is indexed by each channel rounded to one of this many levels
+const CACHE_LEVELS: usize = 64;
+const CACHE_MAX_LEVEL: usize = CACHE_LEVELS - 1;
+const NOT_CACHED: u16 = u16::MAX;
 
-pub fn nearest_palette_code(Rgb24 { r, g, b }: Rgb24) -> u8 {
-    let r = ((r as u16 * RGB_MAX_FIELD as u16) / 255) as u8;
-    let g = ((g as u16 * RGB_MAX_FIELD as u16) / 255) as u8;
-    let b = ((b as u16 * RGB_MAX_FIELD as u16) / 255) as u8;
-    RGB_START + (RGB_FIELD_RANGE * RGB_FIELD_RANGE) * r + RGB_FIELD_RANGE * g + b
+#[derive(Debug, Clone, Copy)]
+struct OkLab {
+    l: f64,
+    a: f64,
+    b: f64,
 }
 
-pub fn nearest_mean_greyscale_code(Rgb24 { r, g, b }: Rgb24) -> u8 {
-    let offset = (r as u16 + g as u16 + b as u16) / (GREY_SCALE_MAX_LEVEL as u16 * 3);
-    GREY_SCALE_START + offset as u8
+fn srgb_to_linear(channel: u8) -> f64 {
+    let channel = channel as f64 / 255.;
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
 }
 
-pub fn nearest_ansi_code(Rgb24 { r, g, b }: Rgb24) -> u8 {
-    mod codes {
-        pub mod normal {
-            pub const BLACK: u8 = 0;
-            pub const RED: u8 = 1;
-            pub const GREEN: u8 = 2;
-            pub const YELLOW: u8 = 3;
-            pub const BLUE: u8 = 4;
-            pub const MAGENTA: u8 = 5;
-            pub const CYAN: u8 = 6;
-            pub const GREY: u8 = 7;
-        }
-        pub mod bright {
-            pub const DARK_GREY: u8 = 8;
-            pub const RED: u8 = 9;
-            pub const GREEN: u8 = 10;
-            pub const YELLOW: u8 = 11;
-            pub const BLUE: u8 = 12;
-            pub const MAGENTA: u8 = 13;
-            pub const CYAN: u8 = 14;
-            pub const WHITE: u8 = 15;
+impl OkLab {
+    fn from_rgb24(Rgb24 { r, g, b }: Rgb24) -> Self {
+        let (r, g, b) = (srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b));
+        let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+        let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+        let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+        Self {
+            l: 0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+            a: 1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+            b: 0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
         }
     }
-    const THIRD: u8 = 255 / 3;
-    macro_rules! lo {
-        () => {
-            0..=1
-        };
+
+    fn distance_squared(self, other: Self) -> f64 {
+        let l = self.l - other.l;
+        let a = self.a - other.a;
+        let b = self.b - other.b;
+        l * l + a * a + b * b
     }
-    macro_rules! hi {
-        () => {
-            2..=3
-        };
+}
+
+/// A set of colours along with a cache of the colour nearest to each region of the rgb cube,
+/// which is filled in as colours are looked up
+struct Palette {
+    colours: Vec<(u8, OkLab)>,
+    cache: Vec<AtomicU16>,
+}
+
+impl Palette {
+    fn new<I>(colours: I) -> Self
+    where
+        I: IntoIterator<Item = (u8, Rgb24)>,
+    {
+        let colours = colours
+            .into_iter()
+            .map(|(code, rgb24)| (code, OkLab::from_rgb24(rgb24)))
+            .collect();
+        let cache = (0..(CACHE_LEVELS * CACHE_LEVELS * CACHE_LEVELS))
+            .map(|_| AtomicU16::new(NOT_CACHED))
+            .collect();
+        Self { colours, cache }
     }
-    match (r / THIRD, g / THIRD, b / THIRD) {
-        (0, 0, 0) => codes::normal::BLACK,
-        (1, 0, 0) => codes::normal::RED,
-        (0, 1, 0) => codes::normal::GREEN,
-        (0, 0, 1) => codes::normal::BLUE,
-        (1, 1, 0) => codes::normal::YELLOW,
-        (0, 1, 1) => codes::normal::CYAN,
-        (1, 0, 1) => codes::normal::MAGENTA,
-        (1, 1, 1) => codes::bright::DARK_GREY,
-        col @ (hi!(), hi!(), hi!()) => {
-            if col == (2, 2, 2) {
-                // despite not being "bright", this tends to be brighter than dark grey
-                codes::normal::GREY
-            } else {
-                codes::bright::WHITE
-            }
+
+    fn nearest_code(&self, Rgb24 { r, g, b }: Rgb24) -> u8 {
+        let level = |channel: u8| (channel as usize * CACHE_MAX_LEVEL + 127) / 255;
+        let (r, g, b) = (level(r), level(g), level(b));
+        let index = (r * CACHE_LEVELS + g) * CACHE_LEVELS + b;
+        let cached = self.cache[index].load(Ordering::Relaxed);
+        if cached != NOT_CACHED {
+            return cached as u8;
         }
-        (hi!(), lo!(), lo!()) => codes::bright::RED,
-        (lo!(), hi!(), lo!()) => codes::bright::GREEN,
-        (lo!(), lo!(), hi!()) => codes::bright::BLUE,
-        (hi!(), hi!(), lo!()) => codes::bright::YELLOW,
-        (lo!(), hi!(), hi!()) => codes::bright::CYAN,
-        (hi!(), lo!(), hi!()) => codes::bright::MAGENTA,
-        _ => unreachable!(),
+        // Match the colour the cache entry's levels stand for, so the result doesn't depend on
+        // which of the colours sharing the entry was looked up first.
+        let channel = |level: usize| ((level * 255 + CACHE_MAX_LEVEL / 2) / CACHE_MAX_LEVEL) as u8;
+        let target = OkLab::from_rgb24(Rgb24::new(channel(r), channel(g), channel(b)));
+        let (code, _) = self
+            .colours
+            .iter()
+            .map(|&(code, colour)| (code, colour.distance_squared(target)))
+            .fold((0, f64::INFINITY), |nearest, candidate| {
+                if candidate.1 < nearest.1 {
+                    candidate
+                } else {
+                    nearest
+                }
+            });
+        self.cache[index].store(code as u16, Ordering::Relaxed);
+        code
     }
 }
 
+pub fn nearest_palette_code(rgb24: Rgb24) -> u8 {
+    static PALETTE: OnceLock<Palette> = OnceLock::new();
+    PALETTE
+        .get_or_init(|| {
+            // the first 16 colours are omitted as their values vary between terminals
+            let cube = (0..RGB_COUNT).map(|i| {
+                let r = CUBE_LEVELS[(i / (RGB_FIELD_RANGE * RGB_FIELD_RANGE)) as usize];
+                let g = CUBE_LEVELS[((i / RGB_FIELD_RANGE) % RGB_FIELD_RANGE) as usize];
+                let b = CUBE_LEVELS[(i % RGB_FIELD_RANGE) as usize];
+                (RGB_START + i, Rgb24::new(r, g, b))
+            });
+            let greys = (0..=GREY_SCALE_MAX_LEVEL).map(|i| {
+                let level = 8 + 10 * i;
+                (GREY_SCALE_START + i, Rgb24::new(level, level, level))
+            });
+            Palette::new(cube.chain(greys))
+        })
+        .nearest_code(rgb24)
+}
+
+pub fn nearest_mean_greyscale_code(Rgb24 { r, g, b }: Rgb24) -> u8 {
+    let offset = (r as u16 + g as u16 + b as u16) / (GREY_SCALE_MAX_LEVEL as u16 * 3);
+    GREY_SCALE_START + offset as u8
+}
+
+pub fn nearest_ansi_code(rgb24: Rgb24) -> u8 {
+    static PALETTE: OnceLock<Palette> = OnceLock::new();
+    PALETTE
+        .get_or_init(|| Palette::new((0..).zip(ANSI_COLOURS.iter().cloned())))
+        .nearest_code(rgb24)
+}
+
 #[cfg(test)]
 mod tests {
     use chargrid_render::Rgb24;
 
+    #[test]
+    fn nearest_palette_code_exact_matches() {
+        assert_eq!(super::nearest_palette_code(Rgb24::new(0, 0, 0)), 16);
+        assert_eq!(super::nearest_palette_code(Rgb24::new(255, 255, 255)), 231);
+        assert_eq!(super::nearest_palette_code(Rgb24::new(95, 135, 215)), 68);
+        // greys are matched to the greyscale ramp, which is finer than the colour cube
+        assert_eq!(super::nearest_palette_code(Rgb24::new(128, 128, 128)), 244);
+        assert_eq!(super::nearest_palette_code(Rgb24::new(18, 18, 18)), 233);
+    }
+
+    #[test]
+    fn nearest_ansi_code_exact_matches() {
+        assert_eq!(super::nearest_ansi_code(Rgb24::new(0, 0, 0)), 0);
+        assert_eq!(super::nearest_ansi_code(Rgb24::new(205, 0, 0)), 1);
+        assert_eq!(super::nearest_ansi_code(Rgb24::new(255, 0, 0)), 9);
+        assert_eq!(super::nearest_ansi_code(Rgb24::new(255, 255, 255)), 15);
+        assert_eq!(super::nearest_ansi_code(Rgb24::new(120, 120, 120)), 8);
+    }
+
     #[test]
     fn nearest_palette_code_all_cases() {
         for r in 0..=255 {
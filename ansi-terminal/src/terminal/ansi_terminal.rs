@@ -1,24 +1,87 @@
 use super::byte_prefix_tree::{BytePrefixTree, Found};
+use super::charset::Charset;
+use super::clipboard;
+use super::colour_support::{self, ColourSupport};
+use super::graphics::{self, ImageProtocol};
+use super::kitty_keyboard;
 use super::low_level::LowLevel;
-use super::term_info_cache::{MousePrefix, TermInfoCache, TerminalInput};
+use super::output::Output;
+use super::report::{take_reports, Report};
+use super::restore;
+use super::sgr_mouse;
+use super::stream::Streams;
+use super::term_info_cache::{self, MousePrefix, TermInfoCache, TerminalInput};
+use super::terminal_colours::{self, TerminalColours};
+use crate::context::Config;
 use crate::error::Result;
 use chargrid_input::*;
-use chargrid_render::*;
+use signal_hook::consts::{SIGCONT, SIGTSTP, SIGWINCH};
+use signal_hook::SigId;
 use std::collections::{vec_deque, VecDeque};
-use term::terminfo::parm::{self, Param};
+use std::io::{self, Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 const OUTPUT_BUFFER_INITIAL_CAPACITY: usize = 32 * 1024;
 const INPUT_BUFFER_INITIAL_CAPACITY: usize = 32;
 const INPUT_RING_INITIAL_CAPACITY: usize = 32;
+const ESCAPE: char = '\u{1b}';
+// sent by ctrl+z
+const SUSPEND: char = '\u{1a}';
+// Characters which begin escape sequences, so aren't treated as alt+<char> when following escape
+const ALT_AMBIGUOUS: &[char] = &['[', 'O'];
+const SYNCHRONIZED_OUTPUT_MODE: u32 = 2026;
+const QUERY_SYNCHRONIZED_OUTPUT: &str = "\x1b[?2026$p";
+const QUERY_PRIMARY_DEVICE_ATTRIBUTES: &str = "\x1b[c";
+const QUERY_CURSOR_POSITION: &str = "\x1b[6n";
+const ENABLE_FOCUS_REPORTING: &str = "\x1b[?1004h";
+const DISABLE_FOCUS_REPORTING: &str = "\x1b[?1004l";
+/// Return the cursor to the terminal's default shape (DECSCUSR 0)
+const RESET_CURSOR_SHAPE: &str = "\x1b[0 q";
+// how long to wait for the terminal to respond to queries
+const QUERY_TIMEOUT: Duration = Duration::from_secs(1);
 
 pub type DrainInput<'a> = vec_deque::Drain<'a, Input>;
 
+/// The rows of the terminal drawn to in inline mode
+#[derive(Debug, Clone, Copy)]
+struct InlineRegion {
+    first_row: u32,
+    height: u32,
+}
+
 pub struct AnsiTerminal {
     low_level: LowLevel,
     output_buffer: String,
     input_buffer: Vec<u8>,
     ti_cache: TermInfoCache,
     input_ring: VecDeque<Input>,
+    styled_underlines: bool,
+    synchronized_output: bool,
+    kitty_keyboard: bool,
+    image_protocol: Option<ImageProtocol>,
+    terminal_colours: TerminalColours,
+    charset: Charset,
+    cell_pixel_size: Size,
+    mouse_pixel_coordinates: bool,
+    resized: Arc<AtomicBool>,
+    suspend_requested: Arc<AtomicBool>,
+    continued: Arc<AtomicBool>,
+    ctrl_z_as_input: bool,
+    redraw: bool,
+    // Bytes are written to `wake_sender` when a signal arrives or a wakeup is requested, to end
+    // waits for input early
+    wake_receiver: UnixStream,
+    wake_sender: UnixStream,
+    signals: Vec<SigId>,
+    inline_region: Option<InlineRegion>,
+    col_encode: col_encode::Dynamic,
+    // The length of the input buffer when more of a paste whose end hasn't arrived was last
+    // received, and when that was
+    incomplete_paste: Option<(usize, Instant)>,
 }
 
 pub mod col_encode {
@@ -29,53 +92,148 @@ pub mod col_encode {
     use chargrid_render::Rgb24;
 
     pub trait Trait: Clone {
-        fn encode_foreground(buffer: &mut String, rgb24: Rgb24, term_info_cache: &TermInfoCache);
-        fn encode_background(buffer: &mut String, rgb24: Rgb24, term_info_cache: &TermInfoCache);
+        /// The distance between neighbouring levels of each channel in the palette this encoding
+        /// chooses colours from, which determines how much to dither colours by when dithering is
+        /// enabled. Encodings which can represent all colours return `None`.
+        fn dither_spread(&self) -> Option<u8> {
+            None
+        }
+        fn encode_foreground(
+            &self,
+            buffer: &mut String,
+            rgb24: Rgb24,
+            term_info_cache: &TermInfoCache,
+        );
+        fn encode_background(
+            &self,
+            buffer: &mut String,
+            rgb24: Rgb24,
+            term_info_cache: &TermInfoCache,
+        );
+        /// Set the colour of styled underlines. Encodings which can't represent the colour send
+        /// nothing, leaving underlines in the foreground colour.
+        fn encode_underline(
+            &self,
+            _buffer: &mut String,
+            _rgb24: Rgb24,
+            _term_info_cache: &TermInfoCache,
+        ) {
+        }
+    }
+
+    // Underline colours have no terminfo capability, so palette colours are selected directly
+    fn encode_underline_palette_code(buffer: &mut String, code: u8) {
+        buffer.push_str(&format!("\x1B[58:5:{}m", code));
     }
 
     #[derive(Clone, Copy)]
     pub struct FromTermInfoRgb;
     impl Trait for FromTermInfoRgb {
-        fn encode_foreground(buffer: &mut String, rgb24: Rgb24, term_info_cache: &TermInfoCache) {
+        fn dither_spread(&self) -> Option<u8> {
+            Some(40)
+        }
+        fn encode_foreground(
+            &self,
+            buffer: &mut String,
+            rgb24: Rgb24,
+            term_info_cache: &TermInfoCache,
+        ) {
             buffer.push_str(term_info_cache.fg_colour(nearest_palette_code(rgb24)));
         }
-        fn encode_background(buffer: &mut String, rgb24: Rgb24, term_info_cache: &TermInfoCache) {
+        fn encode_background(
+            &self,
+            buffer: &mut String,
+            rgb24: Rgb24,
+            term_info_cache: &TermInfoCache,
+        ) {
             buffer.push_str(term_info_cache.bg_colour(nearest_palette_code(rgb24)));
         }
+        fn encode_underline(
+            &self,
+            buffer: &mut String,
+            rgb24: Rgb24,
+            _term_info_cache: &TermInfoCache,
+        ) {
+            encode_underline_palette_code(buffer, nearest_palette_code(rgb24));
+        }
     }
 
     #[derive(Clone, Copy)]
     pub struct FromTermInfoGreyscale;
     impl Trait for FromTermInfoGreyscale {
-        fn encode_foreground(buffer: &mut String, rgb24: Rgb24, term_info_cache: &TermInfoCache) {
+        fn dither_spread(&self) -> Option<u8> {
+            Some(23)
+        }
+        fn encode_foreground(
+            &self,
+            buffer: &mut String,
+            rgb24: Rgb24,
+            term_info_cache: &TermInfoCache,
+        ) {
             buffer.push_str(term_info_cache.fg_colour(nearest_mean_greyscale_code(rgb24)));
         }
-        fn encode_background(buffer: &mut String, rgb24: Rgb24, term_info_cache: &TermInfoCache) {
+        fn encode_background(
+            &self,
+            buffer: &mut String,
+            rgb24: Rgb24,
+            term_info_cache: &TermInfoCache,
+        ) {
             buffer.push_str(term_info_cache.bg_colour(nearest_mean_greyscale_code(rgb24)));
         }
+        fn encode_underline(
+            &self,
+            buffer: &mut String,
+            rgb24: Rgb24,
+            _term_info_cache: &TermInfoCache,
+        ) {
+            encode_underline_palette_code(buffer, nearest_mean_greyscale_code(rgb24));
+        }
     }
 
     #[derive(Clone, Copy)]
     pub struct FromTermInfoAnsi16Colour;
     impl Trait for FromTermInfoAnsi16Colour {
-        fn encode_foreground(buffer: &mut String, rgb24: Rgb24, term_info_cache: &TermInfoCache) {
+        fn dither_spread(&self) -> Option<u8> {
+            Some(128)
+        }
+        fn encode_foreground(
+            &self,
+            buffer: &mut String,
+            rgb24: Rgb24,
+            term_info_cache: &TermInfoCache,
+        ) {
             buffer.push_str(term_info_cache.fg_colour(nearest_ansi_code(rgb24)));
         }
-        fn encode_background(buffer: &mut String, rgb24: Rgb24, term_info_cache: &TermInfoCache) {
+        fn encode_background(
+            &self,
+            buffer: &mut String,
+            rgb24: Rgb24,
+            term_info_cache: &TermInfoCache,
+        ) {
             buffer.push_str(term_info_cache.bg_colour(nearest_ansi_code(rgb24)));
         }
+        fn encode_underline(
+            &self,
+            buffer: &mut String,
+            rgb24: Rgb24,
+            _term_info_cache: &TermInfoCache,
+        ) {
+            encode_underline_palette_code(buffer, nearest_ansi_code(rgb24));
+        }
     }
 
     #[derive(Clone, Copy)]
     pub struct NoColour;
     impl Trait for NoColour {
         fn encode_foreground(
+            &self,
             _buffer: &mut String,
             _rgb24: Rgb24,
             _term_info_cache: &TermInfoCache,
         ) {
         }
         fn encode_background(
+            &self,
             _buffer: &mut String,
             _rgb24: Rgb24,
             _term_info_cache: &TermInfoCache,
@@ -87,6 +245,7 @@ pub mod col_encode {
     pub struct XtermTrueColour;
     impl Trait for XtermTrueColour {
         fn encode_foreground(
+            &self,
             buffer: &mut String,
             Rgb24 { r, g, b }: Rgb24,
             _term_info_cache: &TermInfoCache,
@@ -94,125 +253,699 @@ pub mod col_encode {
             buffer.push_str(&format!("\x1B[38;2;{};{};{}m", r, g, b));
         }
         fn encode_background(
+            &self,
             buffer: &mut String,
             Rgb24 { r, g, b }: Rgb24,
             _term_info_cache: &TermInfoCache,
         ) {
             buffer.push_str(&format!("\x1B[48;2;{};{};{}m", r, g, b));
         }
+        fn encode_underline(
+            &self,
+            buffer: &mut String,
+            Rgb24 { r, g, b }: Rgb24,
+            _term_info_cache: &TermInfoCache,
+        ) {
+            buffer.push_str(&format!("\x1B[58:2::{}:{}:{}m", r, g, b));
+        }
+    }
+
+    /// An encoding chosen at runtime. `Context::col_encode` returns the best encoding supported
+    /// by the current terminal.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Dynamic {
+        XtermTrueColour,
+        FromTermInfoRgb,
+        FromTermInfoAnsi16Colour,
+        FromTermInfoGreyscale,
+        NoColour,
+    }
+    impl Trait for Dynamic {
+        fn dither_spread(&self) -> Option<u8> {
+            match self {
+                Dynamic::XtermTrueColour => XtermTrueColour.dither_spread(),
+                Dynamic::FromTermInfoRgb => FromTermInfoRgb.dither_spread(),
+                Dynamic::FromTermInfoAnsi16Colour => FromTermInfoAnsi16Colour.dither_spread(),
+                Dynamic::FromTermInfoGreyscale => FromTermInfoGreyscale.dither_spread(),
+                Dynamic::NoColour => NoColour.dither_spread(),
+            }
+        }
+        fn encode_foreground(
+            &self,
+            buffer: &mut String,
+            rgb24: Rgb24,
+            term_info_cache: &TermInfoCache,
+        ) {
+            match self {
+                Dynamic::XtermTrueColour => {
+                    XtermTrueColour.encode_foreground(buffer, rgb24, term_info_cache)
+                }
+                Dynamic::FromTermInfoRgb => {
+                    FromTermInfoRgb.encode_foreground(buffer, rgb24, term_info_cache)
+                }
+                Dynamic::FromTermInfoAnsi16Colour => {
+                    FromTermInfoAnsi16Colour.encode_foreground(buffer, rgb24, term_info_cache)
+                }
+                Dynamic::FromTermInfoGreyscale => {
+                    FromTermInfoGreyscale.encode_foreground(buffer, rgb24, term_info_cache)
+                }
+                Dynamic::NoColour => NoColour.encode_foreground(buffer, rgb24, term_info_cache),
+            }
+        }
+        fn encode_background(
+            &self,
+            buffer: &mut String,
+            rgb24: Rgb24,
+            term_info_cache: &TermInfoCache,
+        ) {
+            match self {
+                Dynamic::XtermTrueColour => {
+                    XtermTrueColour.encode_background(buffer, rgb24, term_info_cache)
+                }
+                Dynamic::FromTermInfoRgb => {
+                    FromTermInfoRgb.encode_background(buffer, rgb24, term_info_cache)
+                }
+                Dynamic::FromTermInfoAnsi16Colour => {
+                    FromTermInfoAnsi16Colour.encode_background(buffer, rgb24, term_info_cache)
+                }
+                Dynamic::FromTermInfoGreyscale => {
+                    FromTermInfoGreyscale.encode_background(buffer, rgb24, term_info_cache)
+                }
+                Dynamic::NoColour => NoColour.encode_background(buffer, rgb24, term_info_cache),
+            }
+        }
+        fn encode_underline(
+            &self,
+            buffer: &mut String,
+            rgb24: Rgb24,
+            term_info_cache: &TermInfoCache,
+        ) {
+            match self {
+                Dynamic::XtermTrueColour => {
+                    XtermTrueColour.encode_underline(buffer, rgb24, term_info_cache)
+                }
+                Dynamic::FromTermInfoRgb => {
+                    FromTermInfoRgb.encode_underline(buffer, rgb24, term_info_cache)
+                }
+                Dynamic::FromTermInfoAnsi16Colour => {
+                    FromTermInfoAnsi16Colour.encode_underline(buffer, rgb24, term_info_cache)
+                }
+                Dynamic::FromTermInfoGreyscale => {
+                    FromTermInfoGreyscale.encode_underline(buffer, rgb24, term_info_cache)
+                }
+                Dynamic::NoColour => NoColour.encode_underline(buffer, rgb24, term_info_cache),
+            }
+        }
     }
 }
 
 pub use self::col_encode::Trait as ColEncode;
 
 impl AnsiTerminal {
-    pub fn new() -> Result<Self> {
-        let low_level = LowLevel::new()?;
+    pub fn new(config: &Config) -> Result<Self> {
+        let ti_cache = TermInfoCache::new(&config.term_info_overrides)?;
+        Self::from_low_level(LowLevel::new()?, ti_cache, config)
+    }
+
+    /// Connect to a terminal over a pair of streams. Signals aren't handled, as they concern the
+    /// process's own terminal, so ctrl+z is always reported as input.
+    pub fn new_with_streams<R, W>(streams: Streams<R, W>, config: &Config) -> Result<Self>
+    where
+        R: Read + Send + 'static,
+        W: Write + 'static,
+    {
+        let ti_cache = match streams.term.as_ref() {
+            Some(term) => TermInfoCache::from_name(term, &config.term_info_overrides)?,
+            None => TermInfoCache::new(&config.term_info_overrides)?,
+        };
+        let low_level = LowLevel::from_streams(streams.input, streams.output, streams.size)?;
+        Self::from_low_level(low_level, ti_cache, config)
+    }
+
+    pub(super) fn from_low_level(
+        low_level: LowLevel,
+        ti_cache: TermInfoCache,
+        config: &Config,
+    ) -> Result<Self> {
         let output_buffer = String::with_capacity(OUTPUT_BUFFER_INITIAL_CAPACITY);
         let input_buffer = Vec::with_capacity(INPUT_BUFFER_INITIAL_CAPACITY);
-        let ti_cache = TermInfoCache::new()?;
         let input_ring = VecDeque::with_capacity(INPUT_RING_INITIAL_CAPACITY);
+        let styled_underlines = ti_cache.styled_underlines;
+        let resized = Arc::new(AtomicBool::new(false));
+        let suspend_requested = Arc::new(AtomicBool::new(false));
+        let continued = Arc::new(AtomicBool::new(false));
+        let (wake_receiver, wake_sender) = UnixStream::pair()?;
+        wake_receiver.set_nonblocking(true)?;
+        wake_sender.set_nonblocking(true)?;
+        let mut signals = Vec::new();
+        if let Some(size) = low_level.stream_size() {
+            size.listen(Arc::clone(&resized), wake_sender.try_clone()?);
+        } else {
+            signals.push(signal_hook::flag::register(SIGWINCH, Arc::clone(&resized))?);
+            signals.push(signal_hook::flag::register(
+                SIGTSTP,
+                Arc::clone(&suspend_requested),
+            )?);
+            signals.push(signal_hook::flag::register(
+                SIGCONT,
+                Arc::clone(&continued),
+            )?);
+            for &signal in &[SIGWINCH, SIGTSTP, SIGCONT] {
+                signals.push(signal_hook::low_level::pipe::register(
+                    signal,
+                    wake_sender.try_clone()?,
+                )?);
+            }
+        }
+        let ctrl_z_as_input = config.ctrl_z_as_input || low_level.tty().is_none();
+        // the locale describes the controlling tty, but not terminals connected by other streams
+        let charset = if low_level.tty().is_some() {
+            Charset::from_env(ti_cache.alternate_charset.is_some())
+        } else {
+            Charset::Unicode
+        };
         let mut terminal = Self {
             low_level,
             output_buffer,
             input_buffer,
             ti_cache,
             input_ring,
+            styled_underlines,
+            synchronized_output: false,
+            kitty_keyboard: false,
+            image_protocol: None,
+            terminal_colours: TerminalColours::default(),
+            charset,
+            cell_pixel_size: graphics::DEFAULT_CELL_PIXEL_SIZE,
+            mouse_pixel_coordinates: false,
+            resized,
+            suspend_requested,
+            continued,
+            ctrl_z_as_input,
+            redraw: false,
+            wake_receiver,
+            wake_sender,
+            signals,
+            inline_region: None,
+            col_encode: col_encode::Dynamic::NoColour,
+            incomplete_paste: None,
         };
-        terminal.init()?;
+        terminal.init(config)?;
         Ok(terminal)
     }
 
-    fn init(&mut self) -> Result<()> {
-        if let Some(enter_ca) = self.ti_cache.enter_ca.as_ref() {
+    fn init(&mut self, config: &Config) -> Result<()> {
+        let terminal_height = self.low_level.size()?.height();
+        let inline_height = config
+            .inline_height
+            .map(|height| height.max(1).min(terminal_height));
+        self.push_setup(inline_height);
+        self.flush_buffer()?;
+        // the environment describes the controlling tty, but not terminals connected by other
+        // streams, whose colours are found from terminfo and by asking the terminal
+        let env_colour_support = if self.low_level.tty().is_some() {
+            ColourSupport::from_env()
+        } else {
+            ColourSupport::default()
+        };
+        let mut colour_support = ColourSupport {
+            terminfo_colours: self.ti_cache.colours,
+            terminfo_rgb: self.ti_cache.rgb,
+            ..env_colour_support
+        };
+        let mut queries = QUERY_SYNCHRONIZED_OUTPUT.to_string();
+        queries.push_str(graphics::QUERY_KITTY_GRAPHICS);
+        queries.push_str(graphics::QUERY_CELL_PIXEL_SIZE);
+        queries.push_str(&terminal_colours::query());
+        if colour_support.should_query() {
+            queries.push_str(colour_support::QUERY_TRUE_COLOUR);
+        }
+        if !self.styled_underlines {
+            queries.push_str(term_info_cache::QUERY_STYLED_UNDERLINES);
+        }
+        if config.kitty_keyboard_protocol {
+            queries.push_str(kitty_keyboard::QUERY_FLAGS);
+        }
+        if config.mouse_pixel_coordinates {
+            queries.push_str(sgr_mouse::QUERY_PIXELS);
+        }
+        if inline_height.is_some() {
+            queries.push_str(QUERY_CURSOR_POSITION);
+        }
+        let reports = self.query(&queries)?;
+        colour_support.reported_true_colour = reports.contains(&Report::TrueColour);
+        self.col_encode = colour_support.col_encode();
+        self.styled_underlines |= reports.contains(&Report::StyledUnderlines);
+        if let Some(height) = inline_height {
+            self.place_inline_region(height, &reports)?;
+        }
+        self.synchronized_output = reports
+            .iter()
+            .any(|report| report.is_mode_supported(SYNCHRONIZED_OUTPUT_MODE));
+        self.terminal_colours = TerminalColours::from_reports(&reports);
+        self.image_protocol = if reports.contains(&Report::KittyGraphics) {
+            Some(ImageProtocol::Kitty)
+        } else if reports.contains(&Report::PrimaryDeviceAttributes { sixel: true }) {
+            Some(ImageProtocol::Sixel)
+        } else {
+            None
+        };
+        if let Some(size) = reports.iter().find_map(|report| match *report {
+            Report::CellPixelSize { width, height } => Some(Size::new(width, height)),
+            _ => None,
+        }) {
+            self.cell_pixel_size = size;
+        }
+        if config.kitty_keyboard_protocol {
+            self.kitty_keyboard = reports
+                .iter()
+                .any(|report| matches!(report, Report::KittyKeyboardFlags(_)));
+        }
+        if config.mouse_pixel_coordinates {
+            self.mouse_pixel_coordinates = reports
+                .iter()
+                .any(|report| report.is_mode_supported(sgr_mouse::PIXEL_MODE));
+        }
+        self.push_optional_modes();
+        self.flush_buffer()?;
+        self.update_panic_restore();
+        Ok(())
+    }
+
+    /// Prepare the screen for drawing, and enable input reporting
+    fn push_setup(&mut self, inline_height: Option<u32>) {
+        if let Some(height) = inline_height {
+            // scroll the terminal if there isn't enough room below the cursor for the region
+            self.output_buffer.push('\r');
+            for _ in 1..height {
+                self.output_buffer.push('\n');
+            }
+        } else if let Some(enter_ca) = self.ti_cache.enter_ca.as_ref() {
             self.output_buffer.push_str(enter_ca);
         }
         if let Some(enter_xmit) = self.ti_cache.enter_xmit.as_ref() {
             self.output_buffer.push_str(enter_xmit);
         }
+        if let Some(enable) = self
+            .ti_cache
+            .alternate_charset
+            .as_ref()
+            .and_then(|caps| caps.enable.as_ref())
+        {
+            self.output_buffer.push_str(enable);
+        }
         self.output_buffer.push_str(&self.ti_cache.hide_cursor);
-        self.output_buffer.push_str(&self.ti_cache.clear);
+        if inline_height.is_none() {
+            self.output_buffer.push_str(&self.ti_cache.clear);
+        }
         self.output_buffer
             .push_str(&self.ti_cache.enable_mouse_reporting);
-        self.flush_buffer().map_err(Into::into)
+        self.output_buffer
+            .push_str(clipboard::ENABLE_BRACKETED_PASTE);
+        self.output_buffer.push_str(ENABLE_FOCUS_REPORTING);
     }
 
-    fn teardown(&mut self) -> Result<()> {
+    /// Enable the modes which depend on the terminal's responses to queries
+    fn push_optional_modes(&mut self) {
+        if self.mouse_pixel_coordinates {
+            self.output_buffer.push_str(sgr_mouse::ENABLE_PIXELS);
+        }
+        if self.kitty_keyboard {
+            // the flags are pushed onto a stack belonging to the current screen, so when using
+            // the alternate screen they are discarded when the terminal leaves it, even if
+            // teardown doesn't run
+            self.output_buffer.push_str(&kitty_keyboard::push_flags());
+        }
+    }
+
+    /// Reserve `height` rows ending at the row containing the cursor, according to `reports`
+    fn place_inline_region(&mut self, height: u32, reports: &[Report]) -> Result<()> {
+        // the cursor is on the last row of the region
+        let last_row = reports
+            .iter()
+            .find_map(|report| match *report {
+                Report::CursorPosition { row, .. } => Some(row),
+                _ => None,
+            })
+            .unwrap_or(self.low_level.size()?.height() - 1);
+        self.inline_region = Some(InlineRegion {
+            first_row: (last_row + 1).saturating_sub(height),
+            height,
+        });
+        self.clear_inline_region()
+    }
+
+    /// Send `queries` to the terminal, returning its reports. A device attributes query is sent
+    /// after the other queries, as terminals which don't recognise a query may not respond to it
+    /// at all. Its response is the last report, unless the terminal didn't respond in time.
+    fn query(&mut self, queries: &str) -> Result<Vec<Report>> {
+        self.output_buffer.push_str(queries);
+        self.output_buffer.push_str(QUERY_PRIMARY_DEVICE_ATTRIBUTES);
+        self.flush_buffer()?;
+        let deadline = Instant::now() + QUERY_TIMEOUT;
+        let mut reports = Vec::new();
+        while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+            self.low_level
+                .read_timeout(&mut self.input_buffer, timeout)?;
+            for report in take_reports(&mut self.input_buffer) {
+                let done = matches!(report, Report::PrimaryDeviceAttributes { .. });
+                reports.push(report);
+                if done {
+                    return Ok(reports);
+                }
+            }
+        }
+        Ok(reports)
+    }
+
+    fn push_teardown(&mut self) -> Result<()> {
+        if self.kitty_keyboard {
+            self.output_buffer.push_str(kitty_keyboard::POP_FLAGS);
+        }
+        if self.mouse_pixel_coordinates {
+            self.output_buffer.push_str(sgr_mouse::DISABLE_PIXELS);
+        }
+        self.output_buffer.push_str(DISABLE_FOCUS_REPORTING);
+        self.output_buffer
+            .push_str(clipboard::DISABLE_BRACKETED_PASTE);
         self.output_buffer
             .push_str(&self.ti_cache.disable_mouse_reporting);
-        if let Some(exit_ca) = self.ti_cache.exit_ca.as_ref() {
+        if let Some(region) = self.inline_region {
+            // leave the final frame in place and continue below it
+            self.output_buffer.push_str(&self.ti_cache.reset);
+            let last_row = Coord::new(0, (region.height - 1) as i32);
+            let move_cursor = self.output().cursor_absolute(last_row)?;
+            self.output_buffer.push_str(&move_cursor);
+            self.output_buffer.push_str("\r\n");
+        } else if let Some(exit_ca) = self.ti_cache.exit_ca.as_ref() {
             self.output_buffer.push_str(exit_ca);
         }
         if let Some(exit_xmit) = self.ti_cache.exit_xmit.as_ref() {
             self.output_buffer.push_str(exit_xmit);
         }
+        self.output_buffer.push_str(RESET_CURSOR_SHAPE);
         self.output_buffer.push_str(&self.ti_cache.show_cursor);
         self.output_buffer.push_str(&self.ti_cache.reset);
-        self.flush_buffer().map_err(Into::into)
+        Ok(())
+    }
+
+    fn teardown(&mut self) -> Result<()> {
+        self.push_teardown()?;
+        self.flush_buffer()
     }
 
+    /// Give the panic hook the sequence which tears down the terminal in its current state. This
+    /// depends on the position of the inline region, so it's updated whenever the region moves.
+    fn update_panic_restore(&mut self) {
+        let (tty_fd, original_termios) = match self.low_level.tty() {
+            Some(tty) => tty,
+            None => return,
+        };
+        let start = self.output_buffer.len();
+        if self.push_teardown().is_ok() {
+            let teardown = self.output_buffer.split_off(start);
+            restore::set(tty_fd, original_termios, teardown);
+        } else {
+            self.output_buffer.truncate(start);
+        }
+    }
+
+    /// Restore the terminal and stop the process, as the default action of SIGTSTP would. When
+    /// the process is continued, the terminal is set up again and the next frame is redrawn from
+    /// scratch.
+    fn suspend(&mut self) -> Result<()> {
+        self.teardown()?;
+        self.low_level.leave_raw_mode()?;
+        restore::clear();
+        signal_hook::low_level::emulate_default_handler(SIGTSTP)?;
+        // SIGCONT was received, which would otherwise cause the terminal to be resumed again
+        self.continued.store(false, Ordering::Relaxed);
+        self.resume()
+    }
+
+    /// Set the terminal up again after the process was stopped, as the terminal's settings may
+    /// have been changed (e.g. by the shell) while it was stopped
+    fn resume(&mut self) -> Result<()> {
+        self.low_level.enter_raw_mode()?;
+        let inline_height = self.inline_height();
+        self.push_setup(inline_height);
+        if let Some(height) = inline_height {
+            // the shell may have printed below the region, so start a new region at the cursor
+            let height = height.min(self.low_level.size()?.height());
+            self.flush_buffer()?;
+            let reports = self.query(QUERY_CURSOR_POSITION)?;
+            self.place_inline_region(height, &reports)?;
+        }
+        self.push_optional_modes();
+        self.flush_buffer()?;
+        self.update_panic_restore();
+        self.redraw = true;
+        // the terminal may have been resized while the process was stopped
+        self.resized.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// The size of the area being drawn to. In inline mode this is the inline region.
     pub fn size(&self) -> Result<Size> {
-        self.low_level.size()
+        let size = self.low_level.size()?;
+        Ok(match self.inline_region {
+            Some(region) => Size::new(size.width(), region.height.min(size.height())),
+            None => size,
+        })
     }
 
-    pub fn set_cursor(&mut self, coord: Coord) -> Result<()> {
-        let params = &[Param::Number(coord.y), Param::Number(coord.x)];
-        let command = parm::expand(
-            self.ti_cache.set_cursor.as_bytes(),
-            params,
-            &mut self.ti_cache.vars,
-        )?;
-        let command_slice = ::std::str::from_utf8(&command)?;
-        self.output_buffer.push_str(command_slice);
+    /// The number of rows drawn to, if in inline mode
+    pub fn inline_height(&self) -> Option<u32> {
+        self.inline_region.map(|region| region.height)
+    }
+
+    /// Change the number of rows drawn to in inline mode. The region grows downwards, scrolling
+    /// the terminal if there isn't enough room below it, and rows removed from the bottom of the
+    /// region are cleared. The region is cleared, so everything must be redrawn.
+    pub fn set_inline_height(&mut self, height: u32) -> Result<()> {
+        let terminal_height = self.low_level.size()?.height();
+        let height = height.max(1).min(terminal_height);
+        let mut region = match self.inline_region {
+            Some(region) => region,
+            None => return Ok(()),
+        };
+        if height > region.height {
+            let last_row = Coord::new(0, (region.height - 1) as i32);
+            let move_cursor = self.output().cursor_absolute(last_row)?;
+            self.output_buffer.push_str(&move_cursor);
+            for _ in region.height..height {
+                self.output_buffer.push('\n');
+            }
+            region.first_row = region.first_row.min(terminal_height - height);
+        }
+        region.height = height;
+        self.inline_region = Some(region);
+        self.clear_inline_region()
+    }
+
+    /// Keep the inline region on the screen after the terminal is resized, and clear it
+    pub fn fit_inline_region(&mut self) -> Result<()> {
+        let terminal_height = self.low_level.size()?.height();
+        if let Some(region) = self.inline_region.as_mut() {
+            region.height = region.height.min(terminal_height);
+            region.first_row = region.first_row.min(terminal_height - region.height);
+            self.clear_inline_region()?;
+        }
         Ok(())
     }
 
-    pub fn set_foreground_colour<E>(&mut self, rgb24: Rgb24)
-    where
-        E: ColEncode,
-    {
-        E::encode_foreground(&mut self.output_buffer, rgb24, &self.ti_cache);
+    fn clear_inline_region(&mut self) -> Result<()> {
+        let move_cursor = self.output().cursor_absolute(Coord::new(0, 0))?;
+        self.output_buffer.push_str(&self.ti_cache.reset);
+        self.output_buffer.push_str(&move_cursor);
+        self.output_buffer
+            .push_str(&self.ti_cache.clear_to_end_of_screen);
+        self.flush_buffer()
     }
 
-    pub fn set_background_colour<E>(&mut self, rgb24: Rgb24)
-    where
-        E: ColEncode,
-    {
-        E::encode_background(&mut self.output_buffer, rgb24, &self.ti_cache);
+    /// Wait until there is input, a signal arrives, or a wakeup is requested, or until `timeout`
+    /// elapses (or indefinitely if it's `None`). Returns true iff woken by a signal or wakeup.
+    pub fn wait(&mut self, timeout: Option<Duration>) -> Result<bool> {
+        // stop waiting in time to deliver a paste whose end hasn't arrived
+        let timeout = match self.incomplete_paste {
+            Some((_, last_received)) => {
+                let paste_timeout = (last_received + clipboard::PASTE_TIMEOUT)
+                    .saturating_duration_since(Instant::now());
+                Some(timeout.map_or(paste_timeout, |timeout| timeout.min(paste_timeout)))
+            }
+            None => timeout,
+        };
+        let woken = self
+            .low_level
+            .wait(self.wake_receiver.as_raw_fd(), timeout)?;
+        let mut discard = [0; 64];
+        loop {
+            match self.wake_receiver.read(&mut discard) {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) => return Err(error.into()),
+            }
+        }
+        Ok(woken)
     }
 
-    pub fn set_bold(&mut self) {
-        self.output_buffer.push_str(&self.ti_cache.bold);
+    /// A stream which ends the current or next call to `wait` when written to
+    pub fn wake_sender(&self) -> Result<UnixStream> {
+        self.wake_sender.try_clone().map_err(Into::into)
     }
 
-    pub fn set_underline(&mut self) {
-        self.output_buffer.push_str(&self.ti_cache.underline);
+    /// Returns true iff the terminal was resized (i.e. SIGWINCH was received) since this was
+    /// last called
+    pub fn take_resized(&self) -> bool {
+        self.resized.swap(false, Ordering::Relaxed)
     }
 
-    pub fn clear_underline(&mut self) {
-        self.output_buffer.push_str(&self.ti_cache.no_underline);
+    pub fn output(&mut self) -> Output<'_> {
+        let first_row = self
+            .inline_region
+            .map(|region| region.first_row as i32)
+            .unwrap_or(0);
+        Output::new(
+            &mut self.output_buffer,
+            &mut self.ti_cache,
+            self.styled_underlines,
+        )
+        .with_first_row(first_row)
     }
 
-    pub fn reset(&mut self) {
-        self.output_buffer.push_str(&self.ti_cache.reset);
+    pub fn set_styled_underlines(&mut self, styled_underlines: bool) {
+        self.styled_underlines = styled_underlines;
+    }
+
+    /// True iff frames will be drawn as synchronized updates
+    pub fn synchronized_output(&self) -> bool {
+        self.synchronized_output
+    }
+
+    pub fn set_synchronized_output(&mut self, synchronized_output: bool) {
+        self.synchronized_output = synchronized_output;
+    }
+
+    /// The way images are drawn, if the terminal supports any
+    pub fn image_protocol(&self) -> Option<ImageProtocol> {
+        self.image_protocol
+    }
+
+    pub fn set_image_protocol(&mut self, image_protocol: Option<ImageProtocol>) {
+        self.image_protocol = image_protocol;
+    }
+
+    /// The size of a cell in pixels as reported by the terminal, or a guess if it didn't report it
+    pub fn cell_pixel_size(&self) -> Size {
+        self.cell_pixel_size
+    }
+
+    /// The colours of the terminal's theme, as reported during initialisation
+    pub fn terminal_colours(&self) -> &TerminalColours {
+        &self.terminal_colours
+    }
+
+    /// How characters are sent to the terminal
+    pub fn charset(&self) -> Charset {
+        self.charset
+    }
+
+    pub fn set_charset(&mut self, charset: Charset) {
+        self.charset = charset;
+    }
+
+    /// True iff mouse coordinates are reported in pixels rather than cells
+    pub fn mouse_pixel_coordinates(&self) -> bool {
+        self.mouse_pixel_coordinates
+    }
+
+    /// The colour encoding best suited to the terminal
+    pub fn col_encode(&self) -> col_encode::Dynamic {
+        self.col_encode
+    }
+
+    /// True iff the next frame must be drawn from scratch, as the terminal was set up again after
+    /// the process was stopped
+    pub fn take_redraw(&mut self) -> bool {
+        std::mem::replace(&mut self.redraw, false)
+    }
+
+    pub fn redraw_pending(&self) -> bool {
+        self.redraw
     }
 
     pub fn drain_input(&mut self) -> Result<DrainInput> {
         self.low_level.read_polling(&mut self.input_buffer)?;
         self.drain_input_into_ring()?;
+        if !self.ctrl_z_as_input {
+            let len = self.input_ring.len();
+            self.input_ring.retain(|input| !is_suspend_key(input));
+            if self.input_ring.len() != len {
+                self.suspend_requested.store(true, Ordering::Relaxed);
+            }
+        }
+        if self.suspend_requested.swap(false, Ordering::Relaxed) {
+            self.suspend()?;
+        } else if self.continued.swap(false, Ordering::Relaxed) {
+            self.resume()?;
+        }
+        if let Some(region) = self.inline_region {
+            // make mouse coordinates relative to the inline region
+            let row_height = if self.mouse_pixel_coordinates {
+                self.cell_pixel_size.height()
+            } else {
+                1
+            };
+            offset_mouse_coords(self.input_ring.iter_mut(), region.first_row, row_height);
+        }
         Ok(self.input_ring.drain(..))
     }
 
     fn drain_input_into_ring(&mut self) -> Result<()> {
+        // a long paste may arrive over several reads, so keep it until it's complete
+        let complete_len = clipboard::incomplete_paste_start(&self.input_buffer)
+            .unwrap_or(self.input_buffer.len());
         Self::populate_input_ring(
             &mut self.input_ring,
             &self.ti_cache.escape_sequence_prefix_tree,
-            &self.input_buffer,
+            self.kitty_keyboard,
+            &self.input_buffer[..complete_len],
         )?;
-        self.input_buffer.clear();
+        self.input_buffer.drain(..complete_len);
+        if self.input_buffer.is_empty() {
+            self.incomplete_paste = None;
+            return Ok(());
+        }
+        // the input buffer now starts with a paste whose end hasn't arrived
+        let now = Instant::now();
+        let last_received = match self.incomplete_paste {
+            Some((len, last_received)) if len == self.input_buffer.len() => last_received,
+            _ => now,
+        };
+        if now.duration_since(last_received) >= clipboard::PASTE_TIMEOUT {
+            // the end of the paste isn't coming, so deliver what there is and resume parsing
+            self.input_ring
+                .extend(clipboard::parse_unterminated(&self.input_buffer));
+            self.input_buffer.clear();
+            self.incomplete_paste = None;
+        } else {
+            if self.input_buffer.len() > clipboard::MAX_PASTE_LEN {
+                self.input_ring
+                    .extend(clipboard::take_partial(&mut self.input_buffer));
+            }
+            self.incomplete_paste = Some((self.input_buffer.len(), last_received));
+        }
         Ok(())
     }
 
+    /// Replace the contents of the system clipboard with `text`. Terminals which don't support
+    /// OSC 52 ignore this.
+    pub fn set_clipboard(&mut self, text: &str) -> Result<()> {
+        self.output_buffer.push_str(&clipboard::set_clipboard(text));
+        self.flush_buffer()
+    }
+
     fn chip_char(s: &str) -> Option<(char, &str)> {
         let mut chars = s.chars();
         if let Some(first) = chars.next() {
@@ -225,8 +958,30 @@ impl AnsiTerminal {
     fn populate_input_ring(
         input_ring: &mut VecDeque<Input>,
         prefix_tree: &BytePrefixTree<TerminalInput>,
+        kitty_keyboard: bool,
         slice: &[u8],
     ) -> Result<()> {
+        // sequences with parameters are parsed directly rather than with the prefix tree
+        let parsed = sgr_mouse::parse(slice)
+            .or_else(|| clipboard::parse(slice))
+            .or_else(|| {
+                if kitty_keyboard {
+                    kitty_keyboard::parse(slice)
+                } else {
+                    None
+                }
+            });
+        if let Some((input, len)) = parsed {
+            if let Some(input) = input {
+                input_ring.push_back(input);
+            }
+            return Self::populate_input_ring(
+                input_ring,
+                prefix_tree,
+                kitty_keyboard,
+                &slice[len..],
+            );
+        }
         let (term_input, rest) = match prefix_tree.get_longest(slice) {
             None => {
                 // slice does not begin with an escape sequence - chip off the start and try again
@@ -235,14 +990,24 @@ impl AnsiTerminal {
                 } else {
                     return Ok(());
                 };
-                if let Some((ch, rest)) = Self::chip_char(s) {
-                    (Some(TerminalInput::Char(ch)), Some(rest.as_bytes()))
-                } else {
-                    (None, None)
+                match Self::chip_char(s) {
+                    Some((ESCAPE, rest)) => match Self::chip_char(rest) {
+                        // terminals send alt+<key> as escape followed by the key
+                        Some((ch, rest)) if !ch.is_control() && !ALT_AMBIGUOUS.contains(&ch) => {
+                            let input = Input::keyboard_with_modifiers(
+                                KeyboardInput::Char(ch),
+                                KeyModifiers::ALT,
+                            );
+                            (Some(TerminalInput::Literal(input)), Some(rest.as_bytes()))
+                        }
+                        _ => (Some(TerminalInput::Char(ESCAPE)), Some(rest.as_bytes())),
+                    },
+                    Some((ch, rest)) => (Some(TerminalInput::Char(ch)), Some(rest.as_bytes())),
+                    None => (None, None),
                 }
             }
-            Some(Found::Exact(input)) => (Some(*input), None),
-            Some(Found::WithRemaining(input, remaining)) => (Some(*input), Some(remaining)),
+            Some(Found::Exact(input)) => (Some(input.clone()), None),
+            Some(Found::WithRemaining(input, remaining)) => (Some(input.clone()), Some(remaining)),
         };
         let rest = if let Some(input) = term_input {
             let (input, rest) = match input {
@@ -288,16 +1053,12 @@ impl AnsiTerminal {
         };
 
         if let Some(rest) = rest {
-            Self::populate_input_ring(input_ring, prefix_tree, rest)?;
+            Self::populate_input_ring(input_ring, prefix_tree, kitty_keyboard, rest)?;
         }
 
         Ok(())
     }
 
-    pub fn add_char_to_buffer(&mut self, ch: char) {
-        self.output_buffer.push(ch);
-    }
-
     pub fn flush_buffer(&mut self) -> Result<()> {
         self.low_level.send(&self.output_buffer)?;
         self.output_buffer.clear();
@@ -305,9 +1066,182 @@ impl AnsiTerminal {
     }
 }
 
+/// Make the coordinates of mouse inputs relative to `first_row`. The height of a row is 1 when
+/// coordinates are reported in cells, or the height of a cell when they are reported in pixels.
+fn offset_mouse_coords<'a>(
+    inputs: impl Iterator<Item = &'a mut Input>,
+    first_row: u32,
+    row_height: u32,
+) {
+    let offset = Coord::new(0, (first_row * row_height) as i32);
+    for input in inputs {
+        if let Input::Mouse(mouse_input) = input {
+            match mouse_input {
+                MouseInput::MouseMove { coord, .. }
+                | MouseInput::MousePress { coord, .. }
+                | MouseInput::MouseRelease { coord, .. }
+                | MouseInput::MouseScroll { coord, .. } => *coord -= offset,
+            }
+        }
+    }
+}
+
+/// Returns true iff `input` is ctrl+z, which is handled by suspending the process
+fn is_suspend_key(input: &Input) -> bool {
+    match *input {
+        Input::Keyboard(KeyboardInput::Char(SUSPEND)) => true,
+        Input::ModifiedKeyboard(ModifiedKeyboardInput {
+            key: KeyboardInput::Char('z'),
+            modifiers,
+        }) => modifiers == KeyModifiers::CTRL,
+        _ => false,
+    }
+}
+
 impl Drop for AnsiTerminal {
     fn drop(&mut self) {
-        self.teardown()
-            .expect("Failed to reset terminal to original settings");
+        for &signal in &self.signals {
+            signal_hook::low_level::unregister(signal);
+        }
+        if self.low_level.tty().is_none() {
+            // the other end of the stream may have already gone away (e.g. a client
+            // disconnecting), in which case there's nothing left to restore
+            let _ = self.teardown();
+        } else if restore::clear() {
+            // the panic hook may have already restored the terminal
+            self.teardown()
+                .expect("Failed to reset terminal to original settings");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_with_kitty_keyboard(bytes: &[u8], kitty_keyboard: bool) -> Vec<Input> {
+        let ti_cache = TermInfoCache::xterm_for_tests();
+        let mut input_ring = VecDeque::new();
+        AnsiTerminal::populate_input_ring(
+            &mut input_ring,
+            &ti_cache.escape_sequence_prefix_tree,
+            kitty_keyboard,
+            bytes,
+        )
+        .unwrap();
+        input_ring.into_iter().collect()
+    }
+
+    fn parse(bytes: &[u8]) -> Vec<Input> {
+        parse_with_kitty_keyboard(bytes, false)
+    }
+
+    #[test]
+    fn modifiers() {
+        let modified =
+            |key, modifiers| Input::ModifiedKeyboard(ModifiedKeyboardInput::new(key, modifiers));
+        assert_eq!(
+            parse(b"\x1b[1;6D\x1b[Z\x1bq\x1b[5;3~\x1bOA"),
+            vec![
+                modified(
+                    KeyboardInput::Left,
+                    KeyModifiers::CTRL | KeyModifiers::SHIFT
+                ),
+                modified(keys::TAB, KeyModifiers::SHIFT),
+                modified(KeyboardInput::Char('q'), KeyModifiers::ALT),
+                modified(KeyboardInput::PageUp, KeyModifiers::ALT),
+                Input::Keyboard(KeyboardInput::Up),
+            ]
+        );
+        assert_eq!(
+            parse(b"\x1b\x1b"),
+            vec![Input::Keyboard(keys::ESCAPE), Input::Keyboard(keys::ESCAPE)]
+        );
+    }
+
+    #[test]
+    fn mouse() {
+        assert_eq!(
+            parse(b"\x1b[<0;250;2M\x1b[<0;250;2m\x1b[M#!!"),
+            vec![
+                Input::Mouse(MouseInput::MousePress {
+                    button: MouseButton::Left,
+                    coord: Coord::new(249, 1),
+                }),
+                Input::Mouse(MouseInput::MouseRelease {
+                    button: Ok(MouseButton::Left),
+                    coord: Coord::new(249, 1),
+                }),
+                Input::Mouse(MouseInput::MouseRelease {
+                    button: Err(NotSupported),
+                    coord: Coord::new(0, 0),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn inline_mouse_coords() {
+        let press = |x, y| {
+            Input::Mouse(MouseInput::MousePress {
+                button: MouseButton::Left,
+                coord: Coord::new(x, y),
+            })
+        };
+        let mut inputs = vec![press(3, 12), Input::FocusGained];
+        offset_mouse_coords(inputs.iter_mut(), 10, 1);
+        assert_eq!(inputs, vec![press(3, 2), Input::FocusGained]);
+        // pixel coordinates are offset by the height of the rows above the region in pixels
+        let mut inputs = vec![press(30, 170)];
+        offset_mouse_coords(inputs.iter_mut(), 10, 16);
+        assert_eq!(inputs, vec![press(30, 10)]);
+    }
+
+    #[test]
+    fn focus() {
+        assert_eq!(
+            parse(b"\x1b[O\x1b[I"),
+            vec![Input::FocusLost, Input::FocusGained]
+        );
+    }
+
+    #[test]
+    fn paste() {
+        assert_eq!(
+            parse(b"a\x1b[200~b\x1b[Ac\x1b[201~d"),
+            vec![
+                Input::Keyboard(KeyboardInput::Char('a')),
+                Input::Paste("b\x1b[Ac".to_string()),
+                Input::Keyboard(KeyboardInput::Char('d')),
+            ]
+        );
+    }
+
+    #[test]
+    fn kitty_keyboard() {
+        assert_eq!(
+            parse_with_kitty_keyboard(b"\x1b[27u\x1b[97;1:3ux\x1b[57441;2u\x1b[1;1:2A", true),
+            vec![
+                Input::Keyboard(keys::ESCAPE),
+                Input::KeyRelease(ModifiedKeyboardInput::new(
+                    KeyboardInput::Char('a'),
+                    KeyModifiers::NONE
+                )),
+                Input::Keyboard(KeyboardInput::Char('x')),
+                Input::KeyRepeat(ModifiedKeyboardInput::new(
+                    KeyboardInput::Up,
+                    KeyModifiers::NONE
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn suspend_key() {
+        let inputs = parse_with_kitty_keyboard(b"\x1a", false);
+        assert!(is_suspend_key(&inputs[0]));
+        let inputs = parse_with_kitty_keyboard(b"\x1b[122;5u\x1b[122;3u", true);
+        assert!(is_suspend_key(&inputs[0]));
+        assert!(!is_suspend_key(&inputs[1]));
     }
 }
@@ -0,0 +1,195 @@
+//! Descriptions of common terminals, compiled into the library so that apps can run when the
+//! terminfo database is missing (e.g. in minimal containers) or lacks the terminal's entry
+
+use std::collections::HashMap;
+use term::terminfo::TermInfo;
+
+/// The capabilities of a terminal which are used by this library
+pub struct BuiltinTermInfo {
+    name: &'static str,
+    colours: u32,
+    strings: &'static [(&'static str, &'static str)],
+}
+
+// Capabilities shared by every built-in description. The terminals differ in how they encode
+// the other capabilities.
+const COMMON_STRINGS: &[(&str, &str)] = &[
+    ("kf6", "\x1b[17~"),
+    ("kf7", "\x1b[18~"),
+    ("kf8", "\x1b[19~"),
+    ("kf9", "\x1b[20~"),
+    ("kf10", "\x1b[21~"),
+    ("kf11", "\x1b[23~"),
+    ("kf12", "\x1b[24~"),
+    ("kpp", "\x1b[5~"),
+    ("knp", "\x1b[6~"),
+    ("kdch1", "\x1b[3~"),
+    ("blink", "\x1b[5m"),
+    ("bold", "\x1b[1m"),
+    ("dim", "\x1b[2m"),
+    ("rev", "\x1b[7m"),
+    ("smul", "\x1b[4m"),
+    ("rmul", "\x1b[24m"),
+    ("ed", "\x1b[J"),
+    ("cup", "\x1b[%i%p1%d;%p2%dH"),
+    ("cr", "\r"),
+    ("cuu", "\x1b[%p1%dA"),
+    ("cud", "\x1b[%p1%dB"),
+    ("cub", "\x1b[%p1%dD"),
+    ("cuf", "\x1b[%p1%dC"),
+    ("cud1", "\n"),
+    ("cub1", "\x08"),
+    ("cuf1", "\x1b[C"),
+];
+
+// The 256 colour palette, with the first 16 colours selected with the shorter sgr codes
+const SETAF_256: &str = "\x1b[%?%p1%{8}%<%t3%p1%d%e%p1%{16}%<%t9%p1%{8}%-%d%e38;5;%p1%d%;m";
+const SETAB_256: &str = "\x1b[%?%p1%{8}%<%t4%p1%d%e%p1%{16}%<%t10%p1%{8}%-%d%e48;5;%p1%d%;m";
+
+pub const XTERM_256COLOR: BuiltinTermInfo = BuiltinTermInfo {
+    name: "xterm-256color",
+    colours: 256,
+    strings: &[
+        ("setaf", SETAF_256),
+        ("setab", SETAB_256),
+        ("kf1", "\x1bOP"),
+        ("kf2", "\x1bOQ"),
+        ("kf3", "\x1bOR"),
+        ("kf4", "\x1bOS"),
+        ("kf5", "\x1b[15~"),
+        ("kcuu1", "\x1bOA"),
+        ("kcud1", "\x1bOB"),
+        ("kcuf1", "\x1bOC"),
+        ("kcub1", "\x1bOD"),
+        ("khome", "\x1bOH"),
+        ("kend", "\x1bOF"),
+        ("kcbt", "\x1b[Z"),
+        ("smcup", "\x1b[?1049h\x1b[22;0;0t"),
+        ("rmcup", "\x1b[?1049l\x1b[23;0;0t"),
+        ("smkx", "\x1b[?1h\x1b="),
+        ("rmkx", "\x1b[?1l\x1b>"),
+        ("cnorm", "\x1b[?12l\x1b[?25h"),
+        ("civis", "\x1b[?25l"),
+        ("sgr0", "\x1b(B\x1b[m"),
+        ("clear", "\x1b[H\x1b[2J"),
+        ("sitm", "\x1b[3m"),
+        ("ritm", "\x1b[23m"),
+        ("cuu1", "\x1b[A"),
+        ("rep", "%p1%c\x1b[%p2%{1}%-%db"),
+        ("smacs", "\x1b(0"),
+        ("rmacs", "\x1b(B"),
+        (
+            "acsc",
+            "``aaffggiijjkkllmmnnooppqqrrssttuuvvwwxxyyzz{{||}}~~",
+        ),
+    ],
+};
+
+/// Describes both GNU screen and tmux
+pub const SCREEN_256COLOR: BuiltinTermInfo = BuiltinTermInfo {
+    name: "screen-256color",
+    colours: 256,
+    strings: &[
+        ("setaf", SETAF_256),
+        ("setab", SETAB_256),
+        ("kf1", "\x1bOP"),
+        ("kf2", "\x1bOQ"),
+        ("kf3", "\x1bOR"),
+        ("kf4", "\x1bOS"),
+        ("kf5", "\x1b[15~"),
+        ("kcuu1", "\x1bOA"),
+        ("kcud1", "\x1bOB"),
+        ("kcuf1", "\x1bOC"),
+        ("kcub1", "\x1bOD"),
+        ("khome", "\x1b[1~"),
+        ("kend", "\x1b[4~"),
+        ("kcbt", "\x1b[Z"),
+        ("smcup", "\x1b[?1049h"),
+        ("rmcup", "\x1b[?1049l"),
+        ("smkx", "\x1b[?1h\x1b="),
+        ("rmkx", "\x1b[?1l\x1b>"),
+        ("cnorm", "\x1b[34h\x1b[?25h"),
+        ("civis", "\x1b[?25l"),
+        ("sgr0", "\x1b[m\x0f"),
+        ("clear", "\x1b[H\x1b[J"),
+        ("cuu1", "\x1bM"),
+        ("smacs", "\x0e"),
+        ("rmacs", "\x0f"),
+        ("enacs", "\x1b(B\x1b)0"),
+        (
+            "acsc",
+            "++,,--..00``aaffgghhiijjkkllmmnnooppqqrrssttuuvvwwxxyyzz{{||}}~~",
+        ),
+    ],
+};
+
+/// The linux console, which has no alternate screen and only 8 colours
+pub const LINUX: BuiltinTermInfo = BuiltinTermInfo {
+    name: "linux",
+    colours: 8,
+    strings: &[
+        ("setaf", "\x1b[3%p1%dm"),
+        ("setab", "\x1b[4%p1%dm"),
+        ("kf1", "\x1b[[A"),
+        ("kf2", "\x1b[[B"),
+        ("kf3", "\x1b[[C"),
+        ("kf4", "\x1b[[D"),
+        ("kf5", "\x1b[[E"),
+        ("kcuu1", "\x1b[A"),
+        ("kcud1", "\x1b[B"),
+        ("kcuf1", "\x1b[C"),
+        ("kcub1", "\x1b[D"),
+        ("khome", "\x1b[1~"),
+        ("kend", "\x1b[4~"),
+        ("kcbt", "\x1b\t"),
+        ("cnorm", "\x1b[?25h\x1b[?0c"),
+        ("civis", "\x1b[?25l\x1b[?1c"),
+        ("sgr0", "\x1b[m\x0f"),
+        ("clear", "\x1b[H\x1b[J"),
+        ("cuu1", "\x1b[A"),
+    ],
+};
+
+impl BuiltinTermInfo {
+    /// The built-in description most likely to match the terminal named `term`. Terminals other
+    /// than screen, tmux and the linux console are assumed to be compatible with xterm, as most
+    /// terminal emulators are.
+    pub fn closest_to(term: &str) -> &'static Self {
+        if term.starts_with("screen") || term.starts_with("tmux") {
+            &SCREEN_256COLOR
+        } else if term.starts_with("linux") {
+            &LINUX
+        } else {
+            &XTERM_256COLOR
+        }
+    }
+
+    pub fn term_info(&self) -> TermInfo {
+        let mut numbers = HashMap::new();
+        numbers.insert("colors", self.colours);
+        TermInfo {
+            names: vec![self.name.to_string()],
+            bools: HashMap::new(),
+            numbers,
+            strings: COMMON_STRINGS
+                .iter()
+                .chain(self.strings)
+                .map(|&(name, value)| (name, value.as_bytes().to_vec()))
+                .collect(),
+        }
+    }
+
+    /// A description of a terminal whose terminfo entry lacks capabilities, in which this
+    /// description fills in the missing capabilities. The entry's own capabilities and names are
+    /// kept.
+    pub fn fill_in(&self, term_info: &TermInfo) -> TermInfo {
+        let mut filled_in = self.term_info();
+        filled_in.names = term_info.names.clone();
+        filled_in.bools.extend(&term_info.bools);
+        filled_in.numbers.extend(&term_info.numbers);
+        filled_in
+            .strings
+            .extend(term_info.strings.iter().map(|(&k, v)| (k, v.clone())));
+        filled_in
+    }
+}
@@ -0,0 +1,311 @@
+//! Drawing non-ascii characters on terminals which can't display unicode (e.g. the linux console,
+//! or any terminal in a locale whose character encoding isn't UTF-8)
+
+use std::collections::HashMap;
+use std::env;
+
+// the first of these which is set determines the locale's character encoding
+const LOCALE_VARIABLES: &[&str] = &["LC_ALL", "LC_CTYPE", "LANG"];
+// the terminfo name of the linux console, which can't display most of unicode
+const LINUX_CONSOLE: &str = "linux";
+
+/// The character drawn for non-ascii characters missing from the transliteration table
+pub const DEFAULT_TRANSLITERATION_FALLBACK: char = '?';
+
+// Unicode characters which can be drawn with the DEC special graphics set, along with the
+// character which selects them in that set. Heavy, double and rounded lines are drawn with the
+// set's light lines.
+const DEC_SPECIAL_GRAPHICS: &[(char, char)] = &[
+    ('◆', '`'),
+    ('♦', '`'),
+    ('▒', 'a'),
+    ('°', 'f'),
+    ('±', 'g'),
+    ('░', 'h'),
+    ('█', '0'),
+    ('→', '+'),
+    ('←', ','),
+    ('↑', '-'),
+    ('↓', '.'),
+    ('⎺', 'o'),
+    ('⎻', 'p'),
+    ('⎼', 'r'),
+    ('⎽', 's'),
+    ('≤', 'y'),
+    ('≥', 'z'),
+    ('π', '{'),
+    ('≠', '|'),
+    ('£', '}'),
+    ('·', '~'),
+    ('─', 'q'),
+    ('━', 'q'),
+    ('═', 'q'),
+    ('│', 'x'),
+    ('┃', 'x'),
+    ('║', 'x'),
+    ('┌', 'l'),
+    ('┏', 'l'),
+    ('╔', 'l'),
+    ('╭', 'l'),
+    ('┐', 'k'),
+    ('┓', 'k'),
+    ('╗', 'k'),
+    ('╮', 'k'),
+    ('└', 'm'),
+    ('┗', 'm'),
+    ('╚', 'm'),
+    ('╰', 'm'),
+    ('┘', 'j'),
+    ('┛', 'j'),
+    ('╝', 'j'),
+    ('╯', 'j'),
+    ('├', 't'),
+    ('┣', 't'),
+    ('╠', 't'),
+    ('┤', 'u'),
+    ('┫', 'u'),
+    ('╣', 'u'),
+    ('┴', 'v'),
+    ('┻', 'v'),
+    ('╩', 'v'),
+    ('┬', 'w'),
+    ('┳', 'w'),
+    ('╦', 'w'),
+    ('┼', 'n'),
+    ('╋', 'n'),
+    ('╬', 'n'),
+];
+
+// Ascii replacements for common non-ascii characters. Box-drawing characters which aren't listed
+// here are drawn as '+'.
+const DEFAULT_TRANSLITERATIONS: &[(char, char)] = &[
+    ('─', '-'),
+    ('━', '-'),
+    ('═', '-'),
+    ('┄', '-'),
+    ('┅', '-'),
+    ('┈', '-'),
+    ('┉', '-'),
+    ('╌', '-'),
+    ('╍', '-'),
+    ('╴', '-'),
+    ('╶', '-'),
+    ('╸', '-'),
+    ('╺', '-'),
+    ('│', '|'),
+    ('┃', '|'),
+    ('║', '|'),
+    ('┆', '|'),
+    ('┇', '|'),
+    ('┊', '|'),
+    ('┋', '|'),
+    ('╎', '|'),
+    ('╏', '|'),
+    ('╵', '|'),
+    ('╷', '|'),
+    ('╹', '|'),
+    ('╻', '|'),
+    ('╱', '/'),
+    ('╲', '\\'),
+    ('╳', 'X'),
+    ('█', '#'),
+    ('▉', '#'),
+    ('▊', '#'),
+    ('▋', '#'),
+    ('▓', '#'),
+    ('▀', '#'),
+    ('▄', '#'),
+    ('▌', '#'),
+    ('▐', '#'),
+    ('▒', ':'),
+    ('░', '.'),
+    ('◆', '*'),
+    ('♦', '*'),
+    ('●', '*'),
+    ('•', '*'),
+    ('○', 'o'),
+    ('■', '#'),
+    ('□', '#'),
+    ('▲', '^'),
+    ('▼', 'v'),
+    ('◀', '<'),
+    ('▶', '>'),
+    ('◄', '<'),
+    ('►', '>'),
+    ('←', '<'),
+    ('→', '>'),
+    ('↑', '^'),
+    ('↓', 'v'),
+    ('·', '.'),
+    ('…', '.'),
+    ('–', '-'),
+    ('—', '-'),
+    ('‘', '\''),
+    ('’', '\''),
+    ('“', '"'),
+    ('”', '"'),
+    ('×', 'x'),
+    ('°', 'o'),
+    ('≤', '<'),
+    ('≥', '>'),
+    ('≠', '#'),
+    ('±', '+'),
+    ('£', 'L'),
+    ('π', 'n'),
+];
+
+/// How characters are sent to the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Charset {
+    /// Characters are sent as UTF-8
+    Unicode,
+    /// Box-drawing and other characters in the DEC special graphics set are drawn with the
+    /// terminal's alternate character set (the "smacs", "rmacs" and "acsc" capabilities), and
+    /// other non-ascii characters are transliterated. Terminals without an alternate character
+    /// set are treated as `Ascii`.
+    DecSpecialGraphics,
+    /// Non-ascii characters are transliterated to ascii
+    Ascii,
+}
+
+/// Returns true iff the locale's character encoding is UTF-8, going by its name (e.g.
+/// "en_GB.UTF-8")
+fn is_utf8_locale(locale: &str) -> bool {
+    let locale = locale.to_ascii_lowercase();
+    locale.contains("utf-8") || locale.contains("utf8")
+}
+
+impl Charset {
+    /// Unicode unless the locale is set and its character encoding isn't UTF-8, or the terminal
+    /// is the linux console. Otherwise the terminal's alternate character set is used for
+    /// box-drawing characters if it has one.
+    pub fn from_env(alternate_charset: bool) -> Self {
+        let locale = LOCALE_VARIABLES
+            .iter()
+            .filter_map(|&name| env::var(name).ok())
+            .find(|value| !value.is_empty());
+        let term = env::var("TERM").unwrap_or_default();
+        Self::from_locale(locale.as_deref(), &term, alternate_charset)
+    }
+
+    fn from_locale(locale: Option<&str>, term: &str, alternate_charset: bool) -> Self {
+        let non_utf8_locale = matches!(locale, Some(locale) if !is_utf8_locale(locale));
+        if !(non_utf8_locale || term == LINUX_CONSOLE) {
+            Charset::Unicode
+        } else if alternate_charset {
+            Charset::DecSpecialGraphics
+        } else {
+            Charset::Ascii
+        }
+    }
+}
+
+/// The character which selects `ch` in the DEC special graphics set, if it's in the set
+pub fn dec_special_graphics(ch: char) -> Option<char> {
+    DEC_SPECIAL_GRAPHICS
+        .iter()
+        .find(|&&(unicode, _)| unicode == ch)
+        .map(|&(_, dec)| dec)
+}
+
+/// A table of ascii replacements for non-ascii characters, used when the terminal can't display
+/// unicode. By default, box-drawing and block characters, arrows and common punctuation are
+/// replaced with similar-looking ascii characters, and other characters are replaced with '?'.
+#[derive(Debug, Clone)]
+pub struct Transliteration {
+    table: HashMap<char, char>,
+    fallback: char,
+}
+
+impl Default for Transliteration {
+    fn default() -> Self {
+        let box_drawing = ('\u{2500}'..='\u{257F}').map(|ch| (ch, '+'));
+        Self {
+            table: box_drawing
+                .chain(DEFAULT_TRANSLITERATIONS.iter().cloned())
+                .collect(),
+            fallback: DEFAULT_TRANSLITERATION_FALLBACK,
+        }
+    }
+}
+
+impl Transliteration {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A table with no replacements, in which every non-ascii character is replaced with
+    /// `fallback`
+    pub fn empty(fallback: char) -> Self {
+        assert!(fallback.is_ascii(), "replacements must be ascii");
+        Self {
+            table: HashMap::new(),
+            fallback,
+        }
+    }
+
+    /// Replace `ch` with `replacement`, which must be ascii
+    pub fn with(mut self, ch: char, replacement: char) -> Self {
+        assert!(replacement.is_ascii(), "replacements must be ascii");
+        self.table.insert(ch, replacement);
+        self
+    }
+
+    /// Replace characters missing from the table with `fallback`, which must be ascii
+    pub fn with_fallback(mut self, fallback: char) -> Self {
+        assert!(fallback.is_ascii(), "replacements must be ascii");
+        self.fallback = fallback;
+        self
+    }
+
+    /// The ascii character to draw in place of `ch`
+    pub fn transliterate(&self, ch: char) -> char {
+        if ch.is_ascii() {
+            ch
+        } else {
+            self.table.get(&ch).cloned().unwrap_or(self.fallback)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect() {
+        let detect = Charset::from_locale;
+        assert_eq!(detect(Some("en_GB.UTF-8"), "xterm", true), Charset::Unicode);
+        assert_eq!(detect(Some("C.utf8"), "xterm", false), Charset::Unicode);
+        assert_eq!(
+            detect(Some("en_US.ISO-8859-1"), "xterm", true),
+            Charset::DecSpecialGraphics
+        );
+        assert_eq!(detect(Some("C"), "xterm", false), Charset::Ascii);
+        // when no locale is set, the encoding is unknown, so unicode is assumed
+        assert_eq!(detect(None, "xterm", false), Charset::Unicode);
+        // except on the linux console
+        assert_eq!(detect(None, "linux", true), Charset::DecSpecialGraphics);
+        assert_eq!(
+            detect(Some("en_GB.UTF-8"), "linux", true),
+            Charset::DecSpecialGraphics
+        );
+    }
+
+    #[test]
+    fn transliterate() {
+        let transliteration = Transliteration::new();
+        assert_eq!(transliteration.transliterate('a'), 'a');
+        assert_eq!(transliteration.transliterate('─'), '-');
+        assert_eq!(transliteration.transliterate('┼'), '+');
+        assert_eq!(transliteration.transliterate('█'), '#');
+        assert_eq!(transliteration.transliterate('日'), '?');
+        let transliteration = transliteration.with('█', '@').with_fallback('_');
+        assert_eq!(transliteration.transliterate('█'), '@');
+        assert_eq!(transliteration.transliterate('日'), '_');
+        assert_eq!(Transliteration::empty(' ').transliterate('─'), ' ');
+        assert_eq!(dec_special_graphics('┌'), Some('l'));
+        assert_eq!(dec_special_graphics('╝'), Some('j'));
+        assert_eq!(dec_special_graphics('a'), None);
+    }
+}
@@ -0,0 +1,132 @@
+//! Bracketed paste (DEC private mode 2004) and writing to the clipboard with OSC 52
+
+use chargrid_input::Input;
+use std::time::Duration;
+
+pub const ENABLE_BRACKETED_PASTE: &str = "\x1b[?2004h";
+pub const DISABLE_BRACKETED_PASTE: &str = "\x1b[?2004l";
+
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// Once this much of a paste has arrived without its end, the text received so far is delivered
+/// and the rest of the paste is delivered separately, bounding the size of the input buffer
+pub const MAX_PASTE_LEN: usize = 1 << 20;
+
+/// If no more of a paste arrives for this long before its end, the text received so far is
+/// delivered and input is parsed normally again
+pub const PASTE_TIMEOUT: Duration = Duration::from_millis(500);
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn paste_text(text: &[u8]) -> String {
+    String::from_utf8_lossy(text)
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+}
+
+/// Parse pasted text from the start of `bytes`. Returns the input and the length of the sequence,
+/// or `None` if `bytes` doesn't start with a complete paste. Line endings are normalised to "\n",
+/// as terminals send newlines in pasted text as carriage returns. The end of a paste whose start
+/// was already delivered by `parse_unterminated` is skipped.
+pub fn parse(bytes: &[u8]) -> Option<(Option<Input>, usize)> {
+    if bytes.starts_with(PASTE_END) {
+        return Some((None, PASTE_END.len()));
+    }
+    let text = bytes.strip_prefix(PASTE_START)?;
+    let text_len = find(text, PASTE_END)?;
+    Some((
+        Some(Input::Paste(paste_text(&text[..text_len]))),
+        PASTE_START.len() + text_len + PASTE_END.len(),
+    ))
+}
+
+/// Parse all of `bytes`, which starts with a paste whose end hasn't arrived, as pasted text.
+/// Returns `None` if there's no text.
+pub fn parse_unterminated(bytes: &[u8]) -> Option<Input> {
+    match bytes.strip_prefix(PASTE_START)? {
+        [] => None,
+        text => Some(Input::Paste(paste_text(text))),
+    }
+}
+
+/// Remove the text received so far of a paste at the start of `buffer` whose end hasn't arrived,
+/// leaving the start of the paste so that the rest of it is still parsed as pasted text. A partial
+/// character or line ending at the end is also left, to be completed by the rest of the paste.
+pub fn take_partial(buffer: &mut Vec<u8>) -> Option<Input> {
+    let text = buffer.strip_prefix(PASTE_START)?;
+    let mut text_len = match std::str::from_utf8(text) {
+        Err(error) if error.error_len().is_none() => error.valid_up_to(),
+        _ => text.len(),
+    };
+    if text[..text_len].ends_with(b"\r") {
+        text_len -= 1;
+    }
+    let input = Input::Paste(paste_text(&text[..text_len]));
+    buffer.drain(PASTE_START.len()..(PASTE_START.len() + text_len));
+    Some(input)
+}
+
+/// Returns the index of the start of a paste whose end hasn't been received yet, if any
+pub fn incomplete_paste_start(bytes: &[u8]) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(start) = find(&bytes[offset..], PASTE_START) {
+        let start = offset + start;
+        match find(&bytes[start..], PASTE_END) {
+            Some(end) => offset = start + end + PASTE_END.len(),
+            None => return Some(start),
+        }
+    }
+    None
+}
+
+/// The OSC 52 sequence which sets the clipboard's contents to `text`
+pub fn set_clipboard(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64::encode(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paste() {
+        let bytes = b"\x1b[200~hello\r\nworld\r\x1b[201~a";
+        assert_eq!(
+            parse(bytes),
+            Some((Some(Input::Paste("hello\nworld\n".to_string())), 25))
+        );
+        assert_eq!(parse(&bytes[..20]), None);
+        assert_eq!(incomplete_paste_start(&bytes[..20]), Some(0));
+        let bytes = b"a\x1b[200~b\x1b[201~c\x1b[200~d";
+        assert_eq!(incomplete_paste_start(bytes), Some(15));
+    }
+
+    #[test]
+    fn unterminated_paste() {
+        let bytes = "\x1b[200~a\r\nb\r\u{e9}".as_bytes();
+        assert_eq!(
+            parse_unterminated(bytes),
+            Some(Input::Paste("a\nb\n\u{e9}".to_string()))
+        );
+        // stop before the partial character, then before the carriage return
+        let mut buffer = bytes[..bytes.len() - 1].to_vec();
+        assert_eq!(
+            take_partial(&mut buffer),
+            Some(Input::Paste("a\nb".to_string()))
+        );
+        assert_eq!(buffer, b"\x1b[200~\r\xc3");
+        assert_eq!(take_partial(&mut b"a".to_vec()), None);
+        // the end of a paste which was already delivered is skipped
+        assert_eq!(parse(b"\x1b[201~a"), Some((None, 6)));
+    }
+
+    #[test]
+    fn osc52() {
+        assert_eq!(set_clipboard("chargrid"), "\x1b]52;c;Y2hhcmdyaWQ=\x07");
+    }
+}
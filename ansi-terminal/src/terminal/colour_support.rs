@@ -0,0 +1,97 @@
+//! Choosing a colour encoding based on the colours the terminal supports
+
+use super::ansi_terminal::col_encode::Dynamic;
+use std::env;
+
+/// XTGETTCAP query for the "RGB" and "Tc" capabilities (with hex-encoded names), which terminals
+/// report if they support true colour. Many terminfo databases lack these capabilities, so the
+/// terminal itself is asked.
+pub const QUERY_TRUE_COLOUR: &str = "\x1bP+q524742;5463\x1b\\";
+/// Names of terminfo capabilities which indicate true colour support
+pub const TRUE_COLOUR_CAPS: &[&str] = &["RGB", "Tc"];
+
+/// Everything which is known about the colours supported by the terminal
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ColourSupport {
+    /// The NO_COLOR environment variable is set (see https://no-color.org)
+    pub no_color: bool,
+    /// The COLORTERM environment variable advertises true colour
+    pub colorterm_true_colour: bool,
+    /// The number of colours according to the terminfo database
+    pub terminfo_colours: Option<u32>,
+    /// The terminfo database describes a direct colour terminal
+    pub terminfo_rgb: bool,
+    /// The terminal responded to `QUERY_TRUE_COLOUR`
+    pub reported_true_colour: bool,
+}
+
+impl ColourSupport {
+    pub fn from_env() -> Self {
+        let non_empty = |name| matches!(env::var_os(name), Some(value) if !value.is_empty());
+        let colorterm_true_colour = env::var("COLORTERM")
+            .map(|colorterm| colorterm == "truecolor" || colorterm == "24bit")
+            .unwrap_or(false);
+        Self {
+            no_color: non_empty("NO_COLOR"),
+            colorterm_true_colour,
+            ..Default::default()
+        }
+    }
+
+    /// True iff it's worth asking the terminal whether it supports true colour
+    pub fn should_query(&self) -> bool {
+        !(self.no_color || self.colorterm_true_colour || self.terminfo_rgb)
+    }
+
+    /// The encoding which best reproduces colours on the terminal
+    pub fn col_encode(&self) -> Dynamic {
+        if self.no_color {
+            return Dynamic::NoColour;
+        }
+        if self.colorterm_true_colour || self.terminfo_rgb || self.reported_true_colour {
+            return Dynamic::XtermTrueColour;
+        }
+        match self.terminfo_colours {
+            Some(colours) if colours >= 256 => Dynamic::FromTermInfoRgb,
+            Some(colours) if colours >= 8 => Dynamic::FromTermInfoAnsi16Colour,
+            _ => Dynamic::NoColour,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn choose_col_encode() {
+        let xterm_256 = ColourSupport {
+            terminfo_colours: Some(256),
+            ..Default::default()
+        };
+        assert_eq!(xterm_256.col_encode(), Dynamic::FromTermInfoRgb);
+        assert!(xterm_256.should_query());
+        let reported = ColourSupport {
+            reported_true_colour: true,
+            ..xterm_256
+        };
+        assert_eq!(reported.col_encode(), Dynamic::XtermTrueColour);
+        let colorterm = ColourSupport {
+            colorterm_true_colour: true,
+            ..xterm_256
+        };
+        assert_eq!(colorterm.col_encode(), Dynamic::XtermTrueColour);
+        assert!(!colorterm.should_query());
+        let no_color = ColourSupport {
+            no_color: true,
+            ..colorterm
+        };
+        assert_eq!(no_color.col_encode(), Dynamic::NoColour);
+        let linux = ColourSupport {
+            terminfo_colours: Some(8),
+            ..Default::default()
+        };
+        assert_eq!(linux.col_encode(), Dynamic::FromTermInfoAnsi16Colour);
+        assert_eq!(ColourSupport::default().col_encode(), Dynamic::NoColour);
+    }
+}
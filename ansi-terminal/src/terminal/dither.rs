@@ -0,0 +1,55 @@
+//! Ordered dithering of background colours, which reduces banding in gradients drawn with a
+//! limited palette. Ordered dithering is used rather than error diffusion so that a cell's colour
+//! only depends on its position, which keeps unchanged parts of the screen stable between frames.
+
+use chargrid_render::{Buffer, Rgb24};
+
+const BAYER_SIZE: usize = 4;
+const BAYER_MATRIX: [[u8; BAYER_SIZE]; BAYER_SIZE] =
+    [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+fn offset_channel(channel: u8, offset: i32) -> u8 {
+    (channel as i32 + offset).clamp(0, 255) as u8
+}
+
+/// Offset the background colour of each cell by a position-dependent amount of up to half of
+/// `spread` in either direction, where `spread` is the distance between neighbouring levels of a
+/// channel in the palette
+pub fn dither_backgrounds(buffer: &mut Buffer, spread: u8) {
+    let levels = (BAYER_SIZE * BAYER_SIZE) as i32;
+    buffer.map_background_colours(|coord, Rgb24 { r, g, b }| {
+        let threshold =
+            BAYER_MATRIX[coord.y as usize % BAYER_SIZE][coord.x as usize % BAYER_SIZE] as i32;
+        // centre the thresholds around 0 so the average colour is unchanged
+        let offset = ((2 * threshold + 1 - levels) * spread as i32) / (2 * levels);
+        Rgb24::new(
+            offset_channel(r, offset),
+            offset_channel(g, offset),
+            offset_channel(b, offset),
+        )
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chargrid_render::{Frame, Size, ViewCell};
+
+    #[test]
+    fn dithering_preserves_average() {
+        let size = Size::new(4, 4);
+        let mut buffer = Buffer::new(size);
+        let grey = Rgb24::new(100, 100, 100);
+        for coord in size.coord_iter_row_major() {
+            buffer.set_cell_absolute(coord, 0, ViewCell::new().with_background(grey));
+        }
+        dither_backgrounds(&mut buffer, 40);
+        let colours = buffer
+            .iter()
+            .map(|cell| cell.background_colour.r as i32)
+            .collect::<Vec<_>>();
+        assert_eq!(colours.iter().sum::<i32>() / colours.len() as i32, 100);
+        assert_eq!(colours.iter().min(), Some(&82));
+        assert_eq!(colours.iter().max(), Some(&118));
+    }
+}
@@ -0,0 +1,1138 @@
+use super::ansi_terminal::ColEncode;
+use super::charset::{Charset, Transliteration};
+use super::graphics::{self, ImageProtocol};
+use super::output::Output;
+use crate::error::Result;
+use chargrid_render::*;
+
+/// The colours to draw a cell with, where `None` is the terminal's default colour
+fn foreground(cell: &BufferCell) -> Option<Rgb24> {
+    if cell.default_foreground {
+        None
+    } else {
+        Some(cell.foreground_colour)
+    }
+}
+
+fn background(cell: &BufferCell) -> Option<Rgb24> {
+    if cell.default_background {
+        None
+    } else {
+        Some(cell.background_colour)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct OutputCell {
+    dirty: bool,
+    ch: Grapheme,
+    fg: Option<Rgb24>,
+    bg: Option<Rgb24>,
+    bold: bool,
+    underline: bool,
+    underline_kind: UnderlineKind,
+    underline_colour: Option<Rgb24>,
+    italic: bool,
+    strikethrough: bool,
+    dim: bool,
+    reverse: bool,
+    blink: bool,
+    hyperlink: Option<Hyperlink>,
+}
+
+impl OutputCell {
+    fn matches(&self, cell: &BufferCell) -> bool {
+        !self.dirty
+            && self.ch == cell.character
+            && self.fg == foreground(cell)
+            && self.bg == background(cell)
+            && self.bold == cell.bold
+            && self.underline == cell.underline
+            && self.underline_kind == cell.underline_kind
+            && self.underline_colour == cell.underline_colour
+            && self.italic == cell.italic
+            && self.strikethrough == cell.strikethrough
+            && self.dim == cell.dim
+            && self.reverse == cell.reverse
+            && self.blink == cell.blink
+            && self.hyperlink == cell.hyperlink
+    }
+    /// True iff drawing the two cells requires the same attributes and colours
+    fn same_style(&self, other: &Self) -> bool {
+        self.fg == other.fg
+            && self.bg == other.bg
+            && self.bold == other.bold
+            && self.underline == other.underline
+            && self.underline_kind == other.underline_kind
+            && self.underline_colour == other.underline_colour
+            && self.italic == other.italic
+            && self.strikethrough == other.strikethrough
+            && self.dim == other.dim
+            && self.reverse == other.reverse
+            && self.blink == other.blink
+            && self.hyperlink == other.hyperlink
+    }
+    fn copy_fields(&mut self, cell: &BufferCell) {
+        self.dirty = false;
+        self.ch = cell.character;
+        self.fg = foreground(cell);
+        self.bg = background(cell);
+        self.bold = cell.bold;
+        self.underline = cell.underline;
+        self.underline_kind = cell.underline_kind;
+        self.underline_colour = cell.underline_colour;
+        self.italic = cell.italic;
+        self.strikethrough = cell.strikethrough;
+        self.dim = cell.dim;
+        self.reverse = cell.reverse;
+        self.blink = cell.blink;
+        self.hyperlink = cell.hyperlink;
+    }
+    fn new() -> Self {
+        Self {
+            dirty: true,
+            ch: Grapheme::from_char(' '),
+            fg: Some(Rgb24::new_grey(0)),
+            bg: Some(Rgb24::new_grey(0)),
+            bold: false,
+            underline: false,
+            underline_kind: UnderlineKind::Single,
+            underline_colour: None,
+            italic: false,
+            strikethrough: false,
+            dim: false,
+            reverse: false,
+            blink: false,
+            hyperlink: None,
+        }
+    }
+}
+
+/// The text attributes currently enabled in the terminal
+#[derive(Default)]
+struct Attributes {
+    bold: bool,
+    underline: bool,
+    underline_kind: UnderlineKind,
+    underline_colour: Option<Rgb24>,
+    italic: bool,
+    strikethrough: bool,
+    dim: bool,
+    reverse: bool,
+    blink: bool,
+}
+
+impl Attributes {
+    /// Emit the escape sequences which change the current attributes to match those of `cell`.
+    /// Returns true iff the terminal's attributes were reset, in which case the colours must be
+    /// set again.
+    fn update<E>(&mut self, cell: &BufferCell, output: &mut Output, col_encode: &E) -> bool
+    where
+        E: ColEncode,
+    {
+        // there's no portable way to turn off bold, dim, reverse or blink without resetting
+        let reset = (self.bold && !cell.bold)
+            || (self.dim && !cell.dim)
+            || (self.reverse && !cell.reverse)
+            || (self.blink && !cell.blink);
+        if reset {
+            output.reset();
+            *self = Self::default();
+        }
+        if cell.bold && !self.bold {
+            output.set_bold();
+            self.bold = true;
+        }
+        if cell.dim && !self.dim {
+            output.set_dim();
+            self.dim = true;
+        }
+        if cell.reverse && !self.reverse {
+            output.set_reverse();
+            self.reverse = true;
+        }
+        if cell.blink && !self.blink {
+            output.set_blink();
+            self.blink = true;
+        }
+        let styled_underlines = output.styled_underlines();
+        let underline_kind = if styled_underlines {
+            cell.underline_kind
+        } else {
+            UnderlineKind::Single
+        };
+        if cell.underline != self.underline
+            || (cell.underline && underline_kind != self.underline_kind)
+        {
+            if !cell.underline {
+                output.clear_underline();
+            } else if styled_underlines {
+                output.set_underline_kind(underline_kind);
+            } else {
+                output.set_underline();
+            }
+            self.underline = cell.underline;
+            self.underline_kind = underline_kind;
+        }
+        if styled_underlines && cell.underline && cell.underline_colour != self.underline_colour {
+            // the colour is only recorded if the encoding could send it, so it's never cleared
+            // when it was never set
+            match cell.underline_colour {
+                Some(underline_colour) => {
+                    if output.set_underline_colour(col_encode, underline_colour) {
+                        self.underline_colour = Some(underline_colour);
+                    }
+                }
+                None => {
+                    output.clear_underline_colour();
+                    self.underline_colour = None;
+                }
+            }
+        }
+        if cell.italic != self.italic {
+            if cell.italic {
+                output.set_italic();
+            } else {
+                output.clear_italic();
+            }
+            self.italic = cell.italic;
+        }
+        if cell.strikethrough != self.strikethrough {
+            if cell.strikethrough {
+                output.set_strikethrough();
+            } else {
+                output.clear_strikethrough();
+            }
+            self.strikethrough = cell.strikethrough;
+        }
+        reset
+    }
+}
+
+/// The attributes and colours currently set in the terminal
+struct Pen {
+    attributes: Attributes,
+    fg: Option<Rgb24>,
+    bg: Option<Rgb24>,
+}
+
+impl Pen {
+    /// Emit the escape sequences which change the pen to match `cell`. If the state of the pen is
+    /// unknown, it's reset first. Returns true iff the terminal's attributes were reset.
+    fn update<E>(
+        pen: &mut Option<Self>,
+        cell: &BufferCell,
+        output: &mut Output,
+        col_encode: &E,
+    ) -> bool
+    where
+        E: ColEncode,
+    {
+        let mut reset = pen.is_none();
+        let pen = pen.get_or_insert_with(|| {
+            output.reset();
+            Pen {
+                attributes: Attributes::default(),
+                fg: foreground(cell),
+                bg: background(cell),
+            }
+        });
+        reset |= pen.attributes.update(cell, output, col_encode);
+        // resetting restores the default colours
+        let (fg, bg) = (foreground(cell), background(cell));
+        if (reset && fg.is_some()) || (!reset && fg != pen.fg) {
+            match fg {
+                Some(fg) => output.set_foreground_colour(col_encode, fg),
+                None => output.set_default_foreground_colour(),
+            }
+        }
+        if (reset && bg.is_some()) || (!reset && bg != pen.bg) {
+            match bg {
+                Some(bg) => output.set_background_colour(col_encode, bg),
+                None => output.set_default_background_colour(),
+            }
+        }
+        pen.fg = fg;
+        pen.bg = bg;
+        reset
+    }
+}
+
+/// Returns true iff `placement` can be drawn as a sixel image on a screen of `size` cells. Drawing
+/// sixels moves the cursor below the image, which scrolls the screen if the image reaches the last
+/// row. Disabling sixel scrolling (DECSDM) would prevent this, but also moves images to the top
+/// left of the screen, so such images are left out instead.
+fn sixel_fits(placement: &ImagePlacement, size: Size) -> bool {
+    placement.coord.y + (placement.size.height() as i32) < size.height() as i32
+}
+
+/// Returns the shortest sequence which moves the cursor from `from` to `to`, where `row` is the
+/// current contents of the row containing `to`, and `last_written` is the most recently drawn
+/// cell, whose style the terminal is currently using.
+fn cursor_motion(
+    from: Option<Coord>,
+    to: Coord,
+    row: &[OutputCell],
+    last_written: Option<&OutputCell>,
+    charset_encoder: &CharsetEncoder,
+    output: &mut Output,
+) -> Result<String> {
+    let mut best = output.cursor_absolute(to)?;
+    let from = match from {
+        Some(from) => from,
+        None => return Ok(best),
+    };
+    let mut consider = |candidate: String| {
+        if candidate.len() < best.len() {
+            best = candidate;
+        }
+    };
+    if let Some(relative) = output.cursor_relative(to - from)? {
+        consider(relative);
+    }
+    if let Some(carriage_return) = output.carriage_return().map(str::to_string) {
+        if let Some(relative) = output.cursor_relative(Coord::new(to.x, to.y - from.y))? {
+            consider(carriage_return + &relative);
+        }
+    }
+    // Moving right past cells which are already drawn in the current style can be done by drawing
+    // them again.
+    if let Some(last_written) = last_written {
+        if from.y == to.y && from.x < to.x {
+            let skipped = &row[from.x as usize..to.x as usize];
+            // dirty cells may be covered by an image, or contain something else entirely
+            if skipped.iter().all(|cell| {
+                !cell.dirty
+                    && cell.ch.width() == 1
+                    && !cell.ch.is_continuation()
+                    && charset_encoder.can_redraw(cell.ch)
+                    && cell.same_style(last_written)
+            }) {
+                consider(skipped.iter().map(|cell| cell.ch.as_str()).collect());
+            }
+        }
+    }
+    Ok(best)
+}
+
+/// Sends characters in the terminal's character set, keeping track of whether the terminal is
+/// drawing with its alternate character set
+struct CharsetEncoder {
+    charset: Charset,
+    transliteration: Transliteration,
+    /// Whether the terminal is drawing with its alternate character set, or `None` if it's unknown
+    alternate_charset: Option<bool>,
+}
+
+impl CharsetEncoder {
+    fn new(charset: Charset, transliteration: Transliteration) -> Self {
+        Self {
+            charset,
+            transliteration,
+            alternate_charset: None,
+        }
+    }
+
+    /// True iff sending the grapheme again draws it the same way as it's currently drawn. Only
+    /// ascii is sent unchanged outside of unicode, and not while the alternate character set may
+    /// be in use, as it changes how ascii characters are drawn.
+    fn can_redraw(&self, grapheme: Grapheme) -> bool {
+        match self.charset {
+            Charset::Unicode => true,
+            Charset::Ascii => grapheme.as_str().is_ascii(),
+            Charset::DecSpecialGraphics => {
+                grapheme.as_str().is_ascii() && self.alternate_charset == Some(false)
+            }
+        }
+    }
+
+    fn set_alternate_charset(&mut self, alternate_charset: bool, output: &mut Output) {
+        if self.alternate_charset != Some(alternate_charset) {
+            output.set_alternate_charset(alternate_charset);
+            self.alternate_charset = Some(alternate_charset);
+        }
+    }
+
+    /// Draw a grapheme at the cursor in a form the terminal can display. Graphemes drawn with a
+    /// single character in place of a double-width grapheme are padded with a space so the
+    /// terminal fills the same cells.
+    fn push_grapheme(&mut self, grapheme: Grapheme, output: &mut Output) {
+        let text = grapheme.as_str();
+        if self.charset == Charset::Unicode {
+            output.push_str(text);
+            return;
+        }
+        let ch = text.chars().next().unwrap_or(' ');
+        let alternate_charset_char = match self.charset {
+            Charset::DecSpecialGraphics if !text.is_ascii() => output.alternate_charset_char(ch),
+            _ => None,
+        };
+        let replacement = match alternate_charset_char {
+            Some(alternate_charset_char) => {
+                self.set_alternate_charset(true, output);
+                alternate_charset_char
+            }
+            None => {
+                if self.charset == Charset::DecSpecialGraphics {
+                    self.set_alternate_charset(false, output);
+                }
+                if text.is_ascii() {
+                    output.push_str(text);
+                    return;
+                }
+                self.transliteration.transliterate(ch)
+            }
+        };
+        output.push_str(replacement.encode_utf8(&mut [0; 4]));
+        if grapheme.width() == 2 {
+            output.push_str(" ");
+        }
+    }
+}
+
+/// Draws frames to the terminal by emitting the escape sequences and text needed to update the
+/// cells which changed since the previous frame
+pub struct FrameEncoder {
+    output_frame: grid_2d::Grid<OutputCell>,
+    pen: Option<Pen>,
+    cursor: Option<Coord>,
+    /// Whether the terminal is showing the cursor, or `None` if it's unknown
+    cursor_visible: Option<bool>,
+    /// The shape and blinking of the cursor, or `None` if it hasn't been set
+    cursor_shape: Option<(CursorShape, bool)>,
+    /// The hyperlink applied to characters as they're drawn. Links are closed at the end of each
+    /// frame so they don't leak into anything else written to the terminal.
+    hyperlink: Option<Hyperlink>,
+    last_written: Option<OutputCell>,
+    optimise_cursor_motion: bool,
+    image_protocol: Option<ImageProtocol>,
+    cell_pixel_size: Size,
+    /// The images on the screen, along with the ids of their kitty placements, or `None` if it's
+    /// unknown
+    placed_images: Option<Vec<(ImagePlacement, u32)>>,
+    /// The ids of the images whose pixels the terminal is storing (kitty only)
+    stored_images: Vec<u32>,
+    next_placement_id: u32,
+    charset_encoder: CharsetEncoder,
+}
+
+impl FrameEncoder {
+    pub fn new(size: Size) -> Self {
+        Self {
+            output_frame: grid_2d::Grid::new_fn(size, |_| OutputCell::new()),
+            pen: None,
+            cursor: None,
+            cursor_visible: None,
+            cursor_shape: None,
+            hyperlink: None,
+            last_written: None,
+            optimise_cursor_motion: true,
+            image_protocol: None,
+            cell_pixel_size: graphics::DEFAULT_CELL_PIXEL_SIZE,
+            placed_images: None,
+            stored_images: Vec::new(),
+            next_placement_id: 1,
+            charset_encoder: CharsetEncoder::new(Charset::Unicode, Transliteration::default()),
+        }
+    }
+
+    pub fn size(&self) -> Size {
+        self.output_frame.size()
+    }
+
+    /// Forget everything known about the terminal's contents and state, so the next frame is drawn
+    /// from scratch
+    pub fn invalidate(&mut self) {
+        for output_cell in self.output_frame.iter_mut() {
+            output_cell.dirty = true;
+        }
+        self.pen = None;
+        self.cursor = None;
+        self.cursor_visible = None;
+        self.cursor_shape = None;
+        self.last_written = None;
+        self.placed_images = None;
+        self.charset_encoder.alternate_charset = None;
+    }
+
+    /// When disabled, the cursor is always moved with absolute positioning, and runs of the same
+    /// character are never compressed
+    pub fn set_optimise_cursor_motion(&mut self, optimise_cursor_motion: bool) {
+        self.optimise_cursor_motion = optimise_cursor_motion;
+    }
+
+    /// Draw images with the given protocol (or not at all if it's `None`). Sixel images are scaled
+    /// to cover their cells assuming cells are `cell_pixel_size` pixels.
+    pub fn set_images(&mut self, image_protocol: Option<ImageProtocol>, cell_pixel_size: Size) {
+        self.image_protocol = image_protocol;
+        self.cell_pixel_size = cell_pixel_size;
+    }
+
+    /// Send characters in the given character set, replacing those the terminal can't display
+    /// using `transliteration`
+    pub fn set_charset(&mut self, charset: Charset, transliteration: Transliteration) {
+        self.charset_encoder = CharsetEncoder::new(charset, transliteration);
+    }
+
+    fn hide_cursor_while_drawing(&mut self, output: &mut Output) {
+        if self.cursor_visible != Some(false) {
+            // hide the cursor while drawing so it isn't seen moving between cells
+            output.hide_cursor();
+            self.cursor_visible = Some(false);
+        }
+    }
+
+    fn move_cursor(&mut self, coord: Coord, output: &mut Output) -> Result<()> {
+        if self.cursor != Some(coord) {
+            let motion = if self.optimise_cursor_motion {
+                let row = self.output_frame.rows().nth(coord.y as usize).unwrap();
+                cursor_motion(
+                    self.cursor,
+                    coord,
+                    row,
+                    self.last_written.as_ref(),
+                    &self.charset_encoder,
+                    output,
+                )?
+            } else {
+                output.cursor_absolute(coord)?
+            };
+            output.push_str(&motion);
+            self.cursor = Some(coord);
+        }
+        Ok(())
+    }
+
+    /// Remove the images which were on the screen but aren't part of `frame`. Cells beneath
+    /// removed sixel images are marked as dirty so they're drawn again.
+    fn remove_images(&mut self, frame: &Buffer, output: &mut Output) {
+        let image_protocol = match self.image_protocol {
+            Some(image_protocol) => image_protocol,
+            None => return,
+        };
+        let placed_images = match self.placed_images.take() {
+            Some(placed_images) => placed_images,
+            None => {
+                if image_protocol == ImageProtocol::Kitty {
+                    output.push_str(graphics::KITTY_DELETE_ALL);
+                }
+                self.stored_images.clear();
+                Vec::new()
+            }
+        };
+        let (kept, removed): (Vec<_>, Vec<_>) = placed_images
+            .into_iter()
+            .partition(|(placement, _)| frame.images().contains(placement));
+        for (placement, placement_id) in removed {
+            match image_protocol {
+                ImageProtocol::Kitty => {
+                    let image_id = placement.image.id();
+                    if frame.images().iter().any(|p| p.image == placement.image) {
+                        output.push_str(&graphics::kitty_delete_placement(image_id, placement_id));
+                    } else {
+                        output.push_str(&graphics::kitty_delete_image(image_id));
+                        self.stored_images.retain(|&id| id != image_id);
+                    }
+                }
+                ImageProtocol::Sixel => {
+                    for (coord, output_cell) in self.output_frame.enumerate_mut() {
+                        if placement.covers(coord) {
+                            output_cell.dirty = true;
+                        }
+                    }
+                }
+            }
+        }
+        self.placed_images = Some(kept);
+    }
+
+    /// Draw the images in `frame` which aren't already on the screen
+    fn draw_images(&mut self, frame: &Buffer, output: &mut Output) -> Result<()> {
+        let image_protocol = match self.image_protocol {
+            Some(image_protocol) => image_protocol,
+            None => return Ok(()),
+        };
+        let mut placed_images = self.placed_images.take().unwrap_or_default();
+        for placement in frame.images() {
+            if placed_images.iter().any(|(placed, _)| placed == placement) {
+                continue;
+            }
+            if image_protocol == ImageProtocol::Sixel && !sixel_fits(placement, self.size()) {
+                continue;
+            }
+            self.hide_cursor_while_drawing(output);
+            self.move_cursor(placement.coord, output)?;
+            let placement_id = self.next_placement_id;
+            self.next_placement_id = self.next_placement_id.checked_add(1).unwrap_or(1);
+            match image_protocol {
+                ImageProtocol::Kitty => {
+                    let image = &placement.image;
+                    let sequence = if self.stored_images.contains(&image.id()) {
+                        graphics::kitty_place(image.id(), placement_id, placement.size)
+                    } else {
+                        self.stored_images.push(image.id());
+                        graphics::kitty_transmit_and_place(image, placement_id, placement.size)
+                    };
+                    output.push_str(&sequence);
+                }
+                ImageProtocol::Sixel => {
+                    let pixel_size = Size::new(
+                        placement.size.width() * self.cell_pixel_size.width(),
+                        placement.size.height() * self.cell_pixel_size.height(),
+                    );
+                    output.push_str(&graphics::sixel(&placement.image, pixel_size));
+                    // terminals differ in where they leave the cursor after drawing sixels
+                    self.cursor = None;
+                    for (coord, output_cell) in self.output_frame.enumerate_mut() {
+                        if placement.covers(coord) {
+                            output_cell.dirty = true;
+                        }
+                    }
+                }
+            }
+            placed_images.push((placement.clone(), placement_id));
+        }
+        self.placed_images = Some(placed_images);
+        Ok(())
+    }
+
+    pub fn encode<E>(&mut self, frame: &Buffer, output: &mut Output, col_encode: &E) -> Result<()>
+    where
+        E: ColEncode,
+    {
+        self.remove_images(frame, output);
+        // sixel images are part of the text layer, so cells beneath them aren't drawn
+        let size = self.size();
+        let sixel_images = match self.image_protocol {
+            Some(ImageProtocol::Sixel) => frame
+                .images()
+                .iter()
+                .filter(|placement| sixel_fits(placement, size))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let covered = |coord: Coord| sixel_images.iter().any(|p| p.covers(coord));
+        for (y, row) in frame.rows().enumerate() {
+            let mut x = 0;
+            while x < row.len() {
+                let cell = &row[x];
+                let coord = Coord::new(x as i32, y as i32);
+                let output_cell = self.output_frame.get_checked_mut(coord);
+                if output_cell.matches(cell) || covered(coord) {
+                    x += 1;
+                    continue;
+                }
+                if cell.character.is_continuation() {
+                    // the terminal fills this cell when drawing the double-width grapheme to its left
+                    output_cell.copy_fields(cell);
+                    x += 1;
+                    continue;
+                }
+                self.hide_cursor_while_drawing(output);
+                self.move_cursor(coord, output)?;
+                if Pen::update(&mut self.pen, cell, output, col_encode) {
+                    // some terminals also leave the alternate character set when resetting
+                    self.charset_encoder.alternate_charset = None;
+                }
+                if cell.hyperlink != self.hyperlink {
+                    output.set_hyperlink(cell.hyperlink.and_then(|hyperlink| {
+                        frame
+                            .hyperlink_target(hyperlink)
+                            .map(|target| (hyperlink, target))
+                    }));
+                    self.hyperlink = cell.hyperlink;
+                }
+                let output_row = self.output_frame.rows_mut().nth(y).unwrap();
+                output_row[x].copy_fields(cell);
+                self.charset_encoder.push_grapheme(cell.character, output);
+                let mut repeated = 0;
+                if self.optimise_cursor_motion {
+                    let mut chars = cell.character.as_str().chars();
+                    if let (Some(ch), None) = (chars.next(), chars.next()) {
+                        let run = row[(x + 1)..]
+                            .iter()
+                            .enumerate()
+                            .take_while(|&(i, next)| {
+                                output_row[x].matches(next)
+                                    && !covered(Coord::new((x + 1 + i) as i32, y as i32))
+                            })
+                            .count();
+                        if let Some(repeat) = output.repeat_char(ch, run as u32)? {
+                            if repeat.len() < run * ch.len_utf8() {
+                                output.push_str(&repeat);
+                                repeated = run;
+                            }
+                        }
+                    }
+                }
+                for i in (x + 1)..=(x + repeated) {
+                    output_row[i].copy_fields(&row[i]);
+                }
+                self.last_written = Some(output_row[x].clone());
+                x += 1 + repeated;
+                // The cursor position is unknown after drawing to the last column, as terminals
+                // differ in when they wrap. It's also unknown after drawing a double-width grapheme
+                // in case the terminal disagrees about its width.
+                self.cursor = if cell.character.width() == 1 && x < row.len() {
+                    Some(Coord::new(x as i32, y as i32))
+                } else {
+                    None
+                };
+            }
+        }
+        if self.hyperlink.take().is_some() {
+            output.set_hyperlink(None);
+            // linked cells can no longer be drawn again to move the cursor past them
+            if let Some(last_written) = self.last_written.as_mut() {
+                last_written.hyperlink = None;
+            }
+        }
+        if self.charset_encoder.alternate_charset == Some(true) {
+            self.charset_encoder.set_alternate_charset(false, output);
+        }
+        self.draw_images(frame, output)?;
+        self.encode_cursor(frame.cursor(), output)
+    }
+
+    /// Show the cursor requested for the frame, or hide it if there was no request
+    fn encode_cursor(&mut self, requested: Option<Cursor>, output: &mut Output) -> Result<()> {
+        let requested = match requested {
+            Some(requested) if requested.coord.is_valid(self.size()) => requested,
+            _ => {
+                if self.cursor_visible != Some(false) {
+                    output.hide_cursor();
+                    self.cursor_visible = Some(false);
+                }
+                return Ok(());
+            }
+        };
+        self.move_cursor(requested.coord, output)?;
+        if self.cursor_visible != Some(true) {
+            output.show_cursor();
+            self.cursor_visible = Some(true);
+            // showing the cursor can reset whether it blinks (e.g. xterm's "cnorm" disables
+            // blinking), so its shape is set again afterwards
+            self.cursor_shape = None;
+        }
+        let shape = (requested.shape, requested.blink);
+        if self.cursor_shape != Some(shape) {
+            output.set_cursor_shape(requested.shape, requested.blink);
+            self.cursor_shape = Some(shape);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::col_encode;
+    use crate::terminal::term_info_cache::TermInfoCache;
+    use chargrid::app::{App, ColModify, ControlFlow, Duration, Frame};
+    use chargrid::input::Input;
+    use chargrid_headless::Step;
+
+    fn encode(encoder: &mut FrameEncoder, frame: &Buffer, ti_cache: &mut TermInfoCache) -> String {
+        let mut buffer = String::new();
+        encoder
+            .encode(
+                frame,
+                &mut Output::new(&mut buffer, ti_cache, false),
+                &col_encode::XtermTrueColour,
+            )
+            .unwrap();
+        buffer
+    }
+
+    fn buffer_from_rows(rows: &[&str]) -> Buffer {
+        let mut buffer = Buffer::new(Size::new(rows[0].chars().count() as u32, rows.len() as u32));
+        for (y, row) in rows.iter().enumerate() {
+            for (x, ch) in row.chars().enumerate() {
+                let view_cell = ViewCell::new().with_character(ch);
+                buffer.set_cell_absolute(Coord::new(x as i32, y as i32), 0, view_cell);
+            }
+        }
+        buffer
+    }
+
+    #[test]
+    fn cheapest_cursor_motion() {
+        let mut ti_cache = TermInfoCache::xterm_for_tests();
+        let mut encoder = FrameEncoder::new(Size::new(16, 3));
+        encode(
+            &mut encoder,
+            &buffer_from_rows(&["abcdefghijklmnop", "                ", "                "]),
+            &mut ti_cache,
+        );
+        // skipping a single cell is done by drawing it again
+        let frame = buffer_from_rows(&["aXcYefghijklmnop", "                ", "                "]);
+        assert_eq!(encode(&mut encoder, &frame, &mut ti_cache), "\x1b[1;2HXcY");
+        // the cursor position is unknown after drawing to the last column
+        let frame = buffer_from_rows(&["aXcYefghijklmnoZ", "W               ", "                "]);
+        assert_eq!(
+            encode(&mut encoder, &frame, &mut ti_cache),
+            "\x1b[11CZ\x1b[2;1HW"
+        );
+        // moving to the start of the next row uses a carriage return and line feed
+        let frame = buffer_from_rows(&["aXcYefghijklmnoZ", "W     V         ", "U               "]);
+        assert_eq!(encode(&mut encoder, &frame, &mut ti_cache), "\x1b[5CV\r\nU");
+    }
+
+    #[test]
+    fn repeated_characters() {
+        let mut ti_cache = TermInfoCache::xterm_for_tests();
+        let mut encoder = FrameEncoder::new(Size::new(16, 1));
+        encode(
+            &mut encoder,
+            &buffer_from_rows(&["                "]),
+            &mut ti_cache,
+        );
+        let frame = buffer_from_rows(&["  ==========    "]);
+        assert_eq!(
+            encode(&mut encoder, &frame, &mut ti_cache),
+            "\x1b[1;3H=\x1b[9b"
+        );
+    }
+
+    #[test]
+    fn cursor() {
+        let mut ti_cache = TermInfoCache::xterm_for_tests();
+        let mut encoder = FrameEncoder::new(Size::new(8, 2));
+        let mut frame = buffer_from_rows(&["abc     ", "        "]);
+        // the cursor's state is unknown at first, so it's hidden explicitly
+        assert!(encode(&mut encoder, &frame, &mut ti_cache).starts_with("\x1b[?25l"));
+        let cursor = Cursor::new(Coord::new(3, 0), CursorShape::Bar).with_blink(true);
+        frame.set_cursor_absolute(cursor);
+        assert_eq!(
+            encode(&mut encoder, &frame, &mut ti_cache),
+            "\x1b[1;4H\x1b[?12l\x1b[?25h\x1b[5 q"
+        );
+        // drawing hides the cursor, and its shape is set again after it's shown
+        let mut frame = buffer_from_rows(&["abcd    ", "        "]);
+        frame.set_cursor_absolute(cursor.with_coord(Coord::new(4, 0)));
+        assert_eq!(
+            encode(&mut encoder, &frame, &mut ti_cache),
+            "\x1b[?25ld\x1b[?12l\x1b[?25h\x1b[5 q"
+        );
+        // nothing changed
+        assert_eq!(encode(&mut encoder, &frame, &mut ti_cache), "");
+        let frame = buffer_from_rows(&["abcd    ", "        "]);
+        assert_eq!(encode(&mut encoder, &frame, &mut ti_cache), "\x1b[?25l");
+    }
+
+    #[test]
+    fn synchronized_updates() {
+        let mut ti_cache = TermInfoCache::xterm_for_tests();
+        let mut encoder = FrameEncoder::new(Size::new(4, 1));
+        encode(&mut encoder, &buffer_from_rows(&["    "]), &mut ti_cache);
+        let mut encode_synchronized = |frame: &Buffer| {
+            let mut buffer = "queued".to_string();
+            let mut output = Output::new(&mut buffer, &mut ti_cache, false);
+            let start = output.position();
+            encoder
+                .encode(frame, &mut output, &col_encode::XtermTrueColour)
+                .unwrap();
+            output.synchronize_since(start);
+            buffer
+        };
+        // only the frame's output is synchronized
+        assert_eq!(
+            encode_synchronized(&buffer_from_rows(&["ab  "])),
+            "queued\x1b[?2026h\x1b[1;1Hab\x1b[?2026l"
+        );
+        // frames which don't change the screen aren't synchronized
+        assert_eq!(encode_synchronized(&buffer_from_rows(&["ab  "])), "queued");
+    }
+
+    #[test]
+    fn hyperlinks() {
+        let mut ti_cache = TermInfoCache::xterm_for_tests();
+        let mut encoder = FrameEncoder::new(Size::new(8, 1));
+        encode(
+            &mut encoder,
+            &buffer_from_rows(&["        "]),
+            &mut ti_cache,
+        );
+        let mut frame = buffer_from_rows(&["a link  "]);
+        let link = frame.hyperlink("https://example.com");
+        for (x, ch) in "link".chars().enumerate() {
+            let view_cell = ViewCell::new().with_character(ch).with_hyperlink(link);
+            frame.set_cell_absolute(Coord::new(x as i32 + 2, 0), 0, view_cell);
+        }
+        let open = format!("\x1b]8;id={};https://example.com\x1b\\", link.id());
+        let close = "\x1b]8;;\x1b\\";
+        // the run of linked cells is wrapped in a single link which is closed before the frame ends
+        let output = encode(&mut encoder, &frame, &mut ti_cache);
+        assert!(output.contains(&format!("{}link{}", open, close)));
+        assert!(output.ends_with(close));
+        // redrawing a linked cell opens the link again
+        let mut frame = frame.clone();
+        let view_cell = ViewCell::new().with_character('L').with_hyperlink(link);
+        frame.set_cell_absolute(Coord::new(2, 0), 0, view_cell);
+        let output = encode(&mut encoder, &frame, &mut ti_cache);
+        assert!(output.ends_with(&format!("{}L{}", open, close)));
+        // a cell no longer linked is drawn without a link
+        let frame = buffer_from_rows(&["a Link  "]);
+        let output = encode(&mut encoder, &frame, &mut ti_cache);
+        assert!(!output.contains("\x1b]8"));
+    }
+
+    #[test]
+    fn hyperlink_closed_between_frames() {
+        let mut ti_cache = TermInfoCache::xterm_for_tests();
+        let mut encoder = FrameEncoder::new(Size::new(8, 1));
+        let linked_frame = |text: &str| {
+            let mut frame = buffer_from_rows(&[text]);
+            let link = frame.hyperlink("https://example.com");
+            for x in 0..5 {
+                let ch = text.chars().nth(x).unwrap();
+                let view_cell = ViewCell::new().with_character(ch).with_hyperlink(link);
+                frame.set_cell_absolute(Coord::new(x as i32, 0), 0, view_cell);
+            }
+            frame
+        };
+        encode(&mut encoder, &linked_frame("lllll   "), &mut ti_cache);
+        encode(&mut encoder, &linked_frame("LLlll   "), &mut ti_cache);
+        // the link was closed at the end of the last frame, so the cursor can't be moved past
+        // the linked cells by drawing them again
+        let output = encode(&mut encoder, &linked_frame("LLlllQ  "), &mut ti_cache);
+        assert!(!output.contains("lll"));
+        assert!(output.ends_with('Q'));
+    }
+
+    #[test]
+    fn underline_colours() {
+        let mut frame = Buffer::new(Size::new(1, 1));
+        let view_cell = ViewCell::new()
+            .with_character('a')
+            .with_underline(true)
+            .with_underline_colour(Rgb24::new(255, 0, 0));
+        frame.set_cell_absolute(Coord::new(0, 0), 0, view_cell);
+        let mut ti_cache = TermInfoCache::xterm_for_tests();
+        let mut encode_with = |col_encode: col_encode::Dynamic| {
+            let mut buffer = String::new();
+            FrameEncoder::new(frame.size())
+                .encode(
+                    &frame,
+                    &mut Output::new(&mut buffer, &mut ti_cache, true),
+                    &col_encode,
+                )
+                .unwrap();
+            buffer
+        };
+        // underline colours are sent in the same encoding as other colours
+        assert!(encode_with(col_encode::Dynamic::XtermTrueColour).contains("\x1b[58:2::255:0:0m"));
+        assert!(encode_with(col_encode::Dynamic::FromTermInfoRgb).contains("\x1b[58:5:196m"));
+        assert!(encode_with(col_encode::Dynamic::FromTermInfoAnsi16Colour).contains("\x1b[58:5:9m"));
+        let output = encode_with(col_encode::Dynamic::NoColour);
+        assert!(!output.contains("\x1b[58") && !output.contains("\x1b[59"));
+    }
+
+    #[test]
+    fn default_colours() {
+        let mut ti_cache = TermInfoCache::xterm_for_tests();
+        let mut encoder = FrameEncoder::new(Size::new(2, 1));
+        encode(&mut encoder, &buffer_from_rows(&["  "]), &mut ti_cache);
+        let mut frame = buffer_from_rows(&["  "]);
+        let view_cell = ViewCell::new()
+            .with_character('a')
+            .with_default_foreground(true)
+            .with_default_background(true);
+        frame.set_cell_absolute(Coord::new(0, 0), 0, view_cell);
+        // cells in the terminal's default colours are drawn with sgr 39 and 49
+        let output = encode(&mut encoder, &frame, &mut ti_cache);
+        assert!(output.contains("\x1b[39m"));
+        assert!(output.contains("\x1b[49m"));
+        // cells in explicit colours aren't
+        let output = encode(&mut encoder, &buffer_from_rows(&["bb"]), &mut ti_cache);
+        assert!(!output.contains("\x1b[39m"));
+        assert!(!output.contains("\x1b[49m"));
+    }
+
+    #[test]
+    fn charsets() {
+        let mut ti_cache = TermInfoCache::xterm_for_tests();
+        let frame = buffer_from_rows(&["┌─a┐"]);
+        let mut encoder = FrameEncoder::new(Size::new(4, 1));
+        encoder.set_charset(Charset::Ascii, Transliteration::default());
+        let output = encode(&mut encoder, &frame, &mut ti_cache);
+        assert!(output.contains("+-a+"));
+        assert!(output.is_ascii());
+        // box-drawing characters are drawn with the alternate character set, which is left
+        // before drawing ascii and at the end of the frame
+        let mut encoder = FrameEncoder::new(Size::new(4, 1));
+        encoder.set_charset(Charset::DecSpecialGraphics, Transliteration::default());
+        let output = encode(&mut encoder, &frame, &mut ti_cache);
+        assert!(output.contains("\x1b(0lq\x1b(Ba\x1b(0k\x1b(B"));
+        // characters outside the DEC special graphics set are transliterated, and double-width
+        // characters are padded to fill both cells
+        let mut frame = Buffer::new(Size::new(4, 1));
+        frame.set_cell_absolute(Coord::new(0, 0), 0, ViewCell::new().with_character('日'));
+        frame.set_cell_absolute(Coord::new(2, 0), 0, ViewCell::new().with_character('…'));
+        let transliteration = Transliteration::new().with('…', '~');
+        let mut encoder = FrameEncoder::new(Size::new(4, 1));
+        encoder.set_charset(Charset::DecSpecialGraphics, transliteration);
+        let output = encode(&mut encoder, &frame, &mut ti_cache);
+        assert!(output.contains("? "));
+        assert!(output.ends_with("~ "));
+        assert!(output.is_ascii());
+    }
+
+    #[test]
+    fn kitty_images() {
+        let mut ti_cache = TermInfoCache::xterm_for_tests();
+        let mut encoder = FrameEncoder::new(Size::new(8, 2));
+        encoder.set_images(Some(ImageProtocol::Kitty), Size::new(10, 20));
+        let image = Image::from_fn(Size::new(4, 4), |_| [0, 255, 0, 255]);
+        let size = Size::new(2, 1);
+        let mut frame = buffer_from_rows(&["abcdefgh", "ijklmnop"]);
+        frame.draw_image_absolute(ImagePlacement::new(image.clone(), Coord::new(1, 0), size));
+        // the screen may have images from before, so they're all removed first
+        let output = encode(&mut encoder, &frame, &mut ti_cache);
+        assert!(output.starts_with(graphics::KITTY_DELETE_ALL));
+        // kitty images are drawn over the text, so the cells beneath them are drawn too
+        assert!(output.contains("abcdefgh"));
+        let transmit = graphics::kitty_transmit_and_place(&image, 1, size);
+        assert!(output.ends_with(&format!("\x1b[1;2H{}", transmit)));
+        assert_eq!(encode(&mut encoder, &frame, &mut ti_cache), "");
+        // moving the image doesn't send its pixels again
+        let mut frame = buffer_from_rows(&["abcdefgh", "ijklmnop"]);
+        frame.draw_image_absolute(ImagePlacement::new(image.clone(), Coord::new(3, 1), size));
+        assert_eq!(
+            encode(&mut encoder, &frame, &mut ti_cache),
+            format!(
+                "{}\n\x1b[2C{}",
+                graphics::kitty_delete_placement(image.id(), 1),
+                graphics::kitty_place(image.id(), 2, size)
+            )
+        );
+        let frame = buffer_from_rows(&["abcdefgh", "ijklmnop"]);
+        assert_eq!(
+            encode(&mut encoder, &frame, &mut ti_cache),
+            graphics::kitty_delete_image(image.id())
+        );
+    }
+
+    #[test]
+    fn sixel_images() {
+        let mut ti_cache = TermInfoCache::xterm_for_tests();
+        let mut encoder = FrameEncoder::new(Size::new(4, 2));
+        encoder.set_images(Some(ImageProtocol::Sixel), Size::new(1, 1));
+        let image = Image::from_fn(Size::new(2, 1), |_| [0, 0, 255, 255]);
+        let placement = ImagePlacement::new(image.clone(), Coord::new(1, 0), Size::new(2, 1));
+        let mut frame = buffer_from_rows(&["abcd", "efgh"]);
+        frame.draw_image_absolute(placement.clone());
+        // cells beneath sixel images aren't drawn, as they would draw over the image
+        let output = encode(&mut encoder, &frame, &mut ti_cache);
+        assert!(!output.contains('b') && !output.contains('c'));
+        assert!(output.ends_with(&format!(
+            "\x1b[1;2H{}",
+            graphics::sixel(&image, Size::new(2, 1))
+        )));
+        assert_eq!(encode(&mut encoder, &frame, &mut ti_cache), "");
+        let mut frame = buffer_from_rows(&["aXcd", "efgh"]);
+        frame.draw_image_absolute(placement);
+        assert_eq!(encode(&mut encoder, &frame, &mut ti_cache), "");
+        // the cells are drawn again once the image is removed
+        let frame = buffer_from_rows(&["aXcd", "efgh"]);
+        assert!(encode(&mut encoder, &frame, &mut ti_cache).ends_with("Xc"));
+        // images reaching the last row would scroll the screen, so they're left out and the cells
+        // beneath them are drawn
+        let mut frame = buffer_from_rows(&["aXcd", "eYgh"]);
+        let placement = ImagePlacement::new(image, Coord::new(1, 1), Size::new(2, 1));
+        frame.draw_image_absolute(placement);
+        assert_eq!(encode(&mut encoder, &frame, &mut ti_cache), "\n\x08\x08Y");
+    }
+
+    /// A log viewer which scrolls as messages arrive, with a title bar and a progress bar
+    struct LogViewer {
+        frame_count: usize,
+    }
+
+    impl App for LogViewer {
+        fn on_input(&mut self, _input: Input) -> Option<ControlFlow> {
+            None
+        }
+        fn on_frame<F, C>(
+            &mut self,
+            _since_last_frame: Duration,
+            view_context: ViewContext<C>,
+            frame: &mut F,
+        ) -> Option<ControlFlow>
+        where
+            F: Frame,
+            C: ColModify,
+        {
+            let size = view_context.size;
+            let mut draw_line = |y: i32, text: &str, style: Style| {
+                for (x, ch) in text.chars().chain(std::iter::repeat(' ')).enumerate() {
+                    if x as u32 >= size.width() {
+                        break;
+                    }
+                    let view_cell = ViewCell::new().with_character(ch).with_style(style);
+                    frame.set_cell_relative(Coord::new(x as i32, y), 0, view_cell, view_context);
+                }
+            };
+            let title_style = Style::new()
+                .with_bold(true)
+                .with_foreground(Rgb24::new_grey(255))
+                .with_background(Rgb24::new(0, 0, 128));
+            draw_line(0, " chargrid log viewer", title_style);
+            let num_log_lines = size.height() as usize - 2;
+            let num_messages = self.frame_count / 2;
+            let first_message = num_messages.saturating_sub(num_log_lines);
+            for (y, i) in (first_message..num_messages).enumerate() {
+                let (level, colour) = match i % 7 {
+                    0 => ("ERROR", Rgb24::new(255, 64, 64)),
+                    1 | 4 => ("WARN ", Rgb24::new(255, 192, 0)),
+                    _ => ("INFO ", Rgb24::new_grey(192)),
+                };
+                let text = format!(
+                    "{:>5} {} request {} handled in {}ms",
+                    i,
+                    level,
+                    (i * 7919) % 10000,
+                    (i * 37) % 100
+                );
+                draw_line(y as i32 + 1, &text, Style::new().with_foreground(colour));
+            }
+            let progress = (self.frame_count * 40) / 120;
+            let status = format!(
+                " [{}{}] frame {}",
+                "#".repeat(progress),
+                ".".repeat(40 - progress),
+                self.frame_count
+            );
+            draw_line(
+                size.height() as i32 - 1,
+                &status,
+                Style::new().with_reverse(true),
+            );
+            self.frame_count += 1;
+            None
+        }
+    }
+
+    fn total_bytes(frames: &[Buffer], optimise_cursor_motion: bool) -> usize {
+        let mut ti_cache = TermInfoCache::xterm_for_tests();
+        let mut encoder = FrameEncoder::new(frames[0].size());
+        encoder.set_optimise_cursor_motion(optimise_cursor_motion);
+        frames
+            .iter()
+            .map(|frame| encode(&mut encoder, frame, &mut ti_cache).len())
+            .sum()
+    }
+
+    /// The number of bytes sent to the terminal while drawing a recorded sequence of frames. The
+    /// counts are recorded so that changes to the encoding which send more bytes are noticed. If a
+    /// change is intended to reduce the count, update the recorded count to match.
+    #[test]
+    fn byte_count() {
+        let script = (0..120).map(|_| Step::Frame(Duration::from_millis(16)));
+        let capture = chargrid_headless::Context::new(Size::new(80, 24))
+            .run_app(LogViewer { frame_count: 0 }, script);
+        assert_eq!(capture.frames.len(), 120);
+        assert_eq!(total_bytes(&capture.frames, false), 76456);
+        assert_eq!(total_bytes(&capture.frames, true), 51743);
+    }
+}
@@ -0,0 +1,232 @@
+//! Encoding images with the kitty graphics protocol and sixel
+
+use chargrid_render::*;
+use std::fmt::Write;
+
+/// Asks the terminal whether it supports the kitty graphics protocol by checking whether it
+/// could display a 1x1 image. Only terminals which support the protocol respond.
+pub const QUERY_KITTY_GRAPHICS: &str = "\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\";
+/// The id of the image in `QUERY_KITTY_GRAPHICS`, which identifies the terminal's response
+pub const KITTY_GRAPHICS_QUERY_ID: u32 = 31;
+/// Asks the terminal for the size of a cell in pixels (XTWINOPS)
+pub const QUERY_CELL_PIXEL_SIZE: &str = "\x1b[16t";
+/// The size of a cell in pixels when the terminal doesn't report it
+pub const DEFAULT_CELL_PIXEL_SIZE: Size = Size::new_u16(10, 20);
+/// Removes every image from the screen and frees the terminal's copies of their pixels
+pub const KITTY_DELETE_ALL: &str = "\x1b_Ga=d,d=A,q=2\x1b\\";
+
+// the protocol limits the size of the payload of each escape sequence
+const KITTY_CHUNK_SIZE: usize = 4096;
+// the number of levels of each channel in the sixel palette
+const SIXEL_LEVELS: u32 = 6;
+// pixels less opaque than this are left transparent
+const SIXEL_ALPHA_THRESHOLD: u8 = 128;
+
+/// Ways of drawing images in a terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageProtocol {
+    /// The kitty graphics protocol, which draws images on a layer above the text, scaled by the
+    /// terminal
+    Kitty,
+    /// Sixel graphics, which paint over the cells beneath them
+    Sixel,
+}
+
+/// Send the pixels of `image` to the terminal and display it at the cursor, scaled to cover
+/// `size` cells, without moving the cursor. The terminal keeps the pixels, so the image can be
+/// displayed again with `kitty_place`.
+pub fn kitty_transmit_and_place(image: &Image, placement_id: u32, size: Size) -> String {
+    let data = base64::encode(image.rgba_bytes());
+    let mut chunks = data.as_bytes().chunks(KITTY_CHUNK_SIZE).peekable();
+    let mut output = format!(
+        "\x1b_Ga=T,f=32,s={},v={},i={},p={},c={},r={},C=1,q=2",
+        image.size().width(),
+        image.size().height(),
+        image.id(),
+        placement_id,
+        size.width(),
+        size.height(),
+    );
+    let mut first = true;
+    while let Some(chunk) = chunks.next() {
+        if !first {
+            output.push_str("\x1b_G");
+        }
+        let more = if chunks.peek().is_some() { 1 } else { 0 };
+        let separator = if first { "," } else { "" };
+        write!(output, "{}m={};", separator, more).unwrap();
+        // base64 is ascii
+        output.push_str(std::str::from_utf8(chunk).unwrap());
+        output.push_str("\x1b\\");
+        first = false;
+    }
+    output
+}
+
+/// Display an image whose pixels were already sent to the terminal at the cursor, scaled to
+/// cover `size` cells, without moving the cursor
+pub fn kitty_place(image_id: u32, placement_id: u32, size: Size) -> String {
+    format!(
+        "\x1b_Ga=p,i={},p={},c={},r={},C=1,q=2\x1b\\",
+        image_id,
+        placement_id,
+        size.width(),
+        size.height(),
+    )
+}
+
+/// Remove a single placement of an image from the screen, keeping its pixels
+pub fn kitty_delete_placement(image_id: u32, placement_id: u32) -> String {
+    format!("\x1b_Ga=d,d=i,i={},p={},q=2\x1b\\", image_id, placement_id)
+}
+
+/// Remove every placement of an image from the screen, and free the terminal's copy of its pixels
+pub fn kitty_delete_image(image_id: u32) -> String {
+    format!("\x1b_Ga=d,d=I,i={},q=2\x1b\\", image_id)
+}
+
+/// The index of the palette entry closest to an opaque colour
+fn sixel_palette_index([r, g, b, _]: [u8; 4]) -> usize {
+    let level = |channel: u8| (channel as u32 * SIXEL_LEVELS / 256) as usize;
+    let levels = SIXEL_LEVELS as usize;
+    (level(r) * levels + level(g)) * levels + level(b)
+}
+
+fn push_sixel_run(output: &mut String, sixel: char, count: usize) {
+    if count > 3 {
+        write!(output, "!{}{}", count, sixel).unwrap();
+    } else {
+        for _ in 0..count {
+            output.push(sixel);
+        }
+    }
+}
+
+/// Encode `image` as sixel graphics scaled to `pixel_size` pixels, which is drawn with its
+/// top-left corner at the cursor. Colours are reduced to a palette of 216 colours, and
+/// transparent pixels leave whatever is beneath them.
+pub fn sixel(image: &Image, pixel_size: Size) -> String {
+    let width = pixel_size.width() as usize;
+    let height = pixel_size.height() as usize;
+    let image_size = image.size();
+    // nearest-neighbour scaling
+    let indices = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let source = Coord::new(
+                (x * image_size.width() as usize / width.max(1)) as i32,
+                (y * image_size.height() as usize / height.max(1)) as i32,
+            );
+            image
+                .get(source)
+                .filter(|pixel| pixel[3] >= SIXEL_ALPHA_THRESHOLD)
+                .map(sixel_palette_index)
+        })
+        .collect::<Vec<_>>();
+    let levels = SIXEL_LEVELS as usize;
+    let mut used = vec![false; levels * levels * levels];
+    for &index in indices.iter().flatten() {
+        used[index] = true;
+    }
+    // P2 = 1 leaves pixels which aren't drawn unchanged
+    let mut output = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+    for (index, _) in used.iter().enumerate().filter(|&(_, &used)| used) {
+        let percent = |level: usize| level * 100 / (levels - 1);
+        write!(
+            output,
+            "#{};2;{};{};{}",
+            index,
+            percent(index / (levels * levels)),
+            percent((index / levels) % levels),
+            percent(index % levels),
+        )
+        .unwrap();
+    }
+    for band_top in (0..height).step_by(6) {
+        let band_rows = (height - band_top).min(6);
+        let band = &indices[(band_top * width)..((band_top + band_rows) * width)];
+        let mut colours = band.iter().flatten().cloned().collect::<Vec<_>>();
+        colours.sort_unstable();
+        colours.dedup();
+        for (i, &colour) in colours.iter().enumerate() {
+            if i > 0 {
+                // return to the start of the band to draw the next colour
+                output.push('$');
+            }
+            write!(output, "#{}", colour).unwrap();
+            let mut run: Option<(char, usize)> = None;
+            for x in 0..width {
+                let bits = (0..band_rows)
+                    .filter(|&row| band[row * width + x] == Some(colour))
+                    .fold(0, |bits, row| bits | (1 << row));
+                let sixel = (63 + bits) as u8 as char;
+                run = match run {
+                    Some((run_sixel, count)) if run_sixel == sixel => Some((sixel, count + 1)),
+                    Some((run_sixel, count)) => {
+                        push_sixel_run(&mut output, run_sixel, count);
+                        Some((sixel, 1))
+                    }
+                    None => Some((sixel, 1)),
+                };
+            }
+            // trailing empty sixels can be left out
+            if let Some((run_sixel, count)) = run.filter(|&(run_sixel, _)| run_sixel != '?') {
+                push_sixel_run(&mut output, run_sixel, count);
+            }
+        }
+        if band_top + 6 < height {
+            output.push('-');
+        }
+    }
+    output.push_str("\x1b\\");
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kitty() {
+        let image = Image::from_fn(Size::new(1, 1), |_| [255, 0, 0, 255]);
+        assert_eq!(
+            kitty_transmit_and_place(&image, 7, Size::new(2, 1)),
+            format!(
+                "\x1b_Ga=T,f=32,s=1,v=1,i={},p=7,c=2,r=1,C=1,q=2,m=0;/wAA/w==\x1b\\",
+                image.id()
+            )
+        );
+        // large images are sent in chunks
+        let image = Image::from_fn(Size::new(64, 64), |_| [0, 0, 0, 0]);
+        let output = kitty_transmit_and_place(&image, 1, Size::new(4, 2));
+        assert_eq!(output.matches("\x1b_G").count(), 6);
+        assert_eq!(output.matches("m=1;").count(), 5);
+        assert!(output.contains("\x1b_Gm=0;"));
+        assert_eq!(
+            kitty_place(3, 4, Size::new(5, 6)),
+            "\x1b_Ga=p,i=3,p=4,c=5,r=6,C=1,q=2\x1b\\"
+        );
+    }
+
+    #[test]
+    fn sixel_encoding() {
+        // a red column beside a transparent one, 7 pixels tall so it spans two bands
+        let image = Image::from_fn(Size::new(2, 7), |coord| {
+            if coord.x == 0 {
+                [255, 0, 0, 255]
+            } else {
+                [0, 0, 0, 0]
+            }
+        });
+        assert_eq!(
+            sixel(&image, Size::new(2, 7)),
+            "\x1bP0;1;0q\"1;1;2;7#180;2;100;0;0#180~-#180@\x1b\\"
+        );
+        // scaled to twice the width, with the same colour repeated
+        let image = Image::from_fn(Size::new(2, 1), |_| [0, 0, 255, 255]);
+        assert_eq!(
+            sixel(&image, Size::new(4, 1)),
+            "\x1bP0;1;0q\"1;1;4;1#5;2;0;0;100#5!4@\x1b\\"
+        );
+    }
+}
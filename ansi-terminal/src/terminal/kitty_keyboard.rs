@@ -0,0 +1,203 @@
+//! Decoding of key events reported with the kitty keyboard protocol
+//! (https://sw.kovidgoyal.net/kitty/keyboard-protocol/)
+
+use chargrid_input::{keys, Input, KeyModifiers, KeyboardInput, ModifiedKeyboardInput};
+
+/// Disambiguate escape codes, report event types, report alternate keys, and report all keys as
+/// escape codes. The last is needed to receive release events for keys which produce text.
+pub const FLAGS: u32 = 0b1111;
+pub const QUERY_FLAGS: &str = "\x1b[?u";
+pub const POP_FLAGS: &str = "\x1b[<u";
+
+pub fn push_flags() -> String {
+    format!("\x1b[>{}u", FLAGS)
+}
+
+const CSI: &[u8] = b"\x1b[";
+
+const EVENT_TYPE_REPEAT: u32 = 2;
+const EVENT_TYPE_RELEASE: u32 = 3;
+
+// Key codes of functional keys which are reported in the unicode private use area
+const F13: u32 = 57376;
+const F35: u32 = 57398;
+const KP_0: u32 = 57399;
+const KP_9: u32 = 57408;
+const KP_DECIMAL: u32 = 57409;
+const KP_DIVIDE: u32 = 57410;
+const KP_MULTIPLY: u32 = 57411;
+const KP_SUBTRACT: u32 = 57412;
+const KP_ADD: u32 = 57413;
+const KP_ENTER: u32 = 57414;
+const KP_EQUAL: u32 = 57415;
+
+/// Parameters of a CSI sequence, where each parameter may have colon-separated sub-parameters
+fn parse_params(bytes: &[u8]) -> Vec<Vec<Option<u32>>> {
+    bytes
+        .split(|&b| b == b';')
+        .map(|param| {
+            param
+                .split(|&b| b == b':')
+                .map(|sub_param| {
+                    if sub_param.is_empty() {
+                        None
+                    } else {
+                        Some(sub_param.iter().fold(0u32, |acc, &b| {
+                            acc.saturating_mul(10).saturating_add((b - b'0') as u32)
+                        }))
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn key_from_code(code: u32) -> Option<KeyboardInput> {
+    let key = match code {
+        9 => keys::TAB,
+        13 | KP_ENTER => keys::RETURN,
+        27 => keys::ESCAPE,
+        127 => keys::BACKSPACE,
+        F13..=F35 => KeyboardInput::Function((code - F13 + 13) as u8),
+        KP_0..=KP_9 => KeyboardInput::Char((b'0' + (code - KP_0) as u8) as char),
+        KP_DECIMAL => KeyboardInput::Char('.'),
+        KP_DIVIDE => KeyboardInput::Char('/'),
+        KP_MULTIPLY => KeyboardInput::Char('*'),
+        KP_SUBTRACT => KeyboardInput::Char('-'),
+        KP_ADD => KeyboardInput::Char('+'),
+        KP_EQUAL => KeyboardInput::Char('='),
+        // the remaining functional keys (e.g. modifier keys on their own) have no equivalent
+        57344..=63743 => return None,
+        _ => KeyboardInput::Char(std::char::from_u32(code)?),
+    };
+    Some(key)
+}
+
+fn key_from_tilde_number(number: u32) -> Option<KeyboardInput> {
+    let key = match number {
+        3 => KeyboardInput::Delete,
+        5 => KeyboardInput::PageUp,
+        6 => KeyboardInput::PageDown,
+        7 => KeyboardInput::Home,
+        8 => KeyboardInput::End,
+        11 => KeyboardInput::Function(1),
+        12 => KeyboardInput::Function(2),
+        13 => KeyboardInput::Function(3),
+        14 => KeyboardInput::Function(4),
+        15 => KeyboardInput::Function(5),
+        17 => KeyboardInput::Function(6),
+        18 => KeyboardInput::Function(7),
+        19 => KeyboardInput::Function(8),
+        20 => KeyboardInput::Function(9),
+        21 => KeyboardInput::Function(10),
+        23 => KeyboardInput::Function(11),
+        24 => KeyboardInput::Function(12),
+        _ => return None,
+    };
+    Some(key)
+}
+
+fn key_from_final_byte(final_byte: u8) -> Option<KeyboardInput> {
+    let key = match final_byte {
+        b'A' => KeyboardInput::Up,
+        b'B' => KeyboardInput::Down,
+        b'C' => KeyboardInput::Right,
+        b'D' => KeyboardInput::Left,
+        b'H' => KeyboardInput::Home,
+        b'F' => KeyboardInput::End,
+        b'P' => KeyboardInput::Function(1),
+        b'Q' => KeyboardInput::Function(2),
+        b'S' => KeyboardInput::Function(4),
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Parse a key event from the start of `bytes`. Returns the input (if the key has an equivalent
+/// `KeyboardInput`) and the length of the sequence, or `None` if `bytes` doesn't start with a
+/// complete key event.
+pub fn parse(bytes: &[u8]) -> Option<(Option<Input>, usize)> {
+    if !bytes.starts_with(CSI) {
+        return None;
+    }
+    let body = &bytes[CSI.len()..];
+    let params_len = body
+        .iter()
+        .position(|&b| !(b.is_ascii_digit() || b == b';' || b == b':'))?;
+    let final_byte = body[params_len];
+    let params = parse_params(&body[..params_len]);
+    let param = |index: usize, sub_index: usize| {
+        params
+            .get(index)
+            .and_then(|param| param.get(sub_index).cloned().flatten())
+    };
+    let key = match final_byte {
+        b'u' => {
+            // prefer the shifted key if the terminal reported one
+            let code = param(0, 1).or_else(|| param(0, 0))?;
+            key_from_code(code)
+        }
+        b'~' => key_from_tilde_number(param(0, 0)?),
+        _ => Some(key_from_final_byte(final_byte)?),
+    };
+    let modifiers = KeyModifiers::from_bits(param(1, 0).unwrap_or(1).saturating_sub(1) as u8);
+    let input = key.map(|key| match param(1, 1) {
+        Some(EVENT_TYPE_REPEAT) => {
+            Input::KeyRepeat(ModifiedKeyboardInput::normalised(key, modifiers))
+        }
+        Some(EVENT_TYPE_RELEASE) => {
+            Input::KeyRelease(ModifiedKeyboardInput::normalised(key, modifiers))
+        }
+        _ => Input::keyboard_with_modifiers(key, modifiers),
+    });
+    Some((input, CSI.len() + params_len + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_input(bytes: &[u8]) -> Input {
+        let (input, len) = parse(bytes).unwrap();
+        assert_eq!(len, bytes.len());
+        input.unwrap()
+    }
+
+    #[test]
+    fn key_events() {
+        assert_eq!(parse_input(b"\x1b[27u"), Input::Keyboard(keys::ESCAPE));
+        assert_eq!(
+            parse_input(b"\x1b[97:65;2u"),
+            Input::Keyboard(KeyboardInput::Char('A'))
+        );
+        assert_eq!(
+            parse_input(b"\x1b[99;5u"),
+            Input::ModifiedKeyboard(ModifiedKeyboardInput::new(
+                KeyboardInput::Char('c'),
+                KeyModifiers::CTRL
+            ))
+        );
+        assert_eq!(
+            parse_input(b"\x1b[1;1:2D"),
+            Input::KeyRepeat(ModifiedKeyboardInput::new(
+                KeyboardInput::Left,
+                KeyModifiers::NONE
+            ))
+        );
+        assert_eq!(
+            parse_input(b"\x1b[24;3:3~"),
+            Input::KeyRelease(ModifiedKeyboardInput::new(
+                KeyboardInput::Function(12),
+                KeyModifiers::ALT
+            ))
+        );
+        // caps lock is ignored
+        assert_eq!(
+            parse_input(b"\x1b[57399;65u"),
+            Input::Keyboard(KeyboardInput::Char('0'))
+        );
+        // left shift on its own
+        assert_eq!(parse(b"\x1b[57441;2u"), Some((None, 10)));
+        assert_eq!(parse(b"\x1b[57441;2"), None);
+    }
+}
@@ -1,3 +1,4 @@
+use super::stream::{self, StreamSize};
 use crate::error::{Error, Result};
 use chargrid_render::*;
 use libc;
@@ -5,6 +6,8 @@ use std::fs::{File, OpenOptions};
 use std::io::{self, Read, Write};
 use std::mem::MaybeUninit;
 use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
 
 struct WinSize {
     ws_row: libc::c_ushort,
@@ -13,10 +16,23 @@ struct WinSize {
     _ws_ypixel: libc::c_ushort,
 }
 
+enum Device {
+    Tty {
+        tty_file: File,
+        original_termios: libc::termios,
+    },
+    /// Input is read from `input`, which is fed by a thread reading from the original input
+    /// stream, so it can be waited on like a tty
+    Stream {
+        input: UnixStream,
+        output: Box<dyn Write>,
+        size: StreamSize,
+    },
+}
+
 pub struct LowLevel {
-    tty_file: File,
-    tty_fd: RawFd,
-    original_termios: libc::termios,
+    device: Device,
+    input_fd: RawFd,
 }
 
 impl LowLevel {
@@ -26,8 +42,12 @@ impl LowLevel {
         if res != 0 {
             return Err(Error::last_os_error());
         }
-        let mut termios = unsafe { termios.assume_init() };
-        let original_termios = termios;
+        let original_termios = unsafe { termios.assume_init() };
+        Self::set_termios(fd, &Self::raw_termios(original_termios))?;
+        Ok(original_termios)
+    }
+
+    fn raw_termios(mut termios: libc::termios) -> libc::termios {
         termios.c_iflag &= !(libc::IGNBRK
             | libc::BRKINT
             | libc::PARMRK
@@ -42,25 +62,60 @@ impl LowLevel {
         termios.c_cflag |= libc::CS8;
         termios.c_cc[libc::VMIN] = 0;
         termios.c_cc[libc::VTIME] = 0;
-        let res = unsafe { libc::tcsetattr(fd, libc::TCSAFLUSH, &termios) };
+        termios
+    }
+
+    fn set_termios(fd: RawFd, termios: &libc::termios) -> Result<()> {
+        let res = unsafe { libc::tcsetattr(fd, libc::TCSAFLUSH, termios) };
         if res != 0 {
             return Err(Error::last_os_error());
         }
-        Ok(original_termios)
+        Ok(())
     }
 
     pub fn new() -> Result<Self> {
         let tty_file = OpenOptions::new().write(true).read(true).open("/dev/tty")?;
-        let tty_fd = tty_file.as_raw_fd();
-        let original_termios = Self::init_tty(tty_fd)?;
+        let input_fd = tty_file.as_raw_fd();
+        let original_termios = Self::init_tty(input_fd)?;
         Ok(Self {
-            tty_file,
-            original_termios,
-            tty_fd,
+            device: Device::Tty {
+                tty_file,
+                original_termios,
+            },
+            input_fd,
         })
     }
 
+    pub fn from_streams<R, W>(input: R, output: W, size: StreamSize) -> Result<Self>
+    where
+        R: Read + Send + 'static,
+        W: Write + 'static,
+    {
+        let input = stream::forward_input(input)?;
+        let input_fd = input.as_raw_fd();
+        Ok(Self {
+            device: Device::Stream {
+                input,
+                output: Box::new(output),
+                size,
+            },
+            input_fd,
+        })
+    }
+
+    /// The size source of a terminal connected by streams
+    pub fn stream_size(&self) -> Option<&StreamSize> {
+        match &self.device {
+            Device::Tty { .. } => None,
+            Device::Stream { size, .. } => Some(size),
+        }
+    }
+
     pub fn size(&self) -> Result<Size> {
+        let tty_fd = match &self.device {
+            Device::Tty { .. } => self.input_fd,
+            Device::Stream { size, .. } => return Ok(size.get()),
+        };
         let mut win_size = WinSize {
             ws_row: 0,
             ws_col: 0,
@@ -68,7 +123,7 @@ impl LowLevel {
             _ws_ypixel: 0,
         };
         unsafe {
-            libc::ioctl(self.tty_fd, libc::TIOCGWINSZ.into(), &mut win_size);
+            libc::ioctl(tty_fd, libc::TIOCGWINSZ.into(), &mut win_size);
         }
 
         if win_size.ws_row == 0 || win_size.ws_col == 0 {
@@ -79,26 +134,128 @@ impl LowLevel {
     }
 
     pub fn send(&mut self, data: &str) -> io::Result<()> {
-        self.tty_file.write_all(data.as_bytes())
+        match &mut self.device {
+            Device::Tty { tty_file, .. } => tty_file.write_all(data.as_bytes()),
+            Device::Stream { output, .. } => {
+                output.write_all(data.as_bytes())?;
+                output.flush()
+            }
+        }
     }
 
     pub fn read_polling(&mut self, buf: &mut Vec<u8>) -> Result<()> {
-        self.tty_file.read_to_end(buf)?;
+        match &mut self.device {
+            Device::Tty { tty_file, .. } => {
+                tty_file.read_to_end(buf)?;
+            }
+            Device::Stream { input, .. } => {
+                let mut chunk = [0; 1024];
+                loop {
+                    match input.read(&mut chunk) {
+                        Ok(0) => {
+                            return Err(Error::IoError(io::Error::new(
+                                io::ErrorKind::UnexpectedEof,
+                                "the input stream was closed",
+                            )))
+                        }
+                        Ok(len) => buf.extend_from_slice(&chunk[..len]),
+                        Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                        Err(error) => return Err(error.into()),
+                    }
+                }
+            }
+        }
         Ok(())
     }
 
-    fn teardown(&mut self) -> Result<()> {
-        let res = unsafe { libc::tcsetattr(self.tty_fd, libc::TCSAFLUSH, &self.original_termios) };
-        if res != 0 {
+    /// Wait until there is input to read or until `timeout` elapses, then read any available input
+    pub fn read_timeout(&mut self, buf: &mut Vec<u8>, timeout: Duration) -> Result<()> {
+        let mut poll_fd = libc::pollfd {
+            fd: self.input_fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout_ms = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+        let res = unsafe { libc::poll(&mut poll_fd, 1, timeout_ms) };
+        if res < 0 {
             return Err(Error::last_os_error());
         }
-        Ok(())
+        self.read_polling(buf)
+    }
+
+    /// Wait until there is input to read from the terminal or `wake_fd`, or until `timeout`
+    /// elapses (or indefinitely if it's `None`). Returns true iff `wake_fd` became readable or the
+    /// wait was interrupted by a signal.
+    pub fn wait(&self, wake_fd: RawFd, timeout: Option<Duration>) -> Result<bool> {
+        let mut poll_fds = [
+            libc::pollfd {
+                fd: self.input_fd,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: wake_fd,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        // round up so the wait doesn't end just before the deadline
+        let timeout_ms = timeout.map_or(-1, |timeout| {
+            timeout
+                .as_micros()
+                .div_ceil(1000)
+                .min(libc::c_int::MAX as u128) as libc::c_int
+        });
+        let res = unsafe {
+            libc::poll(
+                poll_fds.as_mut_ptr(),
+                poll_fds.len() as libc::nfds_t,
+                timeout_ms,
+            )
+        };
+        if res < 0 {
+            let error = io::Error::last_os_error();
+            if error.kind() == io::ErrorKind::Interrupted {
+                return Ok(true);
+            }
+            return Err(error.into());
+        }
+        Ok(poll_fds[1].revents & libc::POLLIN != 0)
+    }
+
+    /// The file descriptor and original settings of the tty, unless the terminal is connected by
+    /// streams
+    pub fn tty(&self) -> Option<(RawFd, libc::termios)> {
+        match self.device {
+            Device::Tty {
+                original_termios, ..
+            } => Some((self.input_fd, original_termios)),
+            Device::Stream { .. } => None,
+        }
+    }
+
+    /// Put the terminal back into raw mode after `leave_raw_mode`
+    pub fn enter_raw_mode(&mut self) -> Result<()> {
+        match self.tty() {
+            Some((tty_fd, original_termios)) => {
+                Self::set_termios(tty_fd, &Self::raw_termios(original_termios))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Restore the terminal's original settings
+    pub fn leave_raw_mode(&mut self) -> Result<()> {
+        match self.tty() {
+            Some((tty_fd, original_termios)) => Self::set_termios(tty_fd, &original_termios),
+            None => Ok(()),
+        }
     }
 }
 
 impl Drop for LowLevel {
     fn drop(&mut self) {
-        self.teardown()
+        self.leave_raw_mode()
             .expect("Failed to reset terminal to original settings");
     }
 }
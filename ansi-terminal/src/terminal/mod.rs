@@ -1,138 +1,235 @@
+use self::frame_encoder::FrameEncoder;
+use crate::context::Config;
 use crate::error::Result;
-use chargrid_input::*;
 use chargrid_render::*;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
 
 mod ansi_colour_codes;
 mod ansi_terminal;
+mod builtin_term_info;
 mod byte_prefix_tree;
+mod charset;
+mod clipboard;
+mod colour_support;
+mod dither;
+mod frame_encoder;
+mod graphics;
+mod kitty_keyboard;
 mod low_level;
+mod output;
+mod report;
+mod restore;
+mod sgr_mouse;
+mod stream;
 mod term_info_cache;
+mod terminal_colours;
 
 pub use self::ansi_terminal::{col_encode, AnsiTerminal, ColEncode, DrainInput};
-
-#[derive(Debug, Clone)]
-struct OutputCell {
-    dirty: bool,
-    ch: char,
-    fg: Rgb24,
-    bg: Rgb24,
-    bold: bool,
-    underline: bool,
-}
-
-impl OutputCell {
-    fn matches(&self, cell: &BufferCell) -> bool {
-        !self.dirty
-            && self.ch == cell.character
-            && self.fg == cell.foreground_colour
-            && self.bg == cell.background_colour
-            && self.bold == cell.bold
-            && self.underline == cell.underline
-    }
-    fn copy_fields(&mut self, cell: &BufferCell) {
-        self.dirty = false;
-        self.ch = cell.character;
-        self.fg = cell.foreground_colour;
-        self.bg = cell.background_colour;
-        self.bold = cell.bold;
-        self.underline = cell.underline;
-    }
-    fn new() -> Self {
-        Self {
-            dirty: true,
-            ch: ' ',
-            fg: Rgb24::new_grey(0),
-            bg: Rgb24::new_grey(0),
-            bold: false,
-            underline: false,
-        }
-    }
-}
+pub use self::charset::{Charset, Transliteration};
+pub use self::graphics::ImageProtocol;
+pub use self::stream::{StreamSize, Streams};
+pub use self::term_info_cache::TermInfoOverrides;
+pub use self::terminal_colours::TerminalColours;
 
 pub struct Terminal {
     ansi: AnsiTerminal,
-    output_frame: grid_2d::Grid<OutputCell>,
+    frame_encoder: FrameEncoder,
+    dithering: bool,
+    optimise_cursor_motion: bool,
+    transliteration: Transliteration,
 }
 
 impl Terminal {
-    pub fn new() -> Result<Self> {
-        let ansi = AnsiTerminal::new()?;
+    pub fn new(config: &Config) -> Result<Self> {
+        AnsiTerminal::new(config).and_then(Self::from_ansi)
+    }
+
+    pub fn new_with_streams<R, W>(streams: Streams<R, W>, config: &Config) -> Result<Self>
+    where
+        R: Read + Send + 'static,
+        W: Write + 'static,
+    {
+        AnsiTerminal::new_with_streams(streams, config).and_then(Self::from_ansi)
+    }
+
+    /// Connect to a terminal over a pair of streams, describing the terminal as xterm regardless
+    /// of the terminfo database of the machine running the tests
+    #[cfg(test)]
+    pub fn new_with_streams_for_tests<R, W>(streams: Streams<R, W>, config: &Config) -> Result<Self>
+    where
+        R: Read + Send + 'static,
+        W: Write + 'static,
+    {
+        let low_level =
+            low_level::LowLevel::from_streams(streams.input, streams.output, streams.size)?;
+        let ti_cache = term_info_cache::TermInfoCache::xterm_for_tests();
+        AnsiTerminal::from_low_level(low_level, ti_cache, config).and_then(Self::from_ansi)
+    }
+
+    fn from_ansi(ansi: AnsiTerminal) -> Result<Self> {
         let size = ansi.size()?;
-        let output_frame = grid_2d::Grid::new_fn(size, |_| OutputCell::new());
-        Ok(Self { ansi, output_frame })
+        let mut terminal = Self {
+            ansi,
+            frame_encoder: FrameEncoder::new(size),
+            dithering: false,
+            optimise_cursor_motion: true,
+            transliteration: Transliteration::default(),
+        };
+        terminal.reset_frame_encoder(size);
+        Ok(terminal)
+    }
+
+    /// Replace the frame encoder with one which knows nothing of what's on the screen, so the next
+    /// frame is drawn from scratch
+    fn reset_frame_encoder(&mut self, size: Size) {
+        self.frame_encoder = FrameEncoder::new(size);
+        self.frame_encoder
+            .set_optimise_cursor_motion(self.optimise_cursor_motion);
+        self.frame_encoder
+            .set_images(self.ansi.image_protocol(), self.ansi.cell_pixel_size());
+        self.frame_encoder
+            .set_charset(self.ansi.charset(), self.transliteration.clone());
     }
 
     pub fn resize_if_necessary(&mut self) -> Result<Size> {
+        if self.ansi.inline_height().is_some() {
+            // the inline region is cleared, so everything must be redrawn
+            self.ansi.fit_inline_region()?;
+            let size = self.ansi.size()?;
+            self.reset_frame_encoder(size);
+            return Ok(size);
+        }
         let size = self.ansi.size()?;
-        if size != self.output_frame.size() {
-            self.output_frame = grid_2d::Grid::new_fn(size, |_| OutputCell::new());
+        if size != self.frame_encoder.size() {
+            self.reset_frame_encoder(size);
         }
         Ok(size)
     }
 
+    pub fn inline_height(&self) -> Option<u32> {
+        self.ansi.inline_height()
+    }
+
+    pub fn set_inline_height(&mut self, height: u32) -> Result<Size> {
+        self.ansi.set_inline_height(height)?;
+        let size = self.ansi.size()?;
+        self.reset_frame_encoder(size);
+        Ok(size)
+    }
+
+    pub fn wait(&mut self, timeout: Option<Duration>) -> Result<bool> {
+        self.ansi.wait(timeout)
+    }
+
+    pub fn wake_sender(&self) -> Result<UnixStream> {
+        self.ansi.wake_sender()
+    }
+
+    /// True iff the terminal was set up again after the process was stopped, and must be redrawn
+    pub fn redraw_pending(&self) -> bool {
+        self.ansi.redraw_pending()
+    }
+
+    pub fn take_resized(&self) -> bool {
+        self.ansi.take_resized()
+    }
+
     pub fn size(&self) -> Result<Size> {
         self.ansi.size()
     }
 
-    pub fn draw_frame<E>(&mut self, frame: &mut Buffer) -> Result<()>
+    pub fn set_styled_underlines(&mut self, styled_underlines: bool) {
+        self.ansi.set_styled_underlines(styled_underlines);
+        // redraw everything so existing underlines are updated
+        self.frame_encoder.invalidate();
+    }
+
+    pub fn set_dithering(&mut self, dithering: bool) {
+        self.dithering = dithering;
+    }
+
+    pub fn set_synchronized_output(&mut self, synchronized_output: bool) {
+        self.ansi.set_synchronized_output(synchronized_output);
+    }
+
+    pub fn mouse_pixel_coordinates(&self) -> bool {
+        self.ansi.mouse_pixel_coordinates()
+    }
+
+    pub fn col_encode(&self) -> col_encode::Dynamic {
+        self.ansi.col_encode()
+    }
+
+    pub fn set_optimise_cursor_motion(&mut self, optimise_cursor_motion: bool) {
+        self.optimise_cursor_motion = optimise_cursor_motion;
+        self.frame_encoder
+            .set_optimise_cursor_motion(optimise_cursor_motion);
+    }
+
+    pub fn image_protocol(&self) -> Option<ImageProtocol> {
+        self.ansi.image_protocol()
+    }
+
+    pub fn terminal_colours(&self) -> &TerminalColours {
+        self.ansi.terminal_colours()
+    }
+
+    pub fn charset(&self) -> Charset {
+        self.ansi.charset()
+    }
+
+    /// Change how characters are sent, redrawing everything in the new character set
+    pub fn set_charset(&mut self, charset: Charset) {
+        self.ansi.set_charset(charset);
+        let size = self.frame_encoder.size();
+        self.reset_frame_encoder(size);
+    }
+
+    pub fn set_transliteration(&mut self, transliteration: Transliteration) {
+        self.transliteration = transliteration;
+        let size = self.frame_encoder.size();
+        self.reset_frame_encoder(size);
+    }
+
+    pub fn set_image_protocol(&mut self, image_protocol: Option<ImageProtocol>) {
+        if self.ansi.image_protocol() == Some(ImageProtocol::Kitty) {
+            // kitty images aren't removed by drawing over them
+            self.ansi.output().push_str(graphics::KITTY_DELETE_ALL);
+        }
+        self.ansi.set_image_protocol(image_protocol);
+        let size = self.frame_encoder.size();
+        self.reset_frame_encoder(size);
+    }
+
+    pub fn draw_frame<E>(&mut self, frame: &mut Buffer, col_encode: &E) -> Result<()>
     where
         E: ColEncode,
     {
-        self.ansi.set_cursor(Coord::new(0, 0))?;
-        let mut bold = false;
-        let mut underline = false;
-        let mut fg = Rgb24::new_grey(0);
-        let mut bg = Rgb24::new_grey(0);
-        self.ansi.reset();
-        self.ansi.clear_underline();
-        self.ansi.set_foreground_colour::<E>(fg);
-        self.ansi.set_background_colour::<E>(bg);
-        let mut must_move_cursor = false;
-        for ((coord, cell), output_cell) in frame.enumerate().zip(self.output_frame.iter_mut()) {
-            if output_cell.matches(cell) {
-                must_move_cursor = true;
-                continue;
+        if self.dithering {
+            if let Some(spread) = col_encode.dither_spread() {
+                dither::dither_backgrounds(frame, spread);
             }
-            let reset = if cell.bold != bold {
-                if cell.bold {
-                    self.ansi.set_bold();
-                    bold = true;
-                    false
-                } else {
-                    self.ansi.reset();
-                    bold = false;
-                    true
-                }
-            } else {
-                false
-            };
-            if reset || cell.foreground_colour != fg {
-                self.ansi.set_foreground_colour::<E>(cell.foreground_colour);
-                fg = cell.foreground_colour;
-            }
-            if reset || cell.background_colour != bg {
-                self.ansi.set_background_colour::<E>(cell.background_colour);
-                bg = cell.background_colour;
-            }
-            if reset || (cell.underline != underline) {
-                if cell.underline {
-                    self.ansi.set_underline();
-                } else {
-                    self.ansi.clear_underline();
-                }
-                underline = cell.underline;
-            }
-            if must_move_cursor {
-                self.ansi.set_cursor(coord)?;
-                must_move_cursor = false;
-            }
-            output_cell.copy_fields(cell);
-            self.ansi.add_char_to_buffer(cell.character);
+        }
+        if self.ansi.take_redraw() {
+            self.frame_encoder.invalidate();
+        }
+        let synchronized_output = self.ansi.synchronized_output();
+        let mut output = self.ansi.output();
+        let start = output.position();
+        self.frame_encoder.encode(frame, &mut output, col_encode)?;
+        if synchronized_output {
+            output.synchronize_since(start);
         }
         self.ansi.flush_buffer()?;
         Ok(())
     }
 
+    pub fn set_clipboard(&mut self, text: &str) -> Result<()> {
+        self.ansi.set_clipboard(text)
+    }
+
     pub fn drain_input(&mut self) -> Result<DrainInput> {
         self.ansi.drain_input()
     }
@@ -0,0 +1,300 @@
+use super::ansi_terminal::ColEncode;
+use super::charset;
+use super::term_info_cache::TermInfoCache;
+use crate::error::Result;
+use chargrid_render::*;
+use std::fmt::Write;
+use term::terminfo::parm::{self, Param};
+
+const BEGIN_SYNCHRONIZED_UPDATE: &str = "\x1b[?2026h";
+const END_SYNCHRONIZED_UPDATE: &str = "\x1b[?2026l";
+// select the terminal's default colours, which follow the user's theme
+const DEFAULT_FOREGROUND_COLOUR: &str = "\x1b[39m";
+const DEFAULT_BACKGROUND_COLOUR: &str = "\x1b[49m";
+
+/// Appends escape sequences and text to a buffer which will later be sent to the terminal
+pub struct Output<'a> {
+    buffer: &'a mut String,
+    ti_cache: &'a mut TermInfoCache,
+    styled_underlines: bool,
+    first_row: i32,
+}
+
+impl<'a> Output<'a> {
+    pub fn new(
+        buffer: &'a mut String,
+        ti_cache: &'a mut TermInfoCache,
+        styled_underlines: bool,
+    ) -> Self {
+        Self {
+            buffer,
+            ti_cache,
+            styled_underlines,
+            first_row: 0,
+        }
+    }
+
+    /// Draw with the top of the frame at the given row of the terminal rather than at the top
+    pub fn with_first_row(self, first_row: i32) -> Self {
+        Self { first_row, ..self }
+    }
+
+    fn expand(&mut self, cap: &str, params: &[Param]) -> Result<String> {
+        let command = parm::expand(cap.as_bytes(), params, &mut self.ti_cache.vars)?;
+        Ok(::std::str::from_utf8(&command)?.to_string())
+    }
+
+    /// Returns the sequence which moves the cursor to `coord` regardless of its current position
+    pub fn cursor_absolute(&mut self, coord: Coord) -> Result<String> {
+        let set_cursor = self.ti_cache.set_cursor.clone();
+        self.expand(
+            &set_cursor,
+            &[
+                Param::Number(coord.y + self.first_row),
+                Param::Number(coord.x),
+            ],
+        )
+    }
+
+    /// Returns the shortest sequence which moves the cursor `count` cells in a direction, given
+    /// the capabilities for moving an arbitrary number of cells and for moving a single cell
+    fn cursor_steps(
+        &mut self,
+        many: Option<String>,
+        one: Option<String>,
+        count: i32,
+    ) -> Result<Option<String>> {
+        if count == 0 {
+            return Ok(Some(String::new()));
+        }
+        let many = match many {
+            Some(many) => Some(self.expand(&many, &[Param::Number(count)])?),
+            None => None,
+        };
+        let one = one.map(|one| one.repeat(count as usize));
+        Ok(match (many, one) {
+            (Some(many), Some(one)) => Some(if one.len() <= many.len() { one } else { many }),
+            (many, one) => many.or(one),
+        })
+    }
+
+    /// Returns the shortest sequence which moves the cursor by `delta` using relative motions,
+    /// or `None` if the terminal lacks the necessary capabilities
+    pub fn cursor_relative(&mut self, delta: Coord) -> Result<Option<String>> {
+        let motion = &self.ti_cache.cursor_motion;
+        let (vertical, vertical_one) = if delta.y < 0 {
+            (motion.up.clone(), motion.up_one.clone())
+        } else {
+            (motion.down.clone(), motion.down_one.clone())
+        };
+        let (horizontal, horizontal_one) = if delta.x < 0 {
+            (motion.left.clone(), motion.left_one.clone())
+        } else {
+            (motion.right.clone(), motion.right_one.clone())
+        };
+        let vertical = self.cursor_steps(vertical, vertical_one, delta.y.abs())?;
+        let horizontal = self.cursor_steps(horizontal, horizontal_one, delta.x.abs())?;
+        Ok(match (vertical, horizontal) {
+            (Some(vertical), Some(horizontal)) => Some(vertical + &horizontal),
+            _ => None,
+        })
+    }
+
+    pub fn carriage_return(&self) -> Option<&str> {
+        self.ti_cache.cursor_motion.carriage_return.as_deref()
+    }
+
+    /// Returns a sequence which repeats the previous character `count` times, if the terminal
+    /// supports doing so. Only ascii characters can be repeated.
+    pub fn repeat_char(&mut self, ch: char, count: u32) -> Result<Option<String>> {
+        if !ch.is_ascii() || count == 0 {
+            return Ok(None);
+        }
+        let repeat_char = match self.ti_cache.cursor_motion.repeat_char.clone() {
+            Some(repeat_char) => repeat_char,
+            None => return Ok(None),
+        };
+        // The capability prints the character followed by the repetitions, so the count includes
+        // the character itself. Drop the character, as it has already been printed.
+        let sequence = self.expand(
+            &repeat_char,
+            &[Param::Number(ch as i32), Param::Number(count as i32 + 1)],
+        )?;
+        Ok(sequence
+            .strip_prefix(ch)
+            .map(|repetitions| repetitions.to_string()))
+    }
+
+    pub fn set_foreground_colour<E>(&mut self, col_encode: &E, rgb24: Rgb24)
+    where
+        E: ColEncode,
+    {
+        col_encode.encode_foreground(self.buffer, rgb24, self.ti_cache);
+    }
+
+    pub fn set_background_colour<E>(&mut self, col_encode: &E, rgb24: Rgb24)
+    where
+        E: ColEncode,
+    {
+        col_encode.encode_background(self.buffer, rgb24, self.ti_cache);
+    }
+
+    pub fn set_default_foreground_colour(&mut self) {
+        self.buffer.push_str(DEFAULT_FOREGROUND_COLOUR);
+    }
+
+    pub fn set_default_background_colour(&mut self) {
+        self.buffer.push_str(DEFAULT_BACKGROUND_COLOUR);
+    }
+
+    /// The character which draws `ch` in the terminal's alternate character set, if the terminal
+    /// has one which contains `ch`
+    pub fn alternate_charset_char(&self, ch: char) -> Option<char> {
+        let alternate_charset = self.ti_cache.alternate_charset.as_ref()?;
+        charset::dec_special_graphics(ch).and_then(|dec| alternate_charset.get(dec))
+    }
+
+    /// Draw characters with the alternate character set (or stop doing so) until the next call
+    pub fn set_alternate_charset(&mut self, alternate_charset: bool) {
+        if let Some(caps) = self.ti_cache.alternate_charset.as_ref() {
+            let sequence = if alternate_charset {
+                &caps.enter
+            } else {
+                &caps.exit
+            };
+            self.buffer.push_str(sequence);
+        }
+    }
+
+    pub fn set_bold(&mut self) {
+        self.buffer.push_str(&self.ti_cache.bold);
+    }
+
+    pub fn set_underline(&mut self) {
+        self.buffer.push_str(&self.ti_cache.underline);
+    }
+
+    pub fn clear_underline(&mut self) {
+        self.buffer.push_str(&self.ti_cache.no_underline);
+    }
+
+    /// True iff styled and coloured underlines will be emitted. Otherwise all underlines are
+    /// drawn as plain underlines in the foreground colour.
+    pub fn styled_underlines(&self) -> bool {
+        self.styled_underlines
+    }
+
+    pub fn set_underline_kind(&mut self, underline_kind: UnderlineKind) {
+        let code = match underline_kind {
+            UnderlineKind::Single => 1,
+            UnderlineKind::Double => 2,
+            UnderlineKind::Curly => 3,
+            UnderlineKind::Dotted => 4,
+            UnderlineKind::Dashed => 5,
+        };
+        write!(self.buffer, "\x1b[4:{}m", code).unwrap();
+    }
+
+    /// Set the colour of styled underlines with the given encoding. Returns false if the encoding
+    /// can't represent underline colours, in which case nothing is sent.
+    pub fn set_underline_colour<E>(&mut self, col_encode: &E, rgb24: Rgb24) -> bool
+    where
+        E: ColEncode,
+    {
+        let len = self.buffer.len();
+        col_encode.encode_underline(self.buffer, rgb24, self.ti_cache);
+        self.buffer.len() != len
+    }
+
+    pub fn clear_underline_colour(&mut self) {
+        self.buffer.push_str("\x1b[59m");
+    }
+
+    pub fn set_italic(&mut self) {
+        self.buffer.push_str(&self.ti_cache.italic);
+    }
+
+    pub fn clear_italic(&mut self) {
+        self.buffer.push_str(&self.ti_cache.no_italic);
+    }
+
+    pub fn set_strikethrough(&mut self) {
+        self.buffer.push_str(&self.ti_cache.strikethrough);
+    }
+
+    pub fn clear_strikethrough(&mut self) {
+        self.buffer.push_str(&self.ti_cache.no_strikethrough);
+    }
+
+    pub fn set_dim(&mut self) {
+        self.buffer.push_str(&self.ti_cache.dim);
+    }
+
+    pub fn set_reverse(&mut self) {
+        self.buffer.push_str(&self.ti_cache.reverse);
+    }
+
+    pub fn set_blink(&mut self) {
+        self.buffer.push_str(&self.ti_cache.blink);
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.push_str(&self.ti_cache.reset);
+    }
+
+    pub fn show_cursor(&mut self) {
+        self.buffer.push_str(&self.ti_cache.show_cursor);
+    }
+
+    pub fn hide_cursor(&mut self) {
+        self.buffer.push_str(&self.ti_cache.hide_cursor);
+    }
+
+    /// Set the shape of the cursor with DECSCUSR
+    pub fn set_cursor_shape(&mut self, shape: CursorShape, blink: bool) {
+        let code = match shape {
+            CursorShape::Block => 1,
+            CursorShape::Underline => 3,
+            CursorShape::Bar => 5,
+        };
+        // the steady version of each shape follows the blinking version
+        let code = if blink { code } else { code + 1 };
+        write!(self.buffer, "\x1b[{} q", code).unwrap();
+    }
+
+    /// Start or end an OSC 8 hyperlink. Characters drawn until the next call link to the target.
+    /// The link's id lets terminals treat cells of the same link as one link even when they
+    /// aren't drawn in a single run.
+    pub fn set_hyperlink(&mut self, hyperlink: Option<(Hyperlink, &str)>) {
+        match hyperlink {
+            Some((hyperlink, target)) => {
+                // control characters would terminate the sequence early
+                let target = target
+                    .chars()
+                    .filter(|ch| !ch.is_control())
+                    .collect::<String>();
+                write!(self.buffer, "\x1b]8;id={};{}\x1b\\", hyperlink.id(), target).unwrap();
+            }
+            None => self.buffer.push_str("\x1b]8;;\x1b\\"),
+        }
+    }
+
+    /// The length of the buffer so far, for marking the start of a frame (see `synchronize_since`)
+    pub fn position(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Wrap everything appended since `position` in a synchronized update, so the terminal holds
+    /// off on displaying the changes until they're complete, and partially-drawn frames are never
+    /// visible. Nothing is added if nothing was appended.
+    pub fn synchronize_since(&mut self, position: usize) {
+        if self.buffer.len() > position {
+            self.buffer.insert_str(position, BEGIN_SYNCHRONIZED_UPDATE);
+            self.buffer.push_str(END_SYNCHRONIZED_UPDATE);
+        }
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        self.buffer.push_str(s);
+    }
+}
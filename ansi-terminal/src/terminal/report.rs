@@ -0,0 +1,405 @@
+//! Parsing of the reports sent by the terminal in response to queries
+
+use super::colour_support::TRUE_COLOUR_CAPS;
+use super::graphics::KITTY_GRAPHICS_QUERY_ID;
+use super::term_info_cache::STYLED_UNDERLINE_CAPS;
+use chargrid_render::Rgb24;
+
+const CSI: &[u8] = b"\x1b[";
+const CSI_PRIVATE: &[u8] = b"\x1b[?";
+const DCS: &[u8] = b"\x1bP";
+const ST: &[u8] = b"\x1b\\";
+const KITTY_GRAPHICS_APC: &[u8] = b"\x1b_G";
+const OSC: &[u8] = b"\x1b]";
+// some terminals end operating system commands with BEL rather than ST
+const BEL: u8 = 0x07;
+// operating system commands which report colours
+const OSC_PALETTE_COLOUR: &[u8] = b"4";
+const OSC_DEFAULT_FOREGROUND: &[u8] = b"10";
+const OSC_DEFAULT_BACKGROUND: &[u8] = b"11";
+// the attribute reported in response to a device attributes query by terminals supporting sixel
+const SIXEL_ATTRIBUTE: u32 = 4;
+// the prefix of the parameters of a report of the size of a cell in pixels
+const CELL_PIXEL_SIZE_REPORT: u32 = 6;
+// prefix of the body of a response to XTGETTCAP for capabilities the terminal has
+const TERM_CAP_FOUND: &[u8] = b"1+r";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Report {
+    /// Response to a DECRQM query for the state of a DEC private mode
+    Mode { mode: u32, setting: u32 },
+    /// Response to a query for the enabled kitty keyboard protocol flags. Only terminals which
+    /// support the protocol respond.
+    KittyKeyboardFlags(u32),
+    /// Response to a Device Status Report query for the cursor position. Rows and columns are
+    /// counted from 0.
+    CursorPosition { row: u32, column: u32 },
+    /// Response to an XTGETTCAP query, naming a capability which indicates true colour support
+    TrueColour,
+    /// Response to an XTGETTCAP query, naming a capability which indicates support for styled and
+    /// coloured underlines
+    StyledUnderlines,
+    /// Response to a query for support for the kitty graphics protocol. Only terminals which
+    /// support the protocol respond.
+    KittyGraphics,
+    /// Response to an XTWINOPS query for the size of a cell in pixels
+    CellPixelSize { width: u32, height: u32 },
+    /// Response to an OSC 10 query for the default foreground colour
+    DefaultForeground(Rgb24),
+    /// Response to an OSC 11 query for the default background colour
+    DefaultBackground(Rgb24),
+    /// Response to an OSC 4 query for a colour of the palette
+    PaletteColour { index: u8, colour: Rgb24 },
+    /// Response to a Primary Device Attributes query. Every terminal responds to this, so it's
+    /// sent after other queries to find out when all their responses have arrived.
+    PrimaryDeviceAttributes { sixel: bool },
+}
+
+impl Report {
+    /// Returns true iff the report indicates that the terminal recognises `mode`
+    pub fn is_mode_supported(&self, mode: u32) -> bool {
+        match *self {
+            // 1 and 2 mean the mode is set or reset, 3 means it is permanently set
+            Report::Mode {
+                mode: report_mode,
+                setting,
+            } => report_mode == mode && (1..=3).contains(&setting),
+            Report::KittyKeyboardFlags(_)
+            | Report::CursorPosition { .. }
+            | Report::TrueColour
+            | Report::StyledUnderlines
+            | Report::KittyGraphics
+            | Report::CellPixelSize { .. }
+            | Report::DefaultForeground(_)
+            | Report::DefaultBackground(_)
+            | Report::PaletteColour { .. }
+            | Report::PrimaryDeviceAttributes { .. } => false,
+        }
+    }
+}
+
+fn parse_params(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .split(|&b| b == b';')
+        .map(|param| {
+            param.iter().fold(0u32, |acc, &b| {
+                acc.saturating_mul(10).saturating_add((b - b'0') as u32)
+            })
+        })
+        .collect()
+}
+
+/// Parse a report from the start of `bytes`, which must begin with `CSI_PRIVATE`. Returns the
+/// report (if it's one we recognise) and the length of the sequence, or `None` if the sequence is
+/// incomplete.
+fn parse_report(bytes: &[u8]) -> Option<(Option<Report>, usize)> {
+    let body = &bytes[CSI_PRIVATE.len()..];
+    let params_len = body
+        .iter()
+        .position(|&b| !(b.is_ascii_digit() || b == b';'))?;
+    let params = parse_params(&body[..params_len]);
+    let rest = &body[params_len..];
+    let (report, len) = match *rest.first()? {
+        b'c' => (
+            Some(Report::PrimaryDeviceAttributes {
+                // the first parameter identifies the terminal and the rest list its attributes
+                sixel: params[1..].contains(&SIXEL_ATTRIBUTE),
+            }),
+            1,
+        ),
+        b'u' => (Some(Report::KittyKeyboardFlags(params[0])), 1),
+        b'$' => match *rest.get(1)? {
+            b'y' if params.len() == 2 => (
+                Some(Report::Mode {
+                    mode: params[0],
+                    setting: params[1],
+                }),
+                2,
+            ),
+            _ => (None, 2),
+        },
+        _ => (None, 1),
+    };
+    Some((report, CSI_PRIVATE.len() + params_len + len))
+}
+
+/// Parse a cursor position or cell size report from the start of `bytes`, which must begin with
+/// `CSI`. Cursor position reports have the same form as some modified function keys (e.g.
+/// ctrl+F3), so they're only recognised as reports while waiting for a response to a query.
+fn parse_csi_report(bytes: &[u8]) -> Option<(Report, usize)> {
+    let body = &bytes[CSI.len()..];
+    let params_len = body
+        .iter()
+        .position(|&b| !(b.is_ascii_digit() || b == b';'))?;
+    let params = parse_params(&body[..params_len]);
+    let report = match (body[params_len], &params[..]) {
+        (b'R', &[row, column]) if row > 0 && column > 0 => Report::CursorPosition {
+            row: row - 1,
+            column: column - 1,
+        },
+        (b't', &[CELL_PIXEL_SIZE_REPORT, height, width]) if width > 0 && height > 0 => {
+            Report::CellPixelSize { width, height }
+        }
+        _ => return None,
+    };
+    Some((report, CSI.len() + params_len + 1))
+}
+
+/// Parse a kitty graphics protocol response from the start of `bytes`, which must begin with
+/// `KITTY_GRAPHICS_APC`. Responses take the form "<keys>;<message>", where the message is "OK" on
+/// success. Returns `None` if the sequence is incomplete.
+fn parse_kitty_graphics(bytes: &[u8]) -> Option<(Option<Report>, usize)> {
+    let body = &bytes[KITTY_GRAPHICS_APC.len()..];
+    let body_len = body.windows(ST.len()).position(|window| window == ST)?;
+    let len = KITTY_GRAPHICS_APC.len() + body_len + ST.len();
+    let mut parts = body[..body_len].splitn(2, |&b| b == b';');
+    let keys = parts.next().unwrap_or(&[]);
+    let message = parts.next().unwrap_or(&[]);
+    let query_id = format!("i={}", KITTY_GRAPHICS_QUERY_ID);
+    let is_query_response = keys
+        .split(|&b| b == b',')
+        .any(|key| key == query_id.as_bytes());
+    let report = if is_query_response && message == b"OK" {
+        Some(Report::KittyGraphics)
+    } else {
+        None
+    };
+    Some((report, len))
+}
+
+/// Parse a colour in the form "rgb:<red>/<green>/<blue>", where each component is 1 to 4 hex
+/// digits, scaled to 8 bits
+fn parse_colour(bytes: &[u8]) -> Option<Rgb24> {
+    let components = bytes.strip_prefix(b"rgb:")?;
+    let mut components = components.split(|&b| b == b'/').map(|component| {
+        if component.is_empty() || component.len() > 4 {
+            return None;
+        }
+        let value = u32::from_str_radix(std::str::from_utf8(component).ok()?, 16).ok()?;
+        let max = (1 << (4 * component.len())) - 1;
+        Some((value * 255 / max) as u8)
+    });
+    let r = components.next()??;
+    let g = components.next()??;
+    let b = components.next()??;
+    if components.next().is_some() {
+        return None;
+    }
+    Some(Rgb24::new(r, g, b))
+}
+
+/// Parse an operating system command from the start of `bytes`, which must begin with `OSC`. The
+/// only OSC reports recognised are responses to colour queries, which take the form
+/// "10;<colour>", "11;<colour>" or "4;<index>;<colour>". Returns `None` if the sequence is
+/// incomplete.
+fn parse_operating_system_command(bytes: &[u8]) -> Option<(Option<Report>, usize)> {
+    let body = &bytes[OSC.len()..];
+    let (body_len, terminator_len) = body.iter().enumerate().find_map(|(i, &b)| {
+        if b == BEL {
+            Some((i, 1))
+        } else if body[i..].starts_with(ST) {
+            Some((i, ST.len()))
+        } else {
+            None
+        }
+    })?;
+    let len = OSC.len() + body_len + terminator_len;
+    let params = body[..body_len].split(|&b| b == b';').collect::<Vec<_>>();
+    let report = match params[..] {
+        [OSC_DEFAULT_FOREGROUND, colour] => parse_colour(colour).map(Report::DefaultForeground),
+        [OSC_DEFAULT_BACKGROUND, colour] => parse_colour(colour).map(Report::DefaultBackground),
+        [OSC_PALETTE_COLOUR, index, colour] => std::str::from_utf8(index)
+            .ok()
+            .and_then(|index| index.parse().ok())
+            .and_then(|index| {
+                parse_colour(colour).map(|colour| Report::PaletteColour { index, colour })
+            }),
+        _ => None,
+    };
+    Some((report, len))
+}
+
+fn decode_hex(hex: &[u8]) -> Option<String> {
+    let pairs = hex.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    let bytes = pairs
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// Parse a device control string from the start of `bytes`, which must begin with `DCS`. The
+/// only DCS reports recognised are responses to XTGETTCAP queries, which list the requested
+/// capabilities as "<hex name>=<hex value>" separated by ';'. Each query asks about capabilities
+/// indicating a single feature, which the response is reported as. Returns `None` if the sequence
+/// is incomplete.
+fn parse_device_control_string(bytes: &[u8]) -> Option<(Option<Report>, usize)> {
+    let body = &bytes[DCS.len()..];
+    let body_len = body.windows(ST.len()).position(|window| window == ST)?;
+    let len = DCS.len() + body_len + ST.len();
+    let report = body[..body_len]
+        .strip_prefix(TERM_CAP_FOUND)
+        .and_then(|caps| {
+            caps.split(|&b| b == b';').find_map(|cap| {
+                let name = decode_hex(cap.split(|&b| b == b'=').next().unwrap_or(cap))?;
+                if TRUE_COLOUR_CAPS.contains(&name.as_str()) {
+                    Some(Report::TrueColour)
+                } else if STYLED_UNDERLINE_CAPS.contains(&name.as_str()) {
+                    Some(Report::StyledUnderlines)
+                } else {
+                    None
+                }
+            })
+        });
+    Some((report, len))
+}
+
+/// Remove all complete reports from `bytes`, returning them in the order they were received. Any
+/// other bytes (e.g. from keys pressed while waiting for the reports) are left in place.
+pub fn take_reports(bytes: &mut Vec<u8>) -> Vec<Report> {
+    let mut reports = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(CSI_PRIVATE) {
+            if let Some((Some(report), len)) = parse_report(&bytes[i..]) {
+                reports.push(report);
+                bytes.drain(i..(i + len));
+                continue;
+            }
+        } else if bytes[i..].starts_with(DCS) {
+            if let Some((report, len)) = parse_device_control_string(&bytes[i..]) {
+                reports.extend(report);
+                bytes.drain(i..(i + len));
+                continue;
+            }
+        } else if bytes[i..].starts_with(OSC) {
+            if let Some((report, len)) = parse_operating_system_command(&bytes[i..]) {
+                reports.extend(report);
+                bytes.drain(i..(i + len));
+                continue;
+            }
+        } else if bytes[i..].starts_with(KITTY_GRAPHICS_APC) {
+            if let Some((report, len)) = parse_kitty_graphics(&bytes[i..]) {
+                reports.extend(report);
+                bytes.drain(i..(i + len));
+                continue;
+            }
+        } else if bytes[i..].starts_with(CSI) {
+            if let Some((report, len)) = parse_csi_report(&bytes[i..]) {
+                reports.push(report);
+                bytes.drain(i..(i + len));
+                continue;
+            }
+        }
+        i += 1;
+    }
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_are_removed_from_input() {
+        let mut bytes = b"a\x1b[?2026;2$yb\x1b[?15u\x1b[12;1R\x1b[?62;22c\x1b[A".to_vec();
+        let reports = take_reports(&mut bytes);
+        assert_eq!(
+            reports,
+            vec![
+                Report::Mode {
+                    mode: 2026,
+                    setting: 2
+                },
+                Report::KittyKeyboardFlags(15),
+                Report::CursorPosition { row: 11, column: 0 },
+                Report::PrimaryDeviceAttributes { sixel: false },
+            ]
+        );
+        assert_eq!(bytes, b"ab\x1b[A");
+        assert!(reports[0].is_mode_supported(2026));
+        assert!(!reports[0].is_mode_supported(2004));
+    }
+
+    #[test]
+    fn oversized_params() {
+        let mut bytes = b"\x1b[?99999999999c".to_vec();
+        assert_eq!(
+            take_reports(&mut bytes),
+            vec![Report::PrimaryDeviceAttributes { sixel: false }]
+        );
+        assert_eq!(parse_params(b"99999999999;4"), vec![u32::MAX, 4]);
+    }
+
+    #[test]
+    fn term_caps() {
+        let mut bytes = b"\x1bP1+r5463=\x1b\\\x1bP0+r524742\x1b\\a".to_vec();
+        assert_eq!(take_reports(&mut bytes), vec![Report::TrueColour]);
+        assert_eq!(bytes, b"a");
+        let mut bytes = b"\x1bP1+r536D756C78=5C455B343A25703125646D\x1b\\".to_vec();
+        assert_eq!(take_reports(&mut bytes), vec![Report::StyledUnderlines]);
+        let mut bytes = b"\x1bP1+r636F6C6F7273=323536\x1b\\".to_vec();
+        assert_eq!(take_reports(&mut bytes), vec![]);
+        assert_eq!(bytes, b"");
+    }
+
+    #[test]
+    fn graphics() {
+        let mut bytes = b"\x1b_Gi=31;OK\x1b\\\x1b[6;20;10ta\x1b[?62;4;22c".to_vec();
+        assert_eq!(
+            take_reports(&mut bytes),
+            vec![
+                Report::KittyGraphics,
+                Report::CellPixelSize {
+                    width: 10,
+                    height: 20
+                },
+                Report::PrimaryDeviceAttributes { sixel: true },
+            ]
+        );
+        assert_eq!(bytes, b"a");
+        let mut bytes = b"\x1b_Gi=31;EINVAL:bad\x1b\\".to_vec();
+        assert_eq!(take_reports(&mut bytes), vec![]);
+        assert_eq!(bytes, b"");
+    }
+
+    #[test]
+    fn colours() {
+        let mut bytes =
+            b"\x1b]10;rgb:ffff/ffff/ffff\x1b\\\x1b]11;rgb:28/2c/34\x07a\x1b]4;9;rgb:f/8/0\x1b\\"
+                .to_vec();
+        assert_eq!(
+            take_reports(&mut bytes),
+            vec![
+                Report::DefaultForeground(Rgb24::new(255, 255, 255)),
+                Report::DefaultBackground(Rgb24::new(0x28, 0x2c, 0x34)),
+                Report::PaletteColour {
+                    index: 9,
+                    colour: Rgb24::new(255, 136, 0)
+                },
+            ]
+        );
+        assert_eq!(bytes, b"a");
+        // other operating system commands are consumed
+        let mut bytes = b"\x1b]11;rgb:zz/00/00\x1b\\\x1b]11;rgb:00".to_vec();
+        assert_eq!(take_reports(&mut bytes), vec![]);
+        assert_eq!(bytes, b"\x1b]11;rgb:00");
+    }
+
+    #[test]
+    fn incomplete_reports_are_left_in_place() {
+        let mut bytes = b"\x1b[?2026;0$y\x1b[?62;2".to_vec();
+        let reports = take_reports(&mut bytes);
+        assert_eq!(
+            reports,
+            vec![Report::Mode {
+                mode: 2026,
+                setting: 0
+            }]
+        );
+        assert!(!reports[0].is_mode_supported(2026));
+        assert_eq!(bytes, b"\x1b[?62;2");
+    }
+}
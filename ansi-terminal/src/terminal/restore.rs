@@ -0,0 +1,69 @@
+//! Restoring the terminal from a panic hook. Otherwise the panic message would be printed before
+//! the terminal is torn down (which happens while unwinding), so it would be written to the
+//! alternate screen in raw mode and then be lost when the alternate screen is left.
+
+use std::os::unix::io::RawFd;
+use std::panic;
+use std::sync::{Mutex, Once};
+
+struct Restore {
+    tty_fd: RawFd,
+    original_termios: libc::termios,
+    teardown: String,
+}
+
+static RESTORE: Mutex<Option<Restore>> = Mutex::new(None);
+static INSTALL_PANIC_HOOK: Once = Once::new();
+
+/// Arrange for the panic hook to write `teardown` to the terminal and restore its original
+/// settings. The hook is installed the first time this is called, and runs before any
+/// previously-installed hook.
+pub fn set(tty_fd: RawFd, original_termios: libc::termios, teardown: String) {
+    INSTALL_PANIC_HOOK.call_once(|| {
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore();
+            previous_hook(info);
+        }));
+    });
+    if let Ok(mut state) = RESTORE.lock() {
+        *state = Some(Restore {
+            tty_fd,
+            original_termios,
+            teardown,
+        });
+    }
+}
+
+/// Stop the panic hook from restoring the terminal. Returns false if it has already done so, in
+/// which case the terminal doesn't need to be torn down again.
+pub fn clear() -> bool {
+    match RESTORE.lock() {
+        Ok(mut state) => state.take().is_some(),
+        Err(_) => true,
+    }
+}
+
+fn restore() {
+    // the lock can only be poisoned by a panic while it's held, which none of the code holding it
+    // can cause
+    let state = match RESTORE.lock() {
+        Ok(mut state) => state.take(),
+        Err(_) => None,
+    };
+    if let Some(Restore {
+        tty_fd,
+        original_termios,
+        teardown,
+    }) = state
+    {
+        unsafe {
+            libc::write(
+                tty_fd,
+                teardown.as_ptr() as *const libc::c_void,
+                teardown.len(),
+            );
+            libc::tcsetattr(tty_fd, libc::TCSAFLUSH, &original_termios);
+        }
+    }
+}
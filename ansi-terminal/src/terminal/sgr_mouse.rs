@@ -0,0 +1,160 @@
+//! Decoding of mouse events reported with the SGR extended mouse protocol (DEC private mode
+//! 1006), and its pixel-coordinate variant (DEC private mode 1016)
+
+use chargrid_input::{Coord, Input, MouseButton, MouseInput, ScrollDirection};
+
+pub const PIXEL_MODE: u32 = 1016;
+pub const ENABLE_PIXELS: &str = "\x1b[?1016h";
+pub const DISABLE_PIXELS: &str = "\x1b[?1016l";
+pub const QUERY_PIXELS: &str = "\x1b[?1016$p";
+
+const PREFIX: &[u8] = b"\x1b[<";
+
+// Bits of the button parameter
+const BUTTON_MASK: u32 = 0b11;
+const MOTION: u32 = 32;
+const WHEEL: u32 = 64;
+// buttons 8 to 11 (e.g. back and forward) have no equivalent
+const EXTRA_BUTTONS: u32 = 128;
+
+fn button(bits: u32) -> Option<MouseButton> {
+    match bits & BUTTON_MASK {
+        0 => Some(MouseButton::Left),
+        1 => Some(MouseButton::Middle),
+        2 => Some(MouseButton::Right),
+        _ => None,
+    }
+}
+
+fn scroll_direction(bits: u32) -> ScrollDirection {
+    match bits & BUTTON_MASK {
+        0 => ScrollDirection::Up,
+        1 => ScrollDirection::Down,
+        2 => ScrollDirection::Left,
+        _ => ScrollDirection::Right,
+    }
+}
+
+/// Parse a mouse event from the start of `bytes`. Returns the input (if the event has an
+/// equivalent `MouseInput`) and the length of the sequence, or `None` if `bytes` doesn't start
+/// with a complete mouse event.
+pub fn parse(bytes: &[u8]) -> Option<(Option<Input>, usize)> {
+    if !bytes.starts_with(PREFIX) {
+        return None;
+    }
+    let body = &bytes[PREFIX.len()..];
+    let params_len = body
+        .iter()
+        .position(|&b| !(b.is_ascii_digit() || b == b';'))?;
+    let len = PREFIX.len() + params_len + 1;
+    let pressed = match body[params_len] {
+        b'M' => true,
+        b'm' => false,
+        _ => return Some((None, len)),
+    };
+    // oversized parameters saturate at the largest coordinate, so they can't overflow
+    let params = body[..params_len]
+        .split(|&b| b == b';')
+        .map(|param| {
+            param.iter().fold(0u32, |acc, &b| {
+                acc.saturating_mul(10)
+                    .saturating_add((b - b'0') as u32)
+                    .min(i32::MAX as u32)
+            })
+        })
+        .collect::<Vec<_>>();
+    let (bits, x, y) = match params[..] {
+        [bits, x, y] => (bits, x, y),
+        _ => return Some((None, len)),
+    };
+    // coordinates are reported starting from 1
+    let coord = Coord::new(x as i32 - 1, y as i32 - 1);
+    let mouse_input = if bits & EXTRA_BUTTONS != 0 {
+        None
+    } else if bits & WHEEL != 0 {
+        Some(MouseInput::MouseScroll {
+            direction: scroll_direction(bits),
+            coord,
+        })
+    } else if bits & MOTION != 0 {
+        Some(MouseInput::MouseMove {
+            button: button(bits),
+            coord,
+        })
+    } else if pressed {
+        button(bits).map(|button| MouseInput::MousePress { button, coord })
+    } else {
+        button(bits).map(|button| MouseInput::MouseRelease {
+            button: Ok(button),
+            coord,
+        })
+    };
+    Some((mouse_input.map(Input::Mouse), len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_mouse(bytes: &[u8]) -> MouseInput {
+        let (input, len) = parse(bytes).unwrap();
+        assert_eq!(len, bytes.len());
+        match input.unwrap() {
+            Input::Mouse(mouse_input) => mouse_input,
+            other => panic!("unexpected input: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn oversized_coordinates() {
+        assert_eq!(
+            parse_mouse(b"\x1b[<0;99999999999999999999;3000000000M"),
+            MouseInput::MousePress {
+                button: MouseButton::Left,
+                coord: Coord::new(i32::MAX - 1, i32::MAX - 1),
+            }
+        );
+    }
+
+    #[test]
+    fn mouse_events() {
+        assert_eq!(
+            parse_mouse(b"\x1b[<0;1;1M"),
+            MouseInput::MousePress {
+                button: MouseButton::Left,
+                coord: Coord::new(0, 0),
+            }
+        );
+        assert_eq!(
+            parse_mouse(b"\x1b[<2;300;400m"),
+            MouseInput::MouseRelease {
+                button: Ok(MouseButton::Right),
+                coord: Coord::new(299, 399),
+            }
+        );
+        assert_eq!(
+            parse_mouse(b"\x1b[<33;5;6M"),
+            MouseInput::MouseMove {
+                button: Some(MouseButton::Middle),
+                coord: Coord::new(4, 5),
+            }
+        );
+        assert_eq!(
+            parse_mouse(b"\x1b[<35;5;6M"),
+            MouseInput::MouseMove {
+                button: None,
+                coord: Coord::new(4, 5),
+            }
+        );
+        // ctrl+scroll down
+        assert_eq!(
+            parse_mouse(b"\x1b[<81;7;8M"),
+            MouseInput::MouseScroll {
+                direction: ScrollDirection::Down,
+                coord: Coord::new(6, 7),
+            }
+        );
+        assert_eq!(parse(b"\x1b[<128;1;1M"), Some((None, 11)));
+        assert_eq!(parse(b"\x1b[<0;1;1"), None);
+    }
+}
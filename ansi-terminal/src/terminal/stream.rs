@@ -0,0 +1,107 @@
+//! Connecting to a terminal over a pair of byte streams (e.g. a socket) rather than the
+//! controlling tty
+
+use chargrid_render::Size;
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+/// The size of a terminal connected with `Context::new_with_streams`. Since the size can't be
+/// queried from a stream, whatever learns the size of the terminal (e.g. a server which receives
+/// window size updates from its client) keeps it up to date with `set`.
+#[derive(Clone, Debug)]
+pub struct StreamSize {
+    shared: Arc<Mutex<SharedSize>>,
+}
+
+#[derive(Debug)]
+struct SharedSize {
+    size: Size,
+    listener: Option<Listener>,
+}
+
+/// Notifies a running context that the size changed
+#[derive(Debug)]
+struct Listener {
+    resized: Arc<AtomicBool>,
+    wake_sender: UnixStream,
+}
+
+impl StreamSize {
+    pub fn new(size: Size) -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(SharedSize {
+                size,
+                listener: None,
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SharedSize> {
+        self.shared.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn get(&self) -> Size {
+        self.lock().size
+    }
+
+    /// Change the size of the terminal. The context reports the new size to the app as
+    /// `Input::Resize` before its next frame. Widths and heights of 0 are treated as 1.
+    pub fn set(&self, size: Size) {
+        let size = Size::new(size.width().max(1), size.height().max(1));
+        let mut shared = self.lock();
+        if shared.size == size {
+            return;
+        }
+        shared.size = size;
+        if let Some(listener) = shared.listener.as_ref() {
+            listener.resized.store(true, Ordering::Relaxed);
+            // if the stream is full, the context will be woken anyway
+            let _ = (&listener.wake_sender).write_all(&[0]);
+        }
+    }
+
+    pub(crate) fn listen(&self, resized: Arc<AtomicBool>, wake_sender: UnixStream) {
+        self.lock().listener = Some(Listener {
+            resized,
+            wake_sender,
+        });
+    }
+}
+
+/// The streams connecting a context to a terminal. See `Context::new_with_streams`.
+pub struct Streams<R, W> {
+    /// Bytes sent by the terminal (keys, mouse events and responses to queries)
+    pub input: R,
+    /// Bytes sent to the terminal
+    pub output: W,
+    pub size: StreamSize,
+    /// The name of the terminfo entry describing the terminal (e.g. "xterm-256color"). If this is
+    /// `None`, the TERM environment variable is used.
+    pub term: Option<String>,
+}
+
+/// Spawn a thread which copies `input` into a stream that can be waited on with `poll`. The
+/// returned stream is non-blocking, and reads from it return 0 bytes once `input` has ended.
+pub(crate) fn forward_input<R: Read + Send + 'static>(mut input: R) -> io::Result<UnixStream> {
+    let (receiver, mut sender) = UnixStream::pair()?;
+    receiver.set_nonblocking(true)?;
+    thread::spawn(move || {
+        let mut buf = [0; 1024];
+        loop {
+            let len = match input.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            };
+            // fails once the receiver is dropped
+            if sender.write_all(&buf[..len]).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(receiver)
+}
@@ -1,31 +1,203 @@
+use super::builtin_term_info::BuiltinTermInfo;
 use super::byte_prefix_tree::BytePrefixTree;
 use crate::error::{Error, Result};
-use chargrid_input::{Input, KeyboardInput, MouseButton, ScrollDirection};
+use chargrid_input::{keys, Input, KeyModifiers, KeyboardInput, MouseButton, ScrollDirection};
+use std::collections::HashMap;
+use std::env;
 use term::terminfo::parm::{self, Param, Variables};
 use term::terminfo::TermInfo;
 
 // XXX this might not be portable
 const ESCAPE: &[u8] = &[27];
-const ENABLE_MOUSE_REPORTING: &str = "[?1003h";
-const DISABLE_MOUSE_REPORTING: &str = "[?1003l";
+// Report all mouse motion (1003), using the SGR encoding (1006) on terminals which support it.
+// Other terminals ignore 1006 and fall back to the legacy encoding.
+const ENABLE_MOUSE_REPORTING: &str = "[?1003;1006h";
+const DISABLE_MOUSE_REPORTING: &str = "[?1003;1006l";
+// SGR sequences used when the terminfo database lacks the corresponding capability
+const ITALIC: &str = "[3m";
+const NO_ITALIC: &str = "[23m";
+const DIM: &str = "[2m";
+const REVERSE: &str = "[7m";
+const BLINK: &str = "[5m";
+// there is no standard terminfo capability for strikethrough
+const STRIKETHROUGH: &str = "[9m";
+const NO_STRIKETHROUGH: &str = "[29m";
+const CLEAR_TO_END_OF_SCREEN: &str = "[J";
+// The characters of the DEC special graphics set which a vt100 can draw
+const VT100_ACSC: &[u8] = b"``aaffggjjkkllmmnnooppqqrrssttuuvvwwxxyyzz{{||}}~~";
+/// XTGETTCAP query for the "Smulx" and "Su" capabilities (with hex-encoded names), which terminals
+/// supporting styled (e.g. curly) and coloured underlines report. The terminfo parser doesn't expose
+/// these extended capabilities, so the terminal itself is asked.
+pub const QUERY_STYLED_UNDERLINES: &str = "\x1bP+q536D756C78;5375\x1b\\";
+/// Names of terminfo capabilities which indicate support for styled and coloured underlines
+pub const STYLED_UNDERLINE_CAPS: &[&str] = &["Smulx", "Su"];
+// Keys which xterm reports with modifiers as "CSI 1 ; <modifiers> <final byte>"
+const MODIFIED_KEYS_BY_FINAL_BYTE: &[(char, KeyboardInput)] = &[
+    ('A', KeyboardInput::Up),
+    ('B', KeyboardInput::Down),
+    ('C', KeyboardInput::Right),
+    ('D', KeyboardInput::Left),
+    ('H', KeyboardInput::Home),
+    ('F', KeyboardInput::End),
+    ('P', KeyboardInput::Function(1)),
+    ('Q', KeyboardInput::Function(2)),
+    ('R', KeyboardInput::Function(3)),
+    ('S', KeyboardInput::Function(4)),
+];
+// Keys which xterm reports with modifiers as "CSI <number> ; <modifiers> ~"
+const MODIFIED_KEYS_BY_NUMBER: &[(u8, KeyboardInput)] = &[
+    (3, KeyboardInput::Delete),
+    (5, KeyboardInput::PageUp),
+    (6, KeyboardInput::PageDown),
+    (15, KeyboardInput::Function(5)),
+    (17, KeyboardInput::Function(6)),
+    (18, KeyboardInput::Function(7)),
+    (19, KeyboardInput::Function(8)),
+    (20, KeyboardInput::Function(9)),
+    (21, KeyboardInput::Function(10)),
+    (23, KeyboardInput::Function(11)),
+    (24, KeyboardInput::Function(12)),
+];
 
 #[derive(Debug, Clone, Copy)]
 pub enum MousePrefix {
     Press(MouseButton),
-    // ansi terminals don't report which button was released
+    // the legacy encoding doesn't report which button was released
     Release,
     // ansi terminals only report the last button pressed when reporting a mouse drag
     Move(Option<MouseButton>),
     Scroll(ScrollDirection),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum TerminalInput {
     Char(char),
     Literal(Input),
     MousePrefix(MousePrefix),
 }
 
+/// Capabilities for moving the cursor relative to its current position, and for repeating the
+/// previous character. Terminals lacking any of these can still be drawn to using `set_cursor`.
+pub struct CursorMotionCaps {
+    pub carriage_return: Option<String>,
+    pub up: Option<String>,
+    pub down: Option<String>,
+    pub left: Option<String>,
+    pub right: Option<String>,
+    pub up_one: Option<String>,
+    pub down_one: Option<String>,
+    pub left_one: Option<String>,
+    pub right_one: Option<String>,
+    pub repeat_char: Option<String>,
+}
+
+/// Capabilities for drawing with the terminal's alternate character set, which usually contains
+/// the DEC special graphics (e.g. box-drawing characters)
+pub struct AlternateCharsetCaps {
+    pub enter: String,
+    pub exit: String,
+    /// Sent once before the alternate character set is used, on terminals which require it
+    pub enable: Option<String>,
+    /// Maps each character of the DEC special graphics set (as they're named in terminfo's "acsc"
+    /// capability) to the character which draws it in the alternate character set
+    pub chars: HashMap<char, char>,
+}
+
+impl AlternateCharsetCaps {
+    /// The character which draws `dec` (a character of the DEC special graphics set) in the
+    /// alternate character set, if the terminal has it
+    pub fn get(&self, dec: char) -> Option<char> {
+        self.chars.get(&dec).cloned()
+    }
+}
+
+/// Parse terminfo's "acsc" capability, a string of pairs of characters, in which the first of each
+/// pair names a DEC special graphics character and the second draws it. Pairs which draw
+/// characters outside printable ascii (e.g. the code page of the linux console's font) can't be
+/// sent as UTF-8, so they are left out.
+fn parse_acsc(acsc: &[u8]) -> HashMap<char, char> {
+    acsc.chunks_exact(2)
+        .filter(|pair| pair.iter().all(|&byte| byte.is_ascii_graphic()))
+        .map(|pair| (pair[0] as char, pair[1] as char))
+        .collect()
+}
+
+/// Terminfo capabilities which replace those of the terminal's terminfo entry, for terminals whose
+/// entries are missing or wrong. Capabilities are named as in terminfo(5) (e.g. "smcup"), and
+/// string capabilities use the same parameter encoding as terminfo, with escapes written literally
+/// (e.g. "\x1b[%i%p1%d;%p2%dH").
+#[derive(Clone, Debug, Default)]
+pub struct TermInfoOverrides {
+    strings: HashMap<String, Option<String>>,
+    numbers: HashMap<String, u32>,
+    bools: HashMap<String, bool>,
+}
+
+impl TermInfoOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace a string capability (e.g. "smcup")
+    pub fn with_string(mut self, name: &str, value: &str) -> Self {
+        self.strings
+            .insert(name.to_string(), Some(value.to_string()));
+        self
+    }
+
+    /// Treat a string capability as missing, so that it's not used even if the terminal's entry
+    /// has it (e.g. remove "smcup" to not use the alternate screen)
+    pub fn without_string(mut self, name: &str) -> Self {
+        self.strings.insert(name.to_string(), None);
+        self
+    }
+
+    /// Replace a numeric capability (e.g. "colors")
+    pub fn with_number(mut self, name: &str, value: u32) -> Self {
+        self.numbers.insert(name.to_string(), value);
+        self
+    }
+
+    /// Replace a boolean capability (e.g. "RGB")
+    pub fn with_bool(mut self, name: &str, value: bool) -> Self {
+        self.bools.insert(name.to_string(), value);
+        self
+    }
+}
+
+/// Looks up capabilities in a terminfo entry, unless they are overridden
+struct Capabilities<'a> {
+    term_info: &'a TermInfo,
+    overrides: &'a TermInfoOverrides,
+}
+
+impl<'a> Capabilities<'a> {
+    fn string(&self, name: &str) -> Option<&'a [u8]> {
+        match self.overrides.strings.get(name) {
+            Some(value) => value.as_ref().map(|value| value.as_bytes()),
+            None => self
+                .term_info
+                .strings
+                .get(name)
+                .map(|value| value.as_slice()),
+        }
+    }
+
+    fn number(&self, name: &str) -> Option<u32> {
+        let overrides = self.overrides.numbers.get(name);
+        overrides
+            .or_else(|| self.term_info.numbers.get(name))
+            .cloned()
+    }
+
+    fn bool(&self, name: &str) -> Option<bool> {
+        let overrides = self.overrides.bools.get(name);
+        overrides
+            .or_else(|| self.term_info.bools.get(name))
+            .cloned()
+    }
+}
+
 pub struct TermInfoCache {
     pub enter_ca: Option<String>,
     pub exit_ca: Option<String>,
@@ -34,11 +206,24 @@ pub struct TermInfoCache {
     pub show_cursor: String,
     pub hide_cursor: String,
     pub clear: String,
+    pub clear_to_end_of_screen: String,
     pub reset: String,
     pub set_cursor: String,
+    pub cursor_motion: CursorMotionCaps,
+    pub alternate_charset: Option<AlternateCharsetCaps>,
     pub bold: String,
     pub underline: String,
     pub no_underline: String,
+    pub italic: String,
+    pub no_italic: String,
+    pub strikethrough: String,
+    pub no_strikethrough: String,
+    pub dim: String,
+    pub reverse: String,
+    pub blink: String,
+    pub styled_underlines: bool,
+    pub colours: Option<u32>,
+    pub rgb: bool,
     pub enable_mouse_reporting: String,
     pub disable_mouse_reporting: String,
     pub fg_colours: Vec<String>,
@@ -54,13 +239,63 @@ fn raw_cap(seq: &str) -> Result<String> {
     Ok(string)
 }
 
+fn raw_escseq_owned(seq: String, input: Input) -> (Vec<u8>, TerminalInput) {
+    let mut bytes = ESCAPE.to_vec();
+    bytes.extend_from_slice(seq.as_bytes());
+    (bytes, TerminalInput::Literal(input))
+}
+
 impl TermInfoCache {
-    pub fn new() -> Result<Self> {
-        let term_info = TermInfo::from_env()?;
+    /// Describe the terminal named by the TERM environment variable. If the terminfo database
+    /// can't be read, or lacks the terminal's entry, the closest built-in description is used.
+    pub fn new(overrides: &TermInfoOverrides) -> Result<Self> {
+        let term = env::var("TERM").ok();
+        Self::from_term_info_or_builtin(
+            term.as_deref().unwrap_or(""),
+            TermInfo::from_env(),
+            overrides,
+        )
+    }
+
+    /// Describe the terminal with the given terminfo name, falling back to the closest built-in
+    /// description as in `new`
+    pub fn from_name(name: &str, overrides: &TermInfoOverrides) -> Result<Self> {
+        Self::from_term_info_or_builtin(name, TermInfo::from_name(name), overrides)
+    }
+
+    fn from_term_info_or_builtin(
+        term: &str,
+        term_info: term::Result<TermInfo>,
+        overrides: &TermInfoOverrides,
+    ) -> Result<Self> {
+        let builtin = BuiltinTermInfo::closest_to(term);
+        match term_info {
+            // entries lacking capabilities which are required (e.g. "cup") are completed with
+            // those of the built-in description
+            Ok(term_info) => match Self::from_term_info(&term_info, overrides) {
+                Err(Error::MissingCap(_)) => {
+                    Self::from_term_info(&builtin.fill_in(&term_info), overrides)
+                }
+                other => other,
+            },
+            Err(_) => {
+                let mut term_info = builtin.term_info();
+                // the terminal is still recognised by its own name (e.g. "*-direct" entries)
+                if !term.is_empty() && term != term_info.names[0] {
+                    term_info.names.insert(0, term.to_string());
+                }
+                Self::from_term_info(&term_info, overrides)
+            }
+        }
+    }
+
+    pub fn from_term_info(term_info: &TermInfo, overrides: &TermInfoOverrides) -> Result<Self> {
+        let caps = Capabilities {
+            term_info,
+            overrides,
+        };
         let cap = |name: &'static str| {
-            term_info
-                .strings
-                .get(name)
+            caps.string(name)
                 .ok_or_else(|| Error::MissingCap(name.to_string()))
                 .and_then(|bytes| {
                     ::std::str::from_utf8(bytes)
@@ -85,10 +320,8 @@ impl TermInfoCache {
             );
         }
         let escseq = |name: &'static str, input: Input| {
-            term_info
-                .strings
-                .get(name)
-                .cloned()
+            caps.string(name)
+                .map(|seq| seq.to_vec())
                 .ok_or_else(|| Error::MissingCap(name.to_string()))
                 .map(|seq| (seq, TerminalInput::Literal(input)))
         };
@@ -163,10 +396,36 @@ impl TermInfoCache {
                 "[Mc",
                 TerminalInput::MousePrefix(MousePrefix::Scroll(ScrollDirection::Right)),
             ),
+            // reported when focus reporting (DEC private mode 1004) is enabled
+            raw_escseq("[I", TerminalInput::Literal(Input::FocusGained)),
+            raw_escseq("[O", TerminalInput::Literal(Input::FocusLost)),
         ];
         let mut escape_sequence_prefix_tree = BytePrefixTree::new();
-        for &(ref seq, input) in inputs_to_escape.iter() {
-            escape_sequence_prefix_tree.insert(seq, input);
+        for (seq, input) in inputs_to_escape {
+            escape_sequence_prefix_tree.insert(&seq, input);
+        }
+        if let Some(back_tab) = caps.string("kcbt") {
+            let input = Input::keyboard_with_modifiers(keys::TAB, KeyModifiers::SHIFT);
+            escape_sequence_prefix_tree.insert(back_tab, TerminalInput::Literal(input));
+        }
+        // xterm encodes modifiers as a parameter whose value is 1 plus the modifier bits
+        for bits in 1..=0xF {
+            let modifiers = KeyModifiers::from_bits(bits);
+            let param = bits + 1;
+            for &(final_byte, key) in MODIFIED_KEYS_BY_FINAL_BYTE {
+                let (seq, input) = raw_escseq_owned(
+                    format!("[1;{}{}", param, final_byte),
+                    Input::keyboard_with_modifiers(key, modifiers),
+                );
+                escape_sequence_prefix_tree.insert(&seq, input);
+            }
+            for &(number, key) in MODIFIED_KEYS_BY_NUMBER {
+                let (seq, input) = raw_escseq_owned(
+                    format!("[{};{}~", number, param),
+                    Input::keyboard_with_modifiers(key, modifiers),
+                );
+                escape_sequence_prefix_tree.insert(&seq, input);
+            }
         }
         Ok(Self {
             enter_ca: cap("smcup").ok(),
@@ -177,10 +436,49 @@ impl TermInfoCache {
             hide_cursor: cap("civis")?,
             reset: cap("sgr0")?,
             clear: cap("clear")?,
+            clear_to_end_of_screen: cap("ed").or_else(|_| raw_cap(CLEAR_TO_END_OF_SCREEN))?,
             set_cursor: cap("cup")?,
+            cursor_motion: CursorMotionCaps {
+                carriage_return: cap("cr").ok(),
+                up: cap("cuu").ok(),
+                down: cap("cud").ok(),
+                left: cap("cub").ok(),
+                right: cap("cuf").ok(),
+                up_one: cap("cuu1").ok(),
+                down_one: cap("cud1").ok(),
+                left_one: cap("cub1").ok(),
+                right_one: cap("cuf1").ok(),
+                repeat_char: cap("rep").ok(),
+            },
+            // terminals without "acsc" draw the vt100 graphics with the same characters as vt100
+            alternate_charset: match (cap("smacs"), cap("rmacs")) {
+                (Ok(enter), Ok(exit)) => Some(AlternateCharsetCaps {
+                    enter,
+                    exit,
+                    enable: cap("enacs").ok(),
+                    chars: parse_acsc(caps.string("acsc").unwrap_or(VT100_ACSC)),
+                }),
+                _ => None,
+            },
             bold: cap("bold")?,
             underline: cap("smul")?,
             no_underline: cap("rmul")?,
+            italic: cap("sitm").or_else(|_| raw_cap(ITALIC))?,
+            no_italic: cap("ritm").or_else(|_| raw_cap(NO_ITALIC))?,
+            strikethrough: raw_cap(STRIKETHROUGH)?,
+            no_strikethrough: raw_cap(NO_STRIKETHROUGH)?,
+            dim: cap("dim").or_else(|_| raw_cap(DIM))?,
+            reverse: cap("rev").or_else(|_| raw_cap(REVERSE))?,
+            blink: cap("blink").or_else(|_| raw_cap(BLINK))?,
+            // Like "RGB", these are extended capabilities, so they are only found here if they're
+            // overridden. Otherwise the terminal is asked with `QUERY_STYLED_UNDERLINES`.
+            styled_underlines: caps.string("Smulx").is_some() || caps.bool("Su").unwrap_or(false),
+            colours: caps.number("colors"),
+            // The "RGB" capability is an extended capability, which the terminfo parser doesn't
+            // expose, but entries describing direct colour terminals are conventionally named
+            // "*-direct" (e.g. "xterm-direct").
+            rgb: caps.bool("RGB").unwrap_or(false)
+                || term_info.names.iter().any(|name| name.ends_with("-direct")),
             enable_mouse_reporting: raw_cap(ENABLE_MOUSE_REPORTING)?,
             disable_mouse_reporting: raw_cap(DISABLE_MOUSE_REPORTING)?,
             fg_colours,
@@ -190,6 +488,14 @@ impl TermInfoCache {
         })
     }
 
+    /// A cache populated with the built-in description of xterm, so tests don't depend on the
+    /// terminfo database of the machine running them
+    #[cfg(test)]
+    pub fn xterm_for_tests() -> Self {
+        use super::builtin_term_info::XTERM_256COLOR;
+        Self::from_term_info(&XTERM_256COLOR.term_info(), &TermInfoOverrides::new()).unwrap()
+    }
+
     pub fn fg_colour(&self, colour: u8) -> &str {
         self.fg_colours[colour as usize].as_str()
     }
@@ -198,3 +504,90 @@ impl TermInfoCache {
         self.bg_colours[colour as usize].as_str()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_fallback() {
+        let overrides = TermInfoOverrides::new();
+        let ti_cache = TermInfoCache::from_name("chargrid-no-such-terminal", &overrides).unwrap();
+        assert_eq!(ti_cache.colours, Some(256));
+        assert_eq!(
+            ti_cache.enter_ca.as_deref(),
+            Some("\x1b[?1049h\x1b[22;0;0t")
+        );
+        let alternate_charset = ti_cache.alternate_charset.as_ref().unwrap();
+        assert_eq!(alternate_charset.get('q'), Some('q'));
+        assert_eq!(alternate_charset.get('0'), None);
+        // known terminals missing from the database get their own built-in description
+        let ti_cache = TermInfoCache::from_term_info_or_builtin(
+            "linux",
+            Err(term::Error::TermUnset),
+            &overrides,
+        )
+        .unwrap();
+        assert_eq!(ti_cache.colours, Some(8));
+        assert_eq!(ti_cache.enter_ca, None);
+        // the linux console's alternate character set can't be sent as UTF-8
+        assert!(ti_cache.alternate_charset.is_none());
+        // entries lacking required capabilities are completed
+        let mut strings = HashMap::new();
+        strings.insert("cup", b"\x1b[%i%p1%d;%p2%df".to_vec());
+        let term_info = TermInfo {
+            names: vec!["kitty".to_string()],
+            bools: HashMap::new(),
+            numbers: HashMap::new(),
+            strings,
+        };
+        let ti_cache =
+            TermInfoCache::from_term_info_or_builtin("kitty", Ok(term_info), &overrides).unwrap();
+        assert_eq!(ti_cache.set_cursor, "\x1b[%i%p1%d;%p2%df");
+        assert_eq!(ti_cache.hide_cursor, "\x1b[?25l");
+        // styled underlines are found by asking the terminal rather than by its name
+        assert!(!ti_cache.styled_underlines);
+    }
+
+    #[test]
+    fn acsc() {
+        let chars = parse_acsc(b"``aaq\xc4xx0");
+        assert_eq!(chars.get(&'`'), Some(&'`'));
+        assert_eq!(chars.get(&'x'), Some(&'x'));
+        // characters outside ascii are left out, as is the unpaired character
+        assert_eq!(chars.get(&'q'), None);
+        assert_eq!(chars.get(&'0'), None);
+    }
+
+    #[test]
+    fn overrides() {
+        let overrides = TermInfoOverrides::new()
+            .with_string("civis", "\x1b[?25l\x1b[?1c")
+            .without_string("smcup")
+            .with_number("colors", 16)
+            .with_bool("RGB", true)
+            .with_string("Smulx", "\x1b[4:%p1%dm");
+        let ti_cache = TermInfoCache::from_term_info_or_builtin(
+            "xterm-256color",
+            Err(term::Error::TermUnset),
+            &overrides,
+        )
+        .unwrap();
+        assert_eq!(ti_cache.hide_cursor, "\x1b[?25l\x1b[?1c");
+        assert_eq!(ti_cache.enter_ca, None);
+        assert_eq!(ti_cache.colours, Some(16));
+        assert!(ti_cache.rgb);
+        assert!(ti_cache.styled_underlines);
+        // required capabilities can be supplied for entries which lack them
+        let term_info = TermInfo {
+            names: vec!["dumb".to_string()],
+            bools: HashMap::new(),
+            numbers: HashMap::new(),
+            strings: HashMap::new(),
+        };
+        let overrides = TermInfoOverrides::new().with_string("setaf", "\x1b[3%p1%dm");
+        let ti_cache =
+            TermInfoCache::from_term_info_or_builtin("dumb", Ok(term_info), &overrides).unwrap();
+        assert_eq!(ti_cache.fg_colour(1), "\x1b[31m");
+    }
+}
@@ -0,0 +1,93 @@
+//! Querying the colours of the terminal's theme
+
+use super::report::Report;
+use chargrid_render::Rgb24;
+use std::fmt::Write;
+
+/// The number of palette colours which are queried. These are the colours selected by the
+/// standard and bright sgr colour codes, which are the ones themes usually change.
+pub const PALETTE_SIZE: usize = 16;
+
+/// The queries for the default foreground (OSC 10) and background (OSC 11) colours, and for each
+/// of the first `PALETTE_SIZE` colours of the palette (OSC 4). Terminals which don't support a
+/// query ignore it.
+pub fn query() -> String {
+    let mut query = "\x1b]10;?\x1b\\\x1b]11;?\x1b\\".to_string();
+    for index in 0..PALETTE_SIZE {
+        write!(query, "\x1b]4;{};?\x1b\\", index).unwrap();
+    }
+    query
+}
+
+/// The colours of the terminal's theme, as reported by the terminal when the context was created.
+/// Colours the terminal didn't report are `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TerminalColours {
+    /// The colour of text drawn with the default foreground colour
+    pub foreground: Option<Rgb24>,
+    /// The colour of the background of cells drawn with the default background colour
+    pub background: Option<Rgb24>,
+    /// The standard (0-7) and bright (8-15) colours of the palette
+    pub palette: [Option<Rgb24>; PALETTE_SIZE],
+}
+
+impl TerminalColours {
+    pub fn from_reports(reports: &[Report]) -> Self {
+        let mut colours = Self::default();
+        for report in reports {
+            match *report {
+                Report::DefaultForeground(colour) => colours.foreground = Some(colour),
+                Report::DefaultBackground(colour) => colours.background = Some(colour),
+                Report::PaletteColour { index, colour } => {
+                    if let Some(entry) = colours.palette.get_mut(index as usize) {
+                        *entry = Some(colour);
+                    }
+                }
+                _ => (),
+            }
+        }
+        colours
+    }
+
+    /// Returns true if the default background is dark, false if it's light, or `None` if the
+    /// terminal didn't report it. Useful for choosing colours which are readable on the user's
+    /// theme.
+    pub fn is_background_dark(&self) -> Option<bool> {
+        self.background.map(|Rgb24 { r, g, b }| {
+            // perceived brightness (ITU-R BT.601)
+            let brightness = (299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000;
+            brightness < 128
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn background_brightness() {
+        let reports = [
+            Report::DefaultBackground(Rgb24::new(0xfd, 0xf6, 0xe3)),
+            Report::PaletteColour {
+                index: 1,
+                colour: Rgb24::new(0xdc, 0x32, 0x2f),
+            },
+            Report::PaletteColour {
+                index: 200,
+                colour: Rgb24::new(0, 0, 0),
+            },
+        ];
+        let colours = TerminalColours::from_reports(&reports);
+        assert_eq!(colours.foreground, None);
+        assert_eq!(colours.is_background_dark(), Some(false));
+        assert_eq!(colours.palette[1], Some(Rgb24::new(0xdc, 0x32, 0x2f)));
+        assert_eq!(colours.palette[0], None);
+        let colours = TerminalColours {
+            background: Some(Rgb24::new(0x28, 0x2c, 0x34)),
+            ..Default::default()
+        };
+        assert_eq!(colours.is_background_dark(), Some(true));
+        assert_eq!(TerminalColours::default().is_background_dark(), None);
+    }
+}
@@ -1,9 +1,63 @@
 pub use chargrid_input::Input;
 pub use chargrid_render::{ColModify, Frame, ViewContext};
+use std::cell::RefCell;
+use std::error;
+use std::fmt;
+use std::rc::Rc;
 pub use std::time::Duration;
 
 pub enum ControlFlow {
     Exit,
+    /// Exit, causing the frontend's `try_run_app` to return `Error::App` containing the error
+    ExitWithError(Box<dyn error::Error>),
+}
+
+impl ControlFlow {
+    pub fn exit_with_error<E: Into<Box<dyn error::Error>>>(error: E) -> Self {
+        ControlFlow::ExitWithError(error.into())
+    }
+
+    /// The result which a frontend's `try_run_app` returns when the app exits with `self`
+    pub fn into_result(self) -> Result<()> {
+        match self {
+            ControlFlow::Exit => Ok(()),
+            ControlFlow::ExitWithError(error) => Err(Error::App(error)),
+        }
+    }
+}
+
+/// The reason that a frontend stopped running an app
+#[derive(Debug)]
+pub enum Error {
+    /// The app exited with `ControlFlow::ExitWithError`
+    App(Box<dyn error::Error>),
+    /// The frontend failed to initialise, render or read input
+    Frontend(Box<dyn error::Error>),
+}
+
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+impl Error {
+    pub fn frontend<E: Into<Box<dyn error::Error>>>(error: E) -> Self {
+        Error::Frontend(error.into())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::App(error) => write!(f, "app exited with an error: {}", error),
+            Error::Frontend(error) => write!(f, "frontend error: {}", error),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::App(error) | Error::Frontend(error) => Some(error.as_ref()),
+        }
+    }
 }
 
 pub trait App {
@@ -18,3 +72,27 @@ pub trait App {
         F: Frame,
         C: ColModify;
 }
+
+/// A handle for writing text to the system clipboard. Obtain one from a frontend's context before
+/// running the app. Text set with the handle is written to the clipboard by the frontend soon
+/// afterwards (e.g. at the end of the current frame).
+#[derive(Clone, Debug, Default)]
+pub struct Clipboard {
+    pending: Rc<RefCell<Option<String>>>,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the contents of the clipboard with `text`
+    pub fn set_text<S: Into<String>>(&self, text: S) {
+        *self.pending.borrow_mut() = Some(text.into());
+    }
+
+    /// Used by frontends to take the text most recently set with `set_text`, if any
+    pub fn take_text(&self) -> Option<String> {
+        self.pending.borrow_mut().take()
+    }
+}
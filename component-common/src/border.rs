@@ -116,6 +116,14 @@ impl BorderStyle {
                 background: self.background,
                 bold: Some(self.bold),
                 underline: Some(false),
+                italic: Some(false),
+                strikethrough: Some(false),
+                dim: Some(false),
+                reverse: Some(false),
+                blink: Some(false),
+                default_foreground: None,
+                default_background: None,
+                hyperlink: None,
             },
         }
     }
@@ -0,0 +1,16 @@
+use chargrid_component::*;
+
+/// Draws an image over a rectangle of `size` cells at the top-left of its bounding box
+pub struct ImageComponent {
+    pub image: Image,
+    pub size: Size,
+}
+
+impl PureStaticComponent for ImageComponent {
+    fn render(&self, ctx: Ctx, fb: &mut FrameBuffer) {
+        fb.draw_image_relative_to_ctx(ctx, &self.image, Coord::new(0, 0), self.size);
+    }
+    fn size(&self, _ctx: Ctx) -> Size {
+        self.size
+    }
+}
@@ -1,5 +1,6 @@
 pub mod border;
 pub mod fade;
+pub mod image;
 pub mod menu;
 pub mod pad_to;
 pub mod signal;
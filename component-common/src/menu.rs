@@ -149,7 +149,7 @@ impl<T: Clone, S> Component for Menu<T, S> {
             Event::Tick(duration) => {
                 self.since_epoch += duration;
                 for item in self.items.iter_mut() {
-                    item.identifier.update(state, ctx, event);
+                    item.identifier.update(state, ctx, event.clone());
                 }
                 None
             }
@@ -1,4 +1,7 @@
 pub use chargrid_input as input;
+pub use chargrid_render::{
+    Cursor, CursorShape, Hyperlink, HyperlinkTargets, Image, ImagePlacement,
+};
 use grid_2d::Grid;
 pub use grid_2d::{Coord, Size};
 use input::Input;
@@ -78,8 +81,20 @@ pub struct FrameBufferCell {
     pub character: char,
     pub bold: bool,
     pub underline: bool,
+    pub italic: bool,
+    pub strikethrough: bool,
+    pub dim: bool,
+    pub reverse: bool,
+    pub blink: bool,
     pub foreground: Rgba32,
     pub background: Rgba32,
+    /// If true, terminals draw the character in their default foreground colour rather than
+    /// `foreground`
+    pub default_foreground: bool,
+    /// If true, terminals leave the background in their default background colour rather than
+    /// `background`
+    pub default_background: bool,
+    pub hyperlink: Option<Hyperlink>,
     foreground_depth: i8,
     background_depth: i8,
 }
@@ -93,8 +108,16 @@ impl FrameBufferCell {
         character: ' ',
         bold: false,
         underline: false,
+        italic: false,
+        strikethrough: false,
+        dim: false,
+        reverse: false,
+        blink: false,
         foreground: Rgba32::new_rgb(255, 255, 255),
         background: Rgba32::new_rgb(0, 0, 0),
+        default_foreground: false,
+        default_background: false,
+        hyperlink: None,
         foreground_depth: 0,
         background_depth: 0,
     };
@@ -104,6 +127,12 @@ impl FrameBufferCell {
             self.foreground_depth = depth;
         }
     }
+    fn set_hyperlink(&mut self, hyperlink: Option<Hyperlink>, depth: i8) {
+        if depth >= self.foreground_depth {
+            self.hyperlink = hyperlink;
+            self.foreground_depth = depth;
+        }
+    }
     fn set_bold(&mut self, bold: bool, depth: i8) {
         if depth >= self.foreground_depth {
             self.bold = bold;
@@ -116,15 +145,65 @@ impl FrameBufferCell {
             self.foreground_depth = depth;
         }
     }
+    fn set_italic(&mut self, italic: bool, depth: i8) {
+        if depth >= self.foreground_depth {
+            self.italic = italic;
+            self.foreground_depth = depth;
+        }
+    }
+    fn set_strikethrough(&mut self, strikethrough: bool, depth: i8) {
+        if depth >= self.foreground_depth {
+            self.strikethrough = strikethrough;
+            self.foreground_depth = depth;
+        }
+    }
+    fn set_dim(&mut self, dim: bool, depth: i8) {
+        if depth >= self.foreground_depth {
+            self.dim = dim;
+            self.foreground_depth = depth;
+        }
+    }
+    fn set_reverse(&mut self, reverse: bool, depth: i8) {
+        if depth >= self.foreground_depth {
+            self.reverse = reverse;
+            self.foreground_depth = depth;
+        }
+    }
+    fn set_blink(&mut self, blink: bool, depth: i8) {
+        if depth >= self.foreground_depth {
+            self.blink = blink;
+            self.foreground_depth = depth;
+        }
+    }
     fn set_foreground(&mut self, foreground: Rgba32, depth: i8) {
         if depth >= self.foreground_depth {
             self.foreground = foreground;
+            self.default_foreground = false;
             self.foreground_depth = depth;
         }
     }
     fn set_background(&mut self, background: Rgba32, depth: i8) {
         if depth >= self.background_depth {
             self.background = background;
+            self.default_background = false;
+            self.background_depth = depth;
+        }
+    }
+    fn set_default_foreground(&mut self, default_foreground: bool, depth: i8) {
+        if depth >= self.foreground_depth {
+            if default_foreground {
+                self.foreground = Self::BLANK.foreground;
+            }
+            self.default_foreground = default_foreground;
+            self.foreground_depth = depth;
+        }
+    }
+    fn set_default_background(&mut self, default_background: bool, depth: i8) {
+        if depth >= self.background_depth {
+            if default_background {
+                self.background = Self::BLANK.background;
+            }
+            self.default_background = default_background;
             self.background_depth = depth;
         }
     }
@@ -132,12 +211,18 @@ impl FrameBufferCell {
 
 pub struct FrameBuffer {
     grid: Grid<FrameBufferCell>,
+    cursor: Option<Cursor>,
+    hyperlinks: HyperlinkTargets,
+    images: Vec<ImagePlacement>,
 }
 
 impl FrameBuffer {
     pub fn new(size: Size) -> Self {
         Self {
             grid: Grid::new_copy(size, FrameBufferCell::BLANK),
+            cursor: None,
+            hyperlinks: HyperlinkTargets::new(),
+            images: Vec::new(),
         }
     }
 
@@ -147,6 +232,9 @@ impl FrameBuffer {
 
     pub fn resize(&mut self, size: Size) {
         self.grid = Grid::new_copy(size, FrameBufferCell::BLANK);
+        self.cursor = None;
+        self.hyperlinks.clear();
+        self.images.clear();
     }
 
     pub fn clear_with_background(&mut self, background: Rgba32) {
@@ -156,12 +244,66 @@ impl FrameBuffer {
                 ..FrameBufferCell::BLANK
             };
         }
+        self.cursor = None;
+        self.hyperlinks.clear();
+        self.images.clear();
     }
 
     pub fn clear(&mut self) {
         for cell in self.grid.iter_mut() {
             *cell = FrameBufferCell::BLANK;
         }
+        self.cursor = None;
+        self.hyperlinks.clear();
+        self.images.clear();
+    }
+
+    /// Clear the frame buffer, leaving each cell in the terminal's default colours, so the user's
+    /// terminal theme is visible wherever nothing is drawn
+    pub fn clear_with_default_colours(&mut self) {
+        for cell in self.grid.iter_mut() {
+            *cell = FrameBufferCell {
+                default_foreground: true,
+                default_background: true,
+                ..FrameBufferCell::BLANK
+            };
+        }
+        self.cursor = None;
+        self.images.clear();
+        self.hyperlinks.clear();
+    }
+
+    /// Register the target of a hyperlink, returning a handle to it for styling the cells which
+    /// link to it. Targets are forgotten when the frame buffer is cleared.
+    pub fn hyperlink(&mut self, target: &str) -> Hyperlink {
+        self.hyperlinks.insert(target)
+    }
+
+    /// The target of a hyperlink registered since the frame buffer was last cleared
+    pub fn hyperlink_target(&self, hyperlink: Hyperlink) -> Option<&str> {
+        self.hyperlinks.get(hyperlink)
+    }
+
+    /// The cursor requested since the frame buffer was last cleared, if any
+    pub fn cursor(&self) -> Option<Cursor> {
+        self.cursor
+    }
+
+    pub fn set_cursor(&mut self, cursor: Cursor) {
+        if self.grid.get(cursor.coord).is_some() {
+            self.cursor = Some(cursor);
+        }
+    }
+
+    /// The images drawn since the frame buffer was last cleared, in the order they were drawn
+    pub fn images(&self) -> &[ImagePlacement] {
+        &self.images
+    }
+
+    pub fn draw_image(&mut self, placement: ImagePlacement) {
+        if placement.fits_within(self.size()) {
+            self.images.push(placement);
+        }
     }
 
     pub fn enumerate(&self) -> FrameBufferEnumerate {
@@ -180,7 +322,10 @@ impl FrameBuffer {
         if let Some(cell) = self.grid.get_mut(coord) {
             if cell.foreground_depth <= depth || cell.background_depth <= depth {
                 if let Some(character) = render_cell.character {
+                    cell.set_hyperlink(render_cell.style.hyperlink, depth);
                     cell.set_character(character, depth);
+                } else if let Some(hyperlink) = render_cell.style.hyperlink {
+                    cell.set_hyperlink(Some(hyperlink), depth);
                 }
                 if let Some(bold) = render_cell.style.bold {
                     cell.set_bold(bold, depth);
@@ -188,6 +333,21 @@ impl FrameBuffer {
                 if let Some(underline) = render_cell.style.underline {
                     cell.set_underline(underline, depth);
                 }
+                if let Some(italic) = render_cell.style.italic {
+                    cell.set_italic(italic, depth);
+                }
+                if let Some(strikethrough) = render_cell.style.strikethrough {
+                    cell.set_strikethrough(strikethrough, depth);
+                }
+                if let Some(dim) = render_cell.style.dim {
+                    cell.set_dim(dim, depth);
+                }
+                if let Some(reverse) = render_cell.style.reverse {
+                    cell.set_reverse(reverse, depth);
+                }
+                if let Some(blink) = render_cell.style.blink {
+                    cell.set_blink(blink, depth);
+                }
                 if let Some(foreground) = render_cell.style.foreground {
                     let foreground_blended = foreground.alpha_composite(cell.background);
                     cell.set_foreground(foreground_blended, depth);
@@ -196,6 +356,12 @@ impl FrameBuffer {
                     let background_blended = background.alpha_composite(cell.background);
                     cell.set_background(background_blended, depth);
                 }
+                if let Some(default_foreground) = render_cell.style.default_foreground {
+                    cell.set_default_foreground(default_foreground, depth);
+                }
+                if let Some(default_background) = render_cell.style.default_background {
+                    cell.set_default_background(default_background, depth);
+                }
             }
         }
     }
@@ -220,22 +386,69 @@ impl FrameBuffer {
             );
         }
     }
+
+    pub fn set_cursor_relative_to_ctx<'a>(&mut self, ctx: Ctx<'a>, cursor: Cursor) {
+        if let Some(absolute_coord) = ctx.bounding_box.coord_relative_to_absolute(cursor.coord) {
+            self.set_cursor(cursor.with_coord(absolute_coord));
+        }
+    }
+
+    /// Draw `image` over a rectangle of `size` cells. Images which don't fit entirely within the
+    /// bounding box of `ctx` are ignored.
+    pub fn draw_image_relative_to_ctx<'a>(
+        &mut self,
+        ctx: Ctx<'a>,
+        image: &Image,
+        coord: Coord,
+        size: Size,
+    ) {
+        let bottom_right = match size.to_coord() {
+            Ok(size) => coord + size - Coord::new(1, 1),
+            Err(_) => return,
+        };
+        if let (Some(absolute_coord), Some(_)) = (
+            ctx.bounding_box.coord_relative_to_absolute(coord),
+            ctx.bounding_box.coord_relative_to_absolute(bottom_right),
+        ) {
+            self.draw_image(ImagePlacement::new(image.clone(), absolute_coord, size));
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct Style {
     pub bold: Option<bool>,
     pub underline: Option<bool>,
+    pub italic: Option<bool>,
+    pub strikethrough: Option<bool>,
+    pub dim: Option<bool>,
+    pub reverse: Option<bool>,
+    pub blink: Option<bool>,
     pub foreground: Option<Rgba32>,
     pub background: Option<Rgba32>,
+    /// Draw in the terminal's default foreground colour rather than `foreground`
+    pub default_foreground: Option<bool>,
+    /// Leave the background in the terminal's default background colour rather than `background`
+    pub default_background: Option<bool>,
+    /// Drawing a character replaces the cell's link with this one, or removes the link if this is
+    /// unset. Handles come from `FrameBuffer::hyperlink`.
+    pub hyperlink: Option<Hyperlink>,
 }
 
 impl Style {
     pub const DEFAULT: Self = Self {
         bold: None,
         underline: None,
+        italic: None,
+        strikethrough: None,
+        dim: None,
+        reverse: None,
+        blink: None,
         foreground: None,
         background: None,
+        default_foreground: None,
+        default_background: None,
+        hyperlink: None,
     };
 
     fn apply_tint(self, tint: &dyn Tint) -> Self {
@@ -334,7 +547,7 @@ impl<'a> Ctx<'a> {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum Event {
     Input(Input),
     Tick(Duration),
@@ -523,14 +736,26 @@ where
         self.component
             .render(&(), self.frame_buffer.default_ctx(), &mut self.frame_buffer);
         for (coord, cell) in self.frame_buffer.enumerate() {
+            // the frame keeps its own table of hyperlink targets, so links are registered again
+            let hyperlink = cell
+                .hyperlink
+                .and_then(|hyperlink| self.frame_buffer.hyperlink_target(hyperlink))
+                .map(|target| frame.hyperlink(target));
             frame.set_cell_absolute(
                 coord,
                 1,
                 chargrid_render::ViewCell {
-                    character: Some(cell.character),
+                    character: Some(cell.character.into()),
                     style: chargrid_render::Style {
                         bold: Some(cell.bold),
                         underline: Some(cell.underline),
+                        underline_kind: None,
+                        underline_colour: None,
+                        italic: Some(cell.italic),
+                        strikethrough: Some(cell.strikethrough),
+                        dim: Some(cell.dim),
+                        reverse: Some(cell.reverse),
+                        blink: Some(cell.blink),
                         foreground: Some(chargrid_render::Rgb24::new(
                             cell.foreground.r,
                             cell.foreground.g,
@@ -541,10 +766,21 @@ where
                             cell.background.g,
                             cell.background.b,
                         )),
+                        default_foreground: Some(cell.default_foreground),
+                        default_background: Some(cell.default_background),
+                        hyperlink,
                     },
                 },
             );
         }
+        // the frame buffer isn't cleared between frames, but the cursor and images only last for a
+        // frame
+        if let Some(cursor) = self.frame_buffer.cursor.take() {
+            frame.set_cursor_absolute(cursor);
+        }
+        for placement in self.frame_buffer.images.drain(..) {
+            frame.draw_image_absolute(placement);
+        }
         self.component
             .update(
                 &mut (),
@@ -125,12 +125,22 @@ impl BorderStyle {
     }
     fn view_cell(&self, character: char) -> ViewCell {
         ViewCell {
-            character: Some(character),
+            character: Some(character.into()),
             style: Style {
                 foreground: Some(self.foreground),
                 background: self.background,
                 bold: Some(self.bold),
                 underline: Some(false),
+                underline_kind: None,
+                underline_colour: None,
+                italic: Some(false),
+                strikethrough: Some(false),
+                dim: Some(false),
+                reverse: Some(false),
+                blink: Some(false),
+                default_foreground: None,
+                default_background: None,
+                hyperlink: None,
             },
         }
     }
@@ -183,7 +193,7 @@ where
                 0,
                 ViewCell {
                     style: style.title_style,
-                    character: Some(ch),
+                    character: Some(ch.into()),
                 },
                 context,
             );
@@ -127,7 +127,7 @@ fn render_scroll_bar<F: Frame, C: ColModify>(
     if limits.last_rendered_inner_height > limits.last_rendered_outer_height {
         let view_cell = ViewCell {
             style: scroll_bar_style.style,
-            character: Some(scroll_bar_style.character),
+            character: Some(scroll_bar_style.character.into()),
         };
         let bar_x = context.size.width() as i32 - 1;
         let bar_height = (limits.last_rendered_outer_height * limits.last_rendered_outer_height)
@@ -173,6 +173,9 @@ where
                     background: context
                         .col_modify
                         .background(relative_cell.style.background),
+                    underline_colour: relative_cell.style.underline_colour.and_then(
+                        |underline_colour| context.col_modify.foreground(Some(underline_colour)),
+                    ),
                     ..relative_cell.style
                 },
                 ..relative_cell
@@ -233,6 +236,50 @@ where
             blend,
         );
     }
+
+    fn set_cursor_relative<C: ColModify>(
+        &mut self,
+        relative_cursor: Cursor,
+        context: ViewContext<C>,
+    ) {
+        let adjusted_relative_coord = relative_cursor.coord - self.offset;
+        if adjusted_relative_coord.is_valid(context.size) {
+            let absolute_coord = adjusted_relative_coord + context.offset;
+            self.set_cursor_absolute(relative_cursor.with_coord(absolute_coord));
+        }
+    }
+
+    fn set_cursor_absolute(&mut self, absolute_cursor: Cursor) {
+        self.frame.set_cursor_absolute(absolute_cursor);
+    }
+
+    fn hyperlink(&mut self, target: &str) -> Hyperlink {
+        self.frame.hyperlink(target)
+    }
+
+    fn draw_image_relative<C: ColModify>(
+        &mut self,
+        image: &Image,
+        relative_coord: Coord,
+        size: Size,
+        context: ViewContext<C>,
+    ) {
+        let bottom = relative_coord.y + size.height() as i32 - 1;
+        self.max_y = self.max_y.max(bottom + context.offset.y);
+        // images which are partially scrolled out of view are hidden entirely
+        let adjusted_relative_coord = relative_coord - self.offset;
+        let placement = ImagePlacement::new(image.clone(), adjusted_relative_coord, size);
+        if placement.fits_within(context.size) {
+            self.draw_image_absolute(ImagePlacement {
+                coord: adjusted_relative_coord + context.offset,
+                ..placement
+            });
+        }
+    }
+
+    fn draw_image_absolute(&mut self, absolute_placement: ImagePlacement) {
+        self.frame.draw_image_absolute(absolute_placement);
+    }
 }
 
 impl<'s, 'l, V, T> View<T> for VerticalScrollView<'s, 'l, V>
@@ -1,7 +1,7 @@
 use crate::*;
 use chargrid_input::keys;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum CommonEvent {
     Input(Input),
     Frame(Duration),
@@ -4,6 +4,7 @@ use simon::*;
 
 #[derive(Clone)]
 enum ColEncodeChoice {
+    Auto,
     TrueColour,
     Rgb,
     Greyscale,
@@ -19,18 +20,25 @@ impl ColEncodeChoice {
             flag("", "greyscale", "").some_if(Greyscale),
             flag("", "ansi", "").some_if(Ansi),
         })
-        .with_default(Rgb)
+        .with_default(Auto)
     }
 }
 
 fn main() {
-    let col_encode_choice = ColEncodeChoice::arg()
-        .with_help_default()
-        .parse_env_or_exit();
-    let context = Context::new().unwrap();
+    let (col_encode_choice, dither) = args_all! {
+        ColEncodeChoice::arg(),
+        flag("", "dither", "dither background colours"),
+    }
+    .with_help_default()
+    .parse_env_or_exit();
+    let context = Context::new().unwrap().with_dithering(dither);
     let app = app();
     use ColEncodeChoice as C;
     match col_encode_choice {
+        C::Auto => {
+            let col_encode = context.col_encode();
+            context.run_app(app, col_encode)
+        }
         C::TrueColour => context.run_app(app, col_encode::XtermTrueColour),
         C::Rgb => context.run_app(app, col_encode::FromTermInfoRgb),
         C::Greyscale => context.run_app(app, col_encode::FromTermInfoGreyscale),
@@ -1,7 +1,8 @@
-use chargrid_ansi_terminal::{col_encode, Context};
+use chargrid_ansi_terminal::Context;
 use colour_picker_app::app;
 
 fn main() {
     let context = Context::new().unwrap();
-    context.run_app(app(), col_encode::FromTermInfoRgb);
+    let col_encode = context.col_encode();
+    context.run_app(app(), col_encode);
 }
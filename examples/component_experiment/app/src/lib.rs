@@ -23,6 +23,7 @@ impl HelloWorld {
                     background: Some(Rgba32::new_rgb(255, 0, 255)),
                     underline: Some(true),
                     bold: Some(true),
+                    ..Style::default()
                 },
             }
             .wrap_word(),
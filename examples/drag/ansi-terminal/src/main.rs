@@ -1,7 +1,8 @@
-use chargrid_ansi_terminal::{col_encode, Context};
+use chargrid_ansi_terminal::Context;
 use drag_app::App;
 
 fn main() {
     let context = Context::new().unwrap();
-    context.run_app(App::default(), col_encode::FromTermInfoRgb);
+    let col_encode = context.col_encode();
+    context.run_app(App::default(), col_encode);
 }
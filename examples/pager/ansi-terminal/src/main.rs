@@ -1,11 +1,13 @@
-use chargrid_ansi_terminal::{col_encode, Context};
+use chargrid_ansi_terminal::Context;
 use pager_app::App;
+use std::error::Error;
 use std::io::{self, Read};
 
-fn main() -> io::Result<()> {
+fn main() -> Result<(), Box<dyn Error>> {
     let mut text = String::new();
     io::stdin().read_to_string(&mut text)?;
-    let context = Context::new().unwrap();
-    context.run_app(App::new(text), col_encode::FromTermInfoRgb);
+    let context = Context::new()?.with_idle_mode(true);
+    let col_encode = context.col_encode();
+    context.try_run_app(App::new(text), col_encode)?;
     Ok(())
 }
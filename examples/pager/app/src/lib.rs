@@ -201,3 +201,38 @@ impl app::App for App {
         None
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chargrid_headless::{string_rows, Context, Duration, Step};
+
+    #[test]
+    fn scroll_and_quit() {
+        let frame_duration = Duration::from_millis(16);
+        let text = (0..100)
+            .map(|i| format!("line {}\n", i))
+            .collect::<String>();
+        let script = vec![
+            Step::Frame(frame_duration),
+            Step::Input(Input::Keyboard(KeyboardInput::End)),
+            Step::Frame(frame_duration),
+            Step::Input(Input::Keyboard(KeyboardInput::Char('q'))),
+            Step::Frame(frame_duration),
+        ];
+        let capture = Context::new(Size::new(44, 34)).run_app(App::new(text), script);
+        assert!(capture.exited);
+        assert_eq!(capture.frames.len(), 2);
+        let first = string_rows(&capture.frames[0]);
+        assert!(first[0].starts_with("┌┤Pager├"));
+        assert_eq!(
+            first[6].trim_end(),
+            "│ line 0                                █ │"
+        );
+        let last = string_rows(&capture.frames[1]);
+        assert_eq!(
+            last[29].trim_end(),
+            "│ line 99                               █ │"
+        );
+    }
+}
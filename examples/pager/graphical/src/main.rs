@@ -10,6 +10,8 @@ fn main() -> io::Result<()> {
         font_bytes: FontBytes {
             normal: include_bytes!("./fonts/PxPlus_IBM_CGAthin.ttf").to_vec(),
             bold: include_bytes!("./fonts/PxPlus_IBM_CGA.ttf").to_vec(),
+            italic: None,
+            bold_italic: None,
         },
         title: "Pager".to_string(),
         window_dimensions_px: Dimensions {
@@ -1,9 +1,10 @@
-use chargrid_ansi_terminal::{col_encode, Context};
+use chargrid_ansi_terminal::Context;
 use general_audio_native::NativeAudioPlayer;
 use soundboard_app::app;
 
 fn main() {
     let player = NativeAudioPlayer::new_default_device();
     let context = Context::new().unwrap();
-    context.run_app(app(player), col_encode::FromTermInfoRgb);
+    let col_encode = context.col_encode();
+    context.run_app(app(player), col_encode);
 }
@@ -1,8 +1,9 @@
-use chargrid_ansi_terminal::{col_encode, Context};
+use chargrid_ansi_terminal::Context;
 use tetris_app::TetrisApp;
 
 fn main() {
     let context = Context::new().unwrap();
     let app = TetrisApp::new(rand::thread_rng());
-    context.run_app(app, col_encode::FromTermInfoRgb);
+    let col_encode = context.col_encode();
+    context.run_app(app, col_encode);
 }
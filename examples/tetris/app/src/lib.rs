@@ -45,11 +45,11 @@ impl<'a> View<&'a Tetris> for TetrisBoardView {
             for (j, cell) in row.cells.iter().enumerate() {
                 let mut cell_info = ViewCell::new().with_bold(true);
                 if let Some(typ) = cell.typ {
-                    cell_info.character = Some(BLOCK_CHAR);
+                    cell_info.character = Some(BLOCK_CHAR.into());
                     cell_info.style.foreground = Some(FOREGROUND_COLOUR);
                     cell_info.style.background = Some(piece_colour(typ));
                 } else {
-                    cell_info.character = Some(BLANK_CHAR);
+                    cell_info.character = Some(BLANK_CHAR.into());
                     cell_info.style.foreground = Some(BLANK_FOREGROUND_COLOUR);
                     cell_info.style.background = Some(BACKGROUND_COLOUR);
                 }
@@ -58,12 +58,22 @@ impl<'a> View<&'a Tetris> for TetrisBoardView {
         }
         for coord in tetris.game_state.piece.coords.iter().cloned() {
             let cell_info = ViewCell {
-                character: Some(BLOCK_CHAR),
+                character: Some(BLOCK_CHAR.into()),
                 style: Style {
                     bold: Some(true),
                     underline: Some(false),
+                    underline_kind: None,
+                    underline_colour: None,
+                    italic: Some(false),
+                    strikethrough: Some(false),
+                    dim: Some(false),
+                    reverse: Some(false),
+                    blink: Some(false),
                     foreground: Some(FOREGROUND_COLOUR),
                     background: Some(piece_colour(tetris.game_state.piece.typ)),
+                    default_foreground: None,
+                    default_background: None,
+                    hyperlink: None,
                 },
             };
             frame.set_cell_relative(coord, 0, cell_info, context);
@@ -84,12 +94,22 @@ impl<'a> View<&'a Tetris> for TetrisNextPieceView {
         let offset = Coord::new(1, 0);
         for coord in tetris.game_state.next_piece.coords.iter().cloned() {
             let cell_info = ViewCell {
-                character: Some(BLOCK_CHAR),
+                character: Some(BLOCK_CHAR.into()),
                 style: Style {
                     bold: Some(true),
                     underline: Some(false),
+                    underline_kind: None,
+                    underline_colour: None,
+                    italic: Some(false),
+                    strikethrough: Some(false),
+                    dim: Some(false),
+                    reverse: Some(false),
+                    blink: Some(false),
                     foreground: Some(FOREGROUND_COLOUR),
                     background: Some(piece_colour(tetris.game_state.next_piece.typ)),
+                    default_foreground: None,
+                    default_background: None,
+                    hyperlink: None,
                 },
             };
             frame.set_cell_relative(offset + coord, 0, cell_info, context);
@@ -220,7 +240,8 @@ impl AppData {
                 for input in inputs {
                     match input {
                         Input::Keyboard(keys::ETX) => return Some(app::ControlFlow::Exit),
-                        Input::Keyboard(keys::ESCAPE) => {
+                        // pause when the player switches to another window
+                        Input::Keyboard(keys::ESCAPE) | Input::FocusLost => {
                             self.state = AppState::Menu;
                         }
                         Input::Keyboard(KeyboardInput::Up) => {
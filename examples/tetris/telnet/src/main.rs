@@ -0,0 +1,9 @@
+use chargrid_telnet::Server;
+use tetris_app::TetrisApp;
+
+fn main() {
+    let server = Server::bind("127.0.0.1:2323").unwrap();
+    server
+        .run(|_context| TetrisApp::new(rand::thread_rng()))
+        .unwrap();
+}
@@ -1,16 +1,105 @@
-use chargrid_app::{App, ControlFlow};
+use chargrid_app::{App, Clipboard, ControlFlow};
 pub use chargrid_graphical_common::*;
-use chargrid_input::{keys, Input, KeyboardInput, MouseButton, MouseInput, ScrollDirection};
-use chargrid_render::{Buffer, Coord, Rgb24, Size, ViewContext};
+use chargrid_input::{
+    keys, Input, KeyModifiers, KeyboardInput, MouseButton, MouseInput, ScrollDirection,
+};
+use chargrid_render::{Buffer, Coord, CursorShape, Rgb24, Size, UnderlineKind, ViewContext};
+use std::process;
 use std::time::Instant;
 
 pub struct Context {
     config: Config,
+    clipboard: Clipboard,
 }
 
 struct Fonts {
     normal: ggez::graphics::Font,
     bold: ggez::graphics::Font,
+    italic: ggez::graphics::Font,
+    bold_italic: ggez::graphics::Font,
+}
+
+struct UnderlineMeshes {
+    single: ggez::graphics::Mesh,
+    double: ggez::graphics::Mesh,
+    curly: ggez::graphics::Mesh,
+    dotted: ggez::graphics::Mesh,
+    dashed: ggez::graphics::Mesh,
+}
+
+impl UnderlineMeshes {
+    fn new(ctx: &mut ggez::Context, config: &Config) -> ggez::GameResult<Self> {
+        const CURLY_SEGMENTS: usize = 8;
+        let cell_width = config.cell_dimensions_px.width as f32;
+        let underline_mid_cell_ratio =
+            config.underline_top_offset_cell_ratio + config.underline_width_cell_ratio / 2.0;
+        let y = (underline_mid_cell_ratio * config.cell_dimensions_px.height) as f32;
+        let width = (config.underline_width_cell_ratio * config.cell_dimensions_px.height) as f32;
+        let point = |x: f32, y: f32| ggez::mint::Point2 { x, y };
+        let white = [1., 1., 1., 1.].into();
+        // draws a line for each (start, end) fraction of the cell width at a given height
+        let build_segments = |ctx: &mut ggez::Context,
+                              y: f32,
+                              segments: &[(f32, f32)]|
+         -> ggez::GameResult<ggez::graphics::Mesh> {
+            let mut builder = ggez::graphics::MeshBuilder::new();
+            for &(start, end) in segments {
+                builder.line(
+                    &[point(start * cell_width, y), point(end * cell_width, y)],
+                    width,
+                    white,
+                )?;
+            }
+            builder.build(ctx)
+        };
+        let single = build_segments(ctx, y, &[(0., 1.)])?;
+        let double = {
+            let mut builder = ggez::graphics::MeshBuilder::new();
+            for &y in &[y, y - 2. * width] {
+                builder.line(&[point(0., y), point(cell_width, y)], width, white)?;
+            }
+            builder.build(ctx)?
+        };
+        let curly = {
+            // one period of a sine wave per cell
+            let points = (0..=CURLY_SEGMENTS)
+                .map(|i| {
+                    let ratio = i as f32 / CURLY_SEGMENTS as f32;
+                    let offset = width * (ratio * std::f32::consts::PI * 2.).sin();
+                    point(ratio * cell_width, y + offset)
+                })
+                .collect::<Vec<_>>();
+            ggez::graphics::Mesh::new_polyline(
+                ctx,
+                ggez::graphics::DrawMode::stroke(width),
+                &points,
+                white,
+            )?
+        };
+        let dotted = build_segments(
+            ctx,
+            y,
+            &[(0., 0.125), (0.25, 0.375), (0.5, 0.625), (0.75, 0.875)],
+        )?;
+        let dashed = build_segments(ctx, y, &[(0., 0.35), (0.5, 0.85)])?;
+        Ok(Self {
+            single,
+            double,
+            curly,
+            dotted,
+            dashed,
+        })
+    }
+
+    fn get(&self, underline_kind: UnderlineKind) -> &ggez::graphics::Mesh {
+        match underline_kind {
+            UnderlineKind::Single => &self.single,
+            UnderlineKind::Double => &self.double,
+            UnderlineKind::Curly => &self.curly,
+            UnderlineKind::Dotted => &self.dotted,
+            UnderlineKind::Dashed => &self.dashed,
+        }
+    }
 }
 
 struct GgezApp<A: App + 'static> {
@@ -19,17 +108,43 @@ struct GgezApp<A: App + 'static> {
     buffer: Buffer,
     last_frame: Instant,
     font_scale: ggez::graphics::PxScale,
-    underline_mesh: ggez::graphics::Mesh,
+    underline_meshes: UnderlineMeshes,
+    strikethrough_mesh: ggez::graphics::Mesh,
     background_mesh: ggez::graphics::Mesh,
     cell_width: f32,
     cell_height: f32,
+    underline_width_cell_ratio: f32,
     current_mouse_button: Option<MouseButton>,
     current_mouse_position: Coord,
+    clipboard: Clipboard,
+    system_clipboard: Option<arboard::Clipboard>,
+    exit: Option<chargrid_app::Result<()>>,
     #[cfg(feature = "gamepad")]
-    gamepad_id_to_integer_id: hashbrown::HashMap<ggez::event::GamepadId, u64>,
+    gamepad_id_to_integer_id: hashbrown::HashMap<ggez::input::gamepad::gilrs::GamepadId, u64>,
 }
 
 impl<A: App + 'static> GgezApp<A> {
+    /// Stop the event loop after the app exits, keeping the result of the first exit to be
+    /// returned once the event loop has stopped
+    fn exit(&mut self, ctx: &mut ggez::Context, control_flow: ControlFlow) {
+        self.exit.get_or_insert(control_flow.into_result());
+        ggez::event::quit(ctx);
+    }
+
+    fn is_paste(
+        keycode: ggez::input::keyboard::KeyCode,
+        keymods: ggez::input::keyboard::KeyMods,
+    ) -> bool {
+        use ggez::input::keyboard::{KeyCode, KeyMods};
+        let paste_modifier = if cfg!(target_os = "macos") {
+            KeyMods::LOGO
+        } else {
+            KeyMods::CTRL
+        };
+        (keycode == KeyCode::V && keymods.contains(paste_modifier))
+            || (keycode == KeyCode::Insert && keymods.contains(KeyMods::SHIFT))
+    }
+
     fn convert_mouse_position(&self, x: f32, y: f32) -> Coord {
         Coord {
             x: (x / self.cell_width) as i32,
@@ -37,6 +152,24 @@ impl<A: App + 'static> GgezApp<A> {
         }
     }
 
+    fn convert_keymods(keymods: ggez::input::keyboard::KeyMods) -> KeyModifiers {
+        use ggez::input::keyboard::KeyMods;
+        let mut modifiers = KeyModifiers::NONE;
+        if keymods.contains(KeyMods::SHIFT) {
+            modifiers |= KeyModifiers::SHIFT;
+        }
+        if keymods.contains(KeyMods::ALT) {
+            modifiers |= KeyModifiers::ALT;
+        }
+        if keymods.contains(KeyMods::CTRL) {
+            modifiers |= KeyModifiers::CTRL;
+        }
+        if keymods.contains(KeyMods::LOGO) {
+            modifiers |= KeyModifiers::SUPER;
+        }
+        modifiers
+    }
+
     fn convert_mouse_button(button: ggez::event::MouseButton) -> Option<MouseButton> {
         match button {
             ggez::input::mouse::MouseButton::Left => Some(MouseButton::Left),
@@ -45,6 +178,49 @@ impl<A: App + 'static> GgezApp<A> {
             ggez::input::mouse::MouseButton::Other(_) => None,
         }
     }
+
+    #[cfg(feature = "gamepad")]
+    fn gamepad_event(&mut self, ctx: &mut ggez::Context, event: ggez::input::gamepad::Event) {
+        use chargrid_input::{GamepadButton, GamepadInput};
+        use ggez::input::gamepad::gilrs::EventType;
+        let (btn, id) = match event.event {
+            EventType::ButtonPressed(btn, _) => (btn, event.id),
+            _ => return,
+        };
+        let num_gamepad_ids = self.gamepad_id_to_integer_id.len() as u64;
+        let &mut integer_id = self
+            .gamepad_id_to_integer_id
+            .entry(id)
+            .or_insert(num_gamepad_ids);
+        let button = match btn {
+            ggez::event::Button::DPadUp => GamepadButton::DPadUp,
+            ggez::event::Button::DPadRight => GamepadButton::DPadRight,
+            ggez::event::Button::DPadDown => GamepadButton::DPadDown,
+            ggez::event::Button::DPadLeft => GamepadButton::DPadLeft,
+            ggez::event::Button::North => GamepadButton::North,
+            ggez::event::Button::East => GamepadButton::East,
+            ggez::event::Button::South => GamepadButton::South,
+            ggez::event::Button::West => GamepadButton::West,
+            ggez::event::Button::Start => GamepadButton::Start,
+            ggez::event::Button::Select => GamepadButton::Select,
+            ggez::event::Button::LeftTrigger => GamepadButton::LeftBumper,
+            ggez::event::Button::RightTrigger => GamepadButton::RightBumper,
+            other => {
+                log::warn!("Unhandled input: {:?}", other);
+                return;
+            }
+        };
+        let input = GamepadInput {
+            button,
+            id: integer_id,
+        };
+        if let Some(control_flow) = self.chargrid_app.on_input(Input::Gamepad(input)) {
+            self.exit(ctx, control_flow);
+        }
+    }
+
+    #[cfg(not(feature = "gamepad"))]
+    fn gamepad_event(&mut self, _ctx: &mut ggez::Context, _event: ggez::input::gamepad::Event) {}
 }
 
 impl<A: App + 'static> ggez::event::EventHandler<ggez::GameError> for GgezApp<A> {
@@ -54,62 +230,117 @@ impl<A: App + 'static> ggez::event::EventHandler<ggez::GameError> for GgezApp<A>
         let now = Instant::now();
         self.buffer.clear();
         let view_context = ViewContext::default_with_size(self.buffer.size());
-        if let Some(ControlFlow::Exit) =
+        if let Some(control_flow) =
             self.chargrid_app
                 .on_frame(now - self.last_frame, view_context, &mut self.buffer)
         {
-            ggez::event::quit(ctx);
+            self.exit(ctx, control_flow);
         }
         self.last_frame = now;
+        if let Some(text) = self.clipboard.take_text() {
+            if let Some(system_clipboard) = self.system_clipboard.as_mut() {
+                if let Err(error) = system_clipboard.set_text(text) {
+                    log::warn!("failed to write to the system clipboard: {}", error);
+                }
+            }
+        }
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
         ggez::graphics::clear(ctx, [0., 0., 0., 1.0].into());
+        let since_start = ggez::timer::time_since_start(ctx);
+        let hide_blinking = blink_hidden(since_start);
+        let cursor = visible_cursor(&self.buffer, since_start);
         for (coord, cell) in self.buffer.enumerate() {
-            if cell.character != ' ' {
-                let mut text = ggez::graphics::Text::new(cell.character);
-                let font = if cell.bold {
-                    self.fonts.bold
-                } else {
-                    self.fonts.normal
+            let cursor_shape = cursor
+                .filter(|cursor| cursor.coord == coord)
+                .map(|cursor| cursor.shape);
+            let (foreground_colour, background_colour) = match cursor_shape {
+                Some(CursorShape::Block) => {
+                    let (foreground_colour, background_colour) = cell.display_colours();
+                    (background_colour, foreground_colour)
+                }
+                _ => cell.display_colours(),
+            };
+            let position = ggez::mint::Point2 {
+                x: coord.x as f32 * self.cell_width,
+                y: coord.y as f32 * self.cell_height,
+            };
+            if cell.character != ' '
+                && !cell.character.is_continuation()
+                && !(cell.blink && hide_blinking)
+            {
+                let mut text = ggez::graphics::Text::new(cell.character.as_str());
+                let font = match (cell.bold, cell.italic) {
+                    (false, false) => self.fonts.normal,
+                    (true, false) => self.fonts.bold,
+                    (false, true) => self.fonts.italic,
+                    (true, true) => self.fonts.bold_italic,
                 };
                 text.set_font(font, self.font_scale);
                 ggez::graphics::queue_text(
                     ctx,
                     &text,
-                    ggez::mint::Point2 {
-                        x: coord.x as f32 * self.cell_width,
-                        y: coord.y as f32 * self.cell_height,
-                    },
-                    Some(cell.foreground_colour.to_f32_rgba(1.).into()),
+                    position,
+                    Some(foreground_colour.to_f32_rgba(1.).into()),
                 );
             }
-            if cell.background_colour != Rgb24::new(0, 0, 0) {
+            if background_colour != Rgb24::new(0, 0, 0) {
                 ggez::graphics::draw(
                     ctx,
                     &self.background_mesh,
                     ggez::graphics::DrawParam::default()
-                        .dest(ggez::mint::Point2 {
-                            x: coord.x as f32 * self.cell_width,
-                            y: coord.y as f32 * self.cell_height,
-                        })
-                        .color(cell.background_colour.to_f32_rgba(1.).into()),
-                )
-                .expect("failed to draw background");
+                        .dest(position)
+                        .color(background_colour.to_f32_rgba(1.).into()),
+                )?;
             }
             if cell.underline {
+                let underline_colour = cell.underline_colour.unwrap_or(foreground_colour);
                 ggez::graphics::draw(
                     ctx,
-                    &self.underline_mesh,
+                    self.underline_meshes.get(cell.underline_kind),
                     ggez::graphics::DrawParam::default()
-                        .dest(ggez::mint::Point2 {
-                            x: coord.x as f32 * self.cell_width,
-                            y: coord.y as f32 * self.cell_height,
-                        })
-                        .color(cell.foreground_colour.to_f32_rgba(1.).into()),
-                )
-                .expect("failed to draw underline");
+                        .dest(position)
+                        .color(underline_colour.to_f32_rgba(1.).into()),
+                )?;
+            }
+            if cell.strikethrough {
+                ggez::graphics::draw(
+                    ctx,
+                    &self.strikethrough_mesh,
+                    ggez::graphics::DrawParam::default()
+                        .dest(position)
+                        .color(foreground_colour.to_f32_rgba(1.).into()),
+                )?;
+            }
+            // cursors are twice as thick as underlines, and cells are roughly twice as tall as
+            // they are wide
+            let cursor_param = match cursor_shape {
+                Some(CursorShape::Bar) => Some(
+                    ggez::graphics::DrawParam::default()
+                        .dest(position)
+                        .scale([self.underline_width_cell_ratio * 4., 1.]),
+                ),
+                Some(CursorShape::Underline) => {
+                    let height_ratio = self.underline_width_cell_ratio * 2.;
+                    Some(
+                        ggez::graphics::DrawParam::default()
+                            .dest(ggez::mint::Point2 {
+                                x: position.x,
+                                y: position.y + (1. - height_ratio) * self.cell_height,
+                            })
+                            .scale([1., height_ratio]),
+                    )
+                }
+                Some(CursorShape::Block) | None => None,
+            };
+            if let Some(cursor_param) = cursor_param {
+                ggez::graphics::draw(
+                    ctx,
+                    &self.background_mesh,
+                    cursor_param.color(foreground_colour.to_f32_rgba(1.).into()),
+                )?;
             }
         }
         ggez::graphics::draw_queued_text(
@@ -138,6 +369,19 @@ impl<A: App + 'static> ggez::event::EventHandler<ggez::GameError> for GgezApp<A>
         keymods: ggez::input::keyboard::KeyMods,
         _repeat: bool,
     ) {
+        if Self::is_paste(keycode, keymods) {
+            let text = self
+                .system_clipboard
+                .as_mut()
+                .and_then(|system_clipboard| system_clipboard.get_text().ok());
+            // if the clipboard can't be read, the key press is delivered as input instead
+            if let Some(text) = text {
+                if let Some(control_flow) = self.chargrid_app.on_input(Input::Paste(text)) {
+                    self.exit(ctx, control_flow);
+                }
+                return;
+            }
+        }
         let key_char_shift = |lower: char, upper: char| {
             KeyboardInput::Char(if keymods.contains(ggez::input::keyboard::KeyMods::SHIFT) {
                 upper
@@ -242,13 +486,17 @@ impl<A: App + 'static> ggez::event::EventHandler<ggez::GameError> for GgezApp<A>
             ggez::event::KeyCode::Right => KeyboardInput::Right,
             ggez::event::KeyCode::Return => keys::RETURN,
             ggez::event::KeyCode::Escape => keys::ESCAPE,
+            ggez::event::KeyCode::Tab => keys::TAB,
+            ggez::event::KeyCode::Back => keys::BACKSPACE,
+            ggez::event::KeyCode::Delete => KeyboardInput::Delete,
             other => {
                 log::warn!("Unhandled input: {:?}", other);
                 return;
             }
         };
-        if let Some(ControlFlow::Exit) = self.chargrid_app.on_input(Input::Keyboard(input)) {
-            ggez::event::quit(ctx);
+        let input = Input::keyboard_with_modifiers(input, Self::convert_keymods(keymods));
+        if let Some(control_flow) = self.chargrid_app.on_input(input) {
+            self.exit(ctx, control_flow);
         }
     }
 
@@ -263,9 +511,13 @@ impl<A: App + 'static> ggez::event::EventHandler<ggez::GameError> for GgezApp<A>
             self.current_mouse_button = Some(button);
             let coord = self.convert_mouse_position(x, y);
             self.current_mouse_position = coord;
-            let input = MouseInput::MousePress { button, coord };
-            if let Some(ControlFlow::Exit) = self.chargrid_app.on_input(Input::Mouse(input)) {
-                ggez::event::quit(ctx);
+            let input = Input::Mouse(MouseInput::MousePress { button, coord });
+            let hyperlink_click = hyperlink_click(&self.buffer, &input);
+            for input in std::iter::once(input).chain(hyperlink_click) {
+                if let Some(control_flow) = self.chargrid_app.on_input(input) {
+                    self.exit(ctx, control_flow);
+                    return;
+                }
             }
         }
     }
@@ -285,8 +537,8 @@ impl<A: App + 'static> ggez::event::EventHandler<ggez::GameError> for GgezApp<A>
                 button: Ok(button),
                 coord,
             };
-            if let Some(ControlFlow::Exit) = self.chargrid_app.on_input(Input::Mouse(input)) {
-                ggez::event::quit(ctx);
+            if let Some(control_flow) = self.chargrid_app.on_input(Input::Mouse(input)) {
+                self.exit(ctx, control_flow);
             }
         }
     }
@@ -298,8 +550,8 @@ impl<A: App + 'static> ggez::event::EventHandler<ggez::GameError> for GgezApp<A>
             coord,
             button: self.current_mouse_button,
         };
-        if let Some(ControlFlow::Exit) = self.chargrid_app.on_input(Input::Mouse(input)) {
-            ggez::event::quit(ctx);
+        if let Some(control_flow) = self.chargrid_app.on_input(Input::Mouse(input)) {
+            self.exit(ctx, control_flow);
         }
     }
 
@@ -307,8 +559,8 @@ impl<A: App + 'static> ggez::event::EventHandler<ggez::GameError> for GgezApp<A>
         let mut handle = |direction| {
             let coord = self.current_mouse_position;
             let input = MouseInput::MouseScroll { direction, coord };
-            if let Some(ControlFlow::Exit) = self.chargrid_app.on_input(Input::Mouse(input)) {
-                ggez::event::quit(ctx);
+            if let Some(control_flow) = self.chargrid_app.on_input(Input::Mouse(input)) {
+                self.exit(ctx, control_flow);
             }
         };
         if x > 0.0 {
@@ -325,51 +577,23 @@ impl<A: App + 'static> ggez::event::EventHandler<ggez::GameError> for GgezApp<A>
         }
     }
 
-    fn quit_event(&mut self, _ctx: &mut ggez::Context) -> bool {
-        if let Some(ControlFlow::Exit) = self.chargrid_app.on_input(Input::Keyboard(keys::ETX)) {
-            false
+    fn focus_event(&mut self, ctx: &mut ggez::Context, gained: bool) {
+        let input = if gained {
+            Input::FocusGained
         } else {
-            true
+            Input::FocusLost
+        };
+        if let Some(control_flow) = self.chargrid_app.on_input(input) {
+            self.exit(ctx, control_flow);
         }
     }
 
-    #[cfg(feature = "gamepad")]
-    fn gamepad_button_down_event(
-        &mut self,
-        ctx: &mut ggez::Context,
-        btn: ggez::event::Button,
-        id: ggez::event::GamepadId,
-    ) {
-        use chargrid_input::{GamepadButton, GamepadInput};
-        let num_gamepad_ids = self.gamepad_id_to_integer_id.len() as u64;
-        let &mut integer_id = self
-            .gamepad_id_to_integer_id
-            .entry(id)
-            .or_insert(num_gamepad_ids);
-        let button = match btn {
-            ggez::event::Button::DPadUp => GamepadButton::DPadUp,
-            ggez::event::Button::DPadRight => GamepadButton::DPadRight,
-            ggez::event::Button::DPadDown => GamepadButton::DPadDown,
-            ggez::event::Button::DPadLeft => GamepadButton::DPadLeft,
-            ggez::event::Button::North => GamepadButton::North,
-            ggez::event::Button::East => GamepadButton::East,
-            ggez::event::Button::South => GamepadButton::South,
-            ggez::event::Button::West => GamepadButton::West,
-            ggez::event::Button::Start => GamepadButton::Start,
-            ggez::event::Button::Select => GamepadButton::Select,
-            ggez::event::Button::LeftTrigger => GamepadButton::LeftBumper,
-            ggez::event::Button::RightTrigger => GamepadButton::RightBumper,
-            other => {
-                log::warn!("Unhandled input: {:?}", other);
-                return;
-            }
-        };
-        let input = GamepadInput {
-            button,
-            id: integer_id,
-        };
-        if let Some(ControlFlow::Exit) = self.chargrid_app.on_input(Input::Gamepad(input)) {
-            ggez::event::quit(ctx);
+    fn quit_event(&mut self, ctx: &mut ggez::Context) -> bool {
+        if let Some(control_flow) = self.chargrid_app.on_input(Input::Keyboard(keys::ETX)) {
+            self.exit(ctx, control_flow);
+            false
+        } else {
+            true
         }
     }
 }
@@ -387,18 +611,49 @@ impl WindowHandle {
 
 impl Context {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        Self {
+            config,
+            clipboard: Clipboard::new(),
+        }
+    }
+
+    /// Returns a handle for writing to the system clipboard, which can be passed to an app
+    pub fn clipboard(&self) -> Clipboard {
+        self.clipboard.clone()
     }
 
     pub fn window_handle(&self) -> WindowHandle {
         WindowHandle {}
     }
 
+    /// Run the app until it exits, then exit the process. Panics if ggez can't be initialised or
+    /// the app exits with an error. See `try_run_app` for a version which returns errors instead.
     pub fn run_app<A>(self, app: A) -> !
     where
         A: App + 'static,
     {
-        let Self { config } = self;
+        match self.try_run_app(app) {
+            Ok(()) => process::exit(0),
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Run the app until it exits, returning an error if ggez can't be initialised, updating or
+    /// drawing the app fails, or the app exits with `ControlFlow::ExitWithError`. The window is
+    /// closed before this returns. Unlike `run_app`, this doesn't exit the process, but it's not
+    /// supported on all platforms which winit supports (see `winit::platform::run_return`).
+    pub fn try_run_app<A>(self, app: A) -> chargrid_app::Result<()>
+    where
+        A: App + 'static,
+    {
+        let Self { config, clipboard } = self;
+        let system_clipboard = match arboard::Clipboard::new() {
+            Ok(system_clipboard) => Some(system_clipboard),
+            Err(error) => {
+                log::warn!("failed to access the system clipboard: {}", error);
+                None
+            }
+        };
         let grid_size = Size::new(
             (config.window_dimensions_px.width as f64 / config.cell_dimensions_px.width) as u32,
             (config.window_dimensions_px.height as f64 / config.cell_dimensions_px.height) as u32,
@@ -416,37 +671,54 @@ impl Context {
                         .resizable(config.resizable),
                 )
                 .build()
-                .expect("failed to initialize ggez");
+                .map_err(chargrid_app::Error::frontend)?;
         let fonts = Fonts {
             normal: ggez::graphics::Font::new_glyph_font_bytes(&mut ctx, &config.font_bytes.normal)
-                .expect("failed to load normal font"),
+                .map_err(chargrid_app::Error::frontend)?,
             bold: ggez::graphics::Font::new_glyph_font_bytes(&mut ctx, &config.font_bytes.bold)
-                .expect("failed to load bold font"),
+                .map_err(chargrid_app::Error::frontend)?,
+            italic: ggez::graphics::Font::new_glyph_font_bytes(
+                &mut ctx,
+                config
+                    .font_bytes
+                    .italic
+                    .as_ref()
+                    .unwrap_or(&config.font_bytes.normal),
+            )
+            .map_err(chargrid_app::Error::frontend)?,
+            bold_italic: ggez::graphics::Font::new_glyph_font_bytes(
+                &mut ctx,
+                config
+                    .font_bytes
+                    .bold_italic
+                    .as_ref()
+                    .unwrap_or(&config.font_bytes.bold),
+            )
+            .map_err(chargrid_app::Error::frontend)?,
         };
-        let underline_mesh = {
-            let underline_mid_cell_ratio =
-                config.underline_top_offset_cell_ratio + config.underline_width_cell_ratio / 2.0;
-            let underline_cell_position =
-                (underline_mid_cell_ratio * config.cell_dimensions_px.height) as f32;
-            let underline_width =
+        let underline_meshes =
+            UnderlineMeshes::new(&mut ctx, &config).map_err(chargrid_app::Error::frontend)?;
+        let strikethrough_mesh = {
+            let strikethrough_cell_position = (config.cell_dimensions_px.height / 2.0) as f32;
+            let strikethrough_width =
                 (config.underline_width_cell_ratio * config.cell_dimensions_px.height) as f32;
             let points = [
                 ggez::mint::Point2 {
                     x: 0.0,
-                    y: underline_cell_position,
+                    y: strikethrough_cell_position,
                 },
                 ggez::mint::Point2 {
                     x: config.cell_dimensions_px.width as f32,
-                    y: underline_cell_position,
+                    y: strikethrough_cell_position,
                 },
             ];
             let mesh = ggez::graphics::Mesh::new_line(
                 &mut ctx,
                 &points,
-                underline_width,
+                strikethrough_width,
                 [1., 1., 1., 1.].into(),
             )
-            .expect("failed to build mesh for underline");
+            .map_err(chargrid_app::Error::frontend)?;
             mesh
         };
         let background_mesh = {
@@ -462,30 +734,143 @@ impl Context {
                 rect,
                 [1., 1., 1., 1.].into(),
             )
-            .expect("failed to build mesh for background");
+            .map_err(chargrid_app::Error::frontend)?;
             mesh
         };
-        ggez::event::run(
-            ctx,
-            events_loop,
-            GgezApp {
-                fonts,
-                chargrid_app: app,
-                buffer,
-                last_frame: Instant::now(),
-                font_scale: ggez::graphics::PxScale {
-                    x: config.font_scale.width as f32,
-                    y: config.font_scale.height as f32,
-                },
-                underline_mesh,
-                background_mesh,
-                cell_width: config.cell_dimensions_px.width as f32,
-                cell_height: config.cell_dimensions_px.height as f32,
-                current_mouse_button: None,
-                current_mouse_position: Coord::new(0, 0),
-                #[cfg(feature = "gamepad")]
-                gamepad_id_to_integer_id: hashbrown::HashMap::default(),
+        let mut app = GgezApp {
+            fonts,
+            chargrid_app: app,
+            buffer,
+            last_frame: Instant::now(),
+            font_scale: ggez::graphics::PxScale {
+                x: config.font_scale.width as f32,
+                y: config.font_scale.height as f32,
             },
-        )
+            underline_meshes,
+            strikethrough_mesh,
+            background_mesh,
+            cell_width: config.cell_dimensions_px.width as f32,
+            cell_height: config.cell_dimensions_px.height as f32,
+            underline_width_cell_ratio: config.underline_width_cell_ratio as f32,
+            current_mouse_button: None,
+            current_mouse_position: Coord::new(0, 0),
+            clipboard,
+            system_clipboard,
+            exit: None,
+            #[cfg(feature = "gamepad")]
+            gamepad_id_to_integer_id: hashbrown::HashMap::default(),
+        };
+        run_event_loop(ctx, events_loop, &mut app).map_err(chargrid_app::Error::frontend)?;
+        app.exit.unwrap_or(Ok(()))
     }
 }
+
+/// Dispatch events to the app until the event loop is stopped, returning the first error from
+/// updating or drawing the app. This follows `ggez::event::run` from ggez 0.6.1, and must be kept
+/// in step with it when upgrading ggez. It differs in that:
+/// - it returns once the event loop stops rather than exiting the process, using winit's
+///   `run_return` (which isn't available on every platform winit supports)
+/// - gamepad events are passed straight to the app rather than through `EventHandler`, as
+///   `GamepadId` can't be constructed outside of ggez
+/// - an error from updating or drawing stops the event loop and is returned, rather than being
+///   logged and passed to `EventHandler::on_error`
+/// - the mouse motion delta is the total motion since the last frame rather than since the last
+///   mouse event, which ggez doesn't expose (it's unused by `GgezApp`)
+fn run_event_loop<A: App + 'static>(
+    mut ctx: ggez::Context,
+    mut events_loop: ggez::event::EventLoop<()>,
+    app: &mut GgezApp<A>,
+) -> ggez::GameResult {
+    use ggez::event::winit_event::{
+        ElementState, Event, KeyboardInput, MouseScrollDelta, WindowEvent,
+    };
+    use ggez::event::{ControlFlow, EventHandler};
+    use ggez::input::{keyboard, mouse};
+    use ggez::winit::platform::run_return::EventLoopExtRunReturn;
+    let mut result = Ok(());
+    events_loop.run_return(|mut event, _, control_flow| {
+        if !ctx.continuing {
+            *control_flow = ControlFlow::Exit;
+            return;
+        }
+        *control_flow = ControlFlow::Poll;
+        let ctx = &mut ctx;
+        ggez::event::process_event(ctx, &mut event);
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::Resized(size) => {
+                    app.resize_event(ctx, size.width as f32, size.height as f32)
+                }
+                WindowEvent::CloseRequested => {
+                    let cancelled = app.quit_event(ctx);
+                    if !cancelled {
+                        ggez::event::quit(ctx);
+                    }
+                }
+                WindowEvent::Focused(gained) => app.focus_event(ctx, gained),
+                WindowEvent::ReceivedCharacter(ch) => app.text_input_event(ctx, ch),
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state,
+                            virtual_keycode: Some(keycode),
+                            ..
+                        },
+                    ..
+                } => {
+                    let keymods = keyboard::active_mods(ctx);
+                    match state {
+                        ElementState::Pressed => {
+                            let repeat = keyboard::is_key_repeated(ctx);
+                            app.key_down_event(ctx, keycode, keymods, repeat);
+                        }
+                        ElementState::Released => app.key_up_event(ctx, keycode, keymods),
+                    }
+                }
+                WindowEvent::MouseWheel { delta, .. } => {
+                    let (x, y) = match delta {
+                        MouseScrollDelta::LineDelta(x, y) => (x, y),
+                        MouseScrollDelta::PixelDelta(position) => {
+                            let scale_factor = ggez::graphics::window(ctx).scale_factor();
+                            let position = position.to_logical::<f32>(scale_factor);
+                            (position.x, position.y)
+                        }
+                    };
+                    app.mouse_wheel_event(ctx, x, y);
+                }
+                WindowEvent::MouseInput { state, button, .. } => {
+                    let position = mouse::position(ctx);
+                    match state {
+                        ElementState::Pressed => {
+                            app.mouse_button_down_event(ctx, button, position.x, position.y)
+                        }
+                        ElementState::Released => {
+                            app.mouse_button_up_event(ctx, button, position.x, position.y)
+                        }
+                    }
+                }
+                WindowEvent::CursorMoved { .. } => {
+                    let position = mouse::position(ctx);
+                    let delta = mouse::delta(ctx);
+                    app.mouse_motion_event(ctx, position.x, position.y, delta.x, delta.y);
+                }
+                _ => (),
+            },
+            Event::MainEventsCleared => {
+                ctx.timer_context.tick();
+                // gamepad events are queued until they're taken, even if they aren't used
+                while let Some(event) = ctx.gamepad_context.next_event() {
+                    app.gamepad_event(ctx, event);
+                }
+                if let Err(error) = app.update(ctx).and_then(|()| app.draw(ctx)) {
+                    result = Err(error);
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+                ctx.mouse_context.reset_delta();
+            }
+            _ => (),
+        }
+    });
+    result
+}
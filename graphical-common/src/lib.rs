@@ -1,6 +1,52 @@
+use chargrid_input::{Input, MouseButton, MouseInput};
+use chargrid_render::{Buffer, Cursor};
+use std::time::Duration;
+
 pub struct FontBytes {
     pub normal: Vec<u8>,
     pub bold: Vec<u8>,
+    /// Used for italic text if present. Otherwise italic text is drawn with the normal font.
+    pub italic: Option<Vec<u8>>,
+    /// Used for bold italic text if present. Otherwise bold italic text is drawn with the bold
+    /// font.
+    pub bold_italic: Option<Vec<u8>>,
+}
+
+/// Blinking text is alternately shown and hidden for this long
+pub const BLINK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Returns true iff blinking text should be hidden at the given time since the app started
+pub fn blink_hidden(since_start: Duration) -> bool {
+    (since_start.as_millis() / BLINK_INTERVAL.as_millis()) % 2 == 1
+}
+
+/// Returns the cursor to draw over `buffer` at the given time since the app started, if any.
+/// Blinking cursors are hidden at the same times as blinking text. Frontends draw block cursors
+/// by swapping the cell's foreground and background colours, and bar and underline cursors as a
+/// strip of the cell in its foreground colour.
+pub fn visible_cursor(buffer: &Buffer, since_start: Duration) -> Option<Cursor> {
+    buffer
+        .cursor()
+        .filter(|cursor| !(cursor.blink && blink_hidden(since_start)))
+}
+
+/// Returns the input reporting a click on a hyperlink if `input` is a left click on a cell of
+/// `buffer` which links somewhere. Frontends pass the app this input after the click itself.
+pub fn hyperlink_click(buffer: &Buffer, input: &Input) -> Option<Input> {
+    match *input {
+        Input::Mouse(MouseInput::MousePress {
+            button: MouseButton::Left,
+            coord,
+        }) => buffer
+            .get(coord)
+            .and_then(|cell| cell.hyperlink)
+            .and_then(|hyperlink| buffer.hyperlink_target(hyperlink))
+            .map(|target| Input::HyperlinkClick {
+                target: target.to_string(),
+                coord,
+            }),
+        _ => None,
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
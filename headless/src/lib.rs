@@ -0,0 +1,183 @@
+pub use chargrid_app;
+use chargrid_app::{App, ControlFlow};
+pub use chargrid_input;
+use chargrid_input::Input;
+pub use chargrid_render;
+use chargrid_render::{Buffer, ViewContext};
+pub use chargrid_render::{Coord, Rgb24, Size};
+pub use std::time::Duration;
+
+/// A single step of a script to run an app against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// Pass an input to the app's `on_input`.
+    Input(Input),
+    /// Render a frame by calling the app's `on_frame` with the given duration.
+    Frame(Duration),
+}
+
+/// The result of running an app against a script.
+#[derive(Debug)]
+pub struct Capture {
+    /// A copy of the buffer after each frame, in the order they were rendered.
+    pub frames: Vec<Buffer>,
+    /// True iff the app requested to exit before the end of the script.
+    pub exited: bool,
+    /// The error the app exited with, if it exited with `ControlFlow::ExitWithError`.
+    pub error: Option<chargrid_app::Error>,
+}
+
+impl Capture {
+    fn exited(frames: Vec<Buffer>, control_flow: ControlFlow) -> Self {
+        Self {
+            frames,
+            exited: true,
+            error: control_flow.into_result().err(),
+        }
+    }
+
+    pub fn last_frame(&self) -> Option<&Buffer> {
+        self.frames.last()
+    }
+}
+
+/// Returns the characters in each row of a buffer.
+pub fn string_rows(buffer: &Buffer) -> Vec<String> {
+    buffer
+        .rows()
+        .map(|row| row.iter().map(|cell| cell.character.as_str()).collect())
+        .collect()
+}
+
+/// A context for running apps without a terminal or window.
+pub struct Context {
+    buffer: Buffer,
+}
+
+impl Context {
+    pub fn new(size: Size) -> Self {
+        Self {
+            buffer: Buffer::new(size),
+        }
+    }
+
+    pub fn size(&self) -> Size {
+        self.buffer.size()
+    }
+
+    /// Run an app until the end of the script, or until it exits. The frame in which the app
+    /// exits is not captured, matching the behaviour of the other frontends, which don't draw it.
+    pub fn run_app<A, S>(mut self, mut app: A, script: S) -> Capture
+    where
+        A: App,
+        S: IntoIterator<Item = Step>,
+    {
+        let mut frames = Vec::new();
+        for step in script {
+            match step {
+                Step::Input(input) => {
+                    if let Some(control_flow) = app.on_input(input) {
+                        return Capture::exited(frames, control_flow);
+                    }
+                }
+                Step::Frame(since_last_frame) => {
+                    self.buffer.clear();
+                    let view_context = ViewContext::default_with_size(self.buffer.size());
+                    if let Some(control_flow) =
+                        app.on_frame(since_last_frame, view_context, &mut self.buffer)
+                    {
+                        return Capture::exited(frames, control_flow);
+                    }
+                    frames.push(self.buffer.clone());
+                }
+            }
+        }
+        Capture {
+            frames,
+            exited: false,
+            error: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chargrid_app::{ColModify, ControlFlow, Frame};
+    use chargrid_input::{keys, KeyboardInput};
+    use chargrid_render::ViewCell;
+
+    struct Typewriter {
+        text: String,
+    }
+
+    impl App for Typewriter {
+        fn on_input(&mut self, input: Input) -> Option<ControlFlow> {
+            match input {
+                Input::Keyboard(keys::ESCAPE) => return Some(ControlFlow::Exit),
+                Input::Keyboard(keys::ETX) => {
+                    return Some(ControlFlow::exit_with_error("interrupted"))
+                }
+                Input::Keyboard(KeyboardInput::Char(ch)) => self.text.push(ch),
+                _ => (),
+            }
+            None
+        }
+        fn on_frame<F, C>(
+            &mut self,
+            _since_last_frame: Duration,
+            view_context: ViewContext<C>,
+            frame: &mut F,
+        ) -> Option<ControlFlow>
+        where
+            F: Frame,
+            C: ColModify,
+        {
+            for (i, ch) in self.text.chars().enumerate() {
+                let view_cell = ViewCell::new().with_character(ch);
+                frame.set_cell_relative(Coord::new(i as i32, 0), 0, view_cell, view_context);
+            }
+            None
+        }
+    }
+
+    #[test]
+    fn scripted_inputs_and_frames() {
+        let frame_duration = Duration::from_millis(16);
+        let script = vec![
+            Step::Frame(frame_duration),
+            Step::Input(Input::Keyboard(KeyboardInput::Char('h'))),
+            Step::Input(Input::Keyboard(KeyboardInput::Char('i'))),
+            Step::Frame(frame_duration),
+            Step::Input(Input::Keyboard(keys::ESCAPE)),
+            Step::Frame(frame_duration),
+        ];
+        let app = Typewriter {
+            text: String::new(),
+        };
+        let capture = Context::new(Size::new(4, 1)).run_app(app, script);
+        assert!(capture.exited);
+        assert!(capture.error.is_none());
+        assert_eq!(capture.frames.len(), 2);
+        assert_eq!(string_rows(&capture.frames[0]), &["    "]);
+        assert_eq!(string_rows(capture.last_frame().unwrap()), &["hi  "]);
+    }
+
+    #[test]
+    fn exit_with_error() {
+        let script = vec![
+            Step::Input(Input::Keyboard(keys::ETX)),
+            Step::Frame(Duration::from_millis(16)),
+        ];
+        let app = Typewriter {
+            text: String::new(),
+        };
+        let capture = Context::new(Size::new(4, 1)).run_app(app, script);
+        assert!(capture.exited);
+        assert!(capture.frames.is_empty());
+        assert_eq!(
+            capture.error.map(|error| error.to_string()),
+            Some("app exited with an error: interrupted".to_string())
+        );
+    }
+}
@@ -1,4 +1,4 @@
-pub use coord_2d::Coord;
+pub use coord_2d::{Coord, Size};
 #[cfg(feature = "serialize")]
 use serde::{Deserialize, Serialize};
 
@@ -78,6 +78,24 @@ impl KeyboardInput {
             _ => None,
         }
     }
+
+    fn to_serialized_string(self) -> String {
+        use key_names::*;
+        use KeyboardInput::*;
+        match self {
+            Char(c) => c.to_string(),
+            Function(n) => format!("f{}", n),
+            Up => UP.to_string(),
+            Down => DOWN.to_string(),
+            Left => LEFT.to_string(),
+            Right => RIGHT.to_string(),
+            Home => HOME.to_string(),
+            End => END.to_string(),
+            PageUp => PAGE_UP.to_string(),
+            PageDown => PAGE_DOWN.to_string(),
+            Delete => DELETE.to_string(),
+        }
+    }
 }
 
 #[cfg(feature = "serialize")]
@@ -86,20 +104,9 @@ impl serde::Serialize for KeyboardInput {
     where
         S: serde::Serializer,
     {
-        use key_names::*;
-        use KeyboardInput::*;
         match self {
-            Char(c) => serializer.serialize_char(*c),
-            Function(n) => serializer.serialize_str(&format!("f{}", n)),
-            Up => serializer.serialize_str(UP),
-            Down => serializer.serialize_str(DOWN),
-            Left => serializer.serialize_str(LEFT),
-            Right => serializer.serialize_str(RIGHT),
-            Home => serializer.serialize_str(HOME),
-            End => serializer.serialize_str(END),
-            PageUp => serializer.serialize_str(PAGE_UP),
-            PageDown => serializer.serialize_str(PAGE_DOWN),
-            Delete => serializer.serialize_str(DELETE),
+            KeyboardInput::Char(c) => serializer.serialize_char(*c),
+            _ => serializer.serialize_str(&self.to_serialized_string()),
         }
     }
 }
@@ -130,6 +137,180 @@ impl<'de> serde::Deserialize<'de> for KeyboardInput {
     }
 }
 
+/// The modifier keys held while a key was pressed
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct KeyModifiers {
+    bits: u8,
+}
+
+impl KeyModifiers {
+    pub const NONE: Self = Self { bits: 0 };
+    pub const SHIFT: Self = Self { bits: 1 << 0 };
+    pub const ALT: Self = Self { bits: 1 << 1 };
+    pub const CTRL: Self = Self { bits: 1 << 2 };
+    pub const SUPER: Self = Self { bits: 1 << 3 };
+    const ALL: Self = Self { bits: 0xF };
+
+    /// Bits which don't correspond to a modifier are ignored. The bits match those used to encode
+    /// modifiers in xterm's escape sequences (after subtracting 1).
+    pub const fn from_bits(bits: u8) -> Self {
+        Self {
+            bits: bits & Self::ALL.bits,
+        }
+    }
+
+    pub const fn bits(self) -> u8 {
+        self.bits
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.bits == 0
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.bits & other.bits == other.bits
+    }
+
+    pub const fn union(self, other: Self) -> Self {
+        Self {
+            bits: self.bits | other.bits,
+        }
+    }
+
+    pub const fn difference(self, other: Self) -> Self {
+        Self {
+            bits: self.bits & !other.bits,
+        }
+    }
+
+    pub const fn shift(self) -> bool {
+        self.contains(Self::SHIFT)
+    }
+
+    pub const fn alt(self) -> bool {
+        self.contains(Self::ALT)
+    }
+
+    pub const fn ctrl(self) -> bool {
+        self.contains(Self::CTRL)
+    }
+
+    pub const fn super_(self) -> bool {
+        self.contains(Self::SUPER)
+    }
+}
+
+impl std::ops::BitOr for KeyModifiers {
+    type Output = Self;
+    fn bitor(self, other: Self) -> Self {
+        self.union(other)
+    }
+}
+
+impl std::ops::BitOrAssign for KeyModifiers {
+    fn bitor_assign(&mut self, other: Self) {
+        *self = self.union(other);
+    }
+}
+
+/// A key pressed while holding modifier keys
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ModifiedKeyboardInput {
+    pub key: KeyboardInput,
+    pub modifiers: KeyModifiers,
+}
+
+impl ModifiedKeyboardInput {
+    pub const fn new(key: KeyboardInput, modifiers: KeyModifiers) -> Self {
+        Self { key, modifiers }
+    }
+
+    /// Like `new`, but shift is ignored for printable characters as it's reflected in the
+    /// character itself (e.g. shift+a is 'A')
+    pub fn normalised(key: KeyboardInput, modifiers: KeyModifiers) -> Self {
+        let modifiers = match key {
+            KeyboardInput::Char(c) if !c.is_control() => modifiers.difference(KeyModifiers::SHIFT),
+            _ => modifiers,
+        };
+        Self::new(key, modifiers)
+    }
+}
+
+#[cfg(feature = "serialize")]
+mod modifier_names {
+    use super::KeyModifiers;
+
+    /// Modifier names in the order they appear in serialized key descriptions
+    pub const MODIFIERS: &[(&str, KeyModifiers)] = &[
+        ("ctrl-", KeyModifiers::CTRL),
+        ("alt-", KeyModifiers::ALT),
+        ("shift-", KeyModifiers::SHIFT),
+        ("super-", KeyModifiers::SUPER),
+    ];
+}
+
+#[cfg(feature = "serialize")]
+impl ModifiedKeyboardInput {
+    fn try_from_str(mut s: &str) -> Option<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        'strip_modifier: loop {
+            for &(name, modifier) in modifier_names::MODIFIERS {
+                // a single remaining character is the key itself (e.g. the "-" in "ctrl--")
+                if s.len() > name.len() && s.starts_with(name) {
+                    s = &s[name.len()..];
+                    modifiers |= modifier;
+                    continue 'strip_modifier;
+                }
+            }
+            break;
+        }
+        KeyboardInput::try_from_str(s).map(|key| Self::new(key, modifiers))
+    }
+}
+
+#[cfg(feature = "serialize")]
+impl serde::Serialize for ModifiedKeyboardInput {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut s = String::new();
+        for &(name, modifier) in modifier_names::MODIFIERS {
+            if self.modifiers.contains(modifier) {
+                s.push_str(name);
+            }
+        }
+        s.push_str(&self.key.to_serialized_string());
+        serializer.serialize_str(&s)
+    }
+}
+
+#[cfg(feature = "serialize")]
+impl<'de> serde::Deserialize<'de> for ModifiedKeyboardInput {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor;
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = ModifiedKeyboardInput;
+
+            fn expecting(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                formatter.write_str("a keyboard input description with optional modifiers")
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                ModifiedKeyboardInput::try_from_str(s)
+                    .ok_or_else(|| E::custom(format!("couldn't parse {}", s)))
+            }
+        }
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum MouseInput {
@@ -142,7 +323,8 @@ pub enum MouseInput {
         coord: Coord,
     },
     MouseRelease {
-        // some platforms (e.g. ansi terminal) don't report the button that was released
+        // some platforms (e.g. ansi terminals without SGR mouse support) don't report the button
+        // that was released
         button: Result<MouseButton, NotSupported>,
         coord: Coord,
     },
@@ -187,19 +369,62 @@ pub use gamepad::{GamepadButton, GamepadInput};
 
 /// An input event
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum Input {
+    /// A key pressed without any modifiers, other than shift when it's reflected in the character
     Keyboard(KeyboardInput),
+    /// A key pressed while holding modifier keys
+    ModifiedKeyboard(ModifiedKeyboardInput),
+    /// A key repeating because it's being held down. Only reported by some frontends. Frontends
+    /// which don't report repeats report repeating keys as repeated presses instead.
+    KeyRepeat(ModifiedKeyboardInput),
+    /// A key being released. Only reported by some frontends.
+    KeyRelease(ModifiedKeyboardInput),
     Mouse(MouseInput),
+    /// Text pasted into the application all at once, rather than as individual key presses
+    Paste(String),
+    /// The number of cells in the terminal changed to the given size. Graphical frontends scale
+    /// the grid to fit the window, so the number of cells never changes and this isn't reported.
+    Resize(Size),
+    /// The terminal or window gained focus
+    FocusGained,
+    /// The terminal or window lost focus
+    FocusLost,
+    /// A cell which links to `target` was clicked. Only reported by graphical frontends, after
+    /// the mouse press itself. Terminals open links themselves.
+    HyperlinkClick {
+        target: String,
+        coord: Coord,
+    },
     #[cfg(feature = "gamepad")]
     Gamepad(GamepadInput),
 }
 
 impl Input {
+    /// Returns a `Keyboard` input if no modifiers are held, and a `ModifiedKeyboard` input
+    /// otherwise. Shift is ignored for printable characters as it's reflected in the character
+    /// itself (e.g. shift+a is 'A').
+    pub fn keyboard_with_modifiers(key: KeyboardInput, modifiers: KeyModifiers) -> Self {
+        let input = ModifiedKeyboardInput::normalised(key, modifiers);
+        if input.modifiers.is_empty() {
+            Input::Keyboard(key)
+        } else {
+            Input::ModifiedKeyboard(input)
+        }
+    }
+
     pub fn is_keyboard(&self) -> bool {
         match self {
             Input::Keyboard(_) => true,
+            Input::ModifiedKeyboard(_) => true,
+            Input::KeyRepeat(_) => true,
+            Input::KeyRelease(_) => true,
             Input::Mouse(_) => false,
+            Input::Paste(_) => false,
+            Input::Resize(_) => false,
+            Input::FocusGained => false,
+            Input::FocusLost => false,
+            Input::HyperlinkClick { .. } => false,
             #[cfg(feature = "gamepad")]
             Input::Gamepad(_) => false,
         }
@@ -240,6 +465,47 @@ mod serde_test {
         t(KeyboardInput::Char('☃'));
     }
 
+    #[test]
+    fn reversable_with_modifiers() {
+        use super::{KeyModifiers, KeyboardInput, ModifiedKeyboardInput};
+        fn t(s: &str, input: ModifiedKeyboardInput) {
+            assert_eq!(serde_json::to_string(&input).unwrap(), format!("\"{}\"", s));
+            assert_eq!(
+                serde_json::from_str::<ModifiedKeyboardInput>(&format!("\"{}\"", s)).unwrap(),
+                input
+            );
+        }
+        t(
+            "ctrl-shift-left",
+            ModifiedKeyboardInput::new(
+                KeyboardInput::Left,
+                KeyModifiers::CTRL | KeyModifiers::SHIFT,
+            ),
+        );
+        t(
+            "alt-super-f4",
+            ModifiedKeyboardInput::new(
+                KeyboardInput::Function(4),
+                KeyModifiers::ALT | KeyModifiers::SUPER,
+            ),
+        );
+        t(
+            "ctrl--",
+            ModifiedKeyboardInput::new(KeyboardInput::Char('-'), KeyModifiers::CTRL),
+        );
+        t(
+            "ctrl-alt-a",
+            ModifiedKeyboardInput::new(
+                KeyboardInput::Char('a'),
+                KeyModifiers::CTRL | KeyModifiers::ALT,
+            ),
+        );
+        t(
+            "page-down",
+            ModifiedKeyboardInput::new(KeyboardInput::PageDown, KeyModifiers::NONE),
+        );
+    }
+
     #[test]
     fn example() {
         use super::KeyboardInput;
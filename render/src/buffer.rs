@@ -1,21 +1,66 @@
-use super::{Blend, Coord, Frame, Rgb24, Size, ViewCell};
+use super::{
+    Blend, Coord, Cursor, Frame, Grapheme, Hyperlink, HyperlinkTargets, ImagePlacement, Rgb24,
+    Size, UnderlineKind, ViewCell,
+};
 
 #[derive(Debug, Clone, Copy)]
 pub struct BufferCell {
-    pub character: char,
+    /// The grapheme in this cell. The cell to the right of a double-width grapheme contains a
+    /// continuation marker (see `Grapheme::is_continuation`) which frontends don't render.
+    pub character: Grapheme,
     pub bold: bool,
     pub underline: bool,
+    pub underline_kind: UnderlineKind,
+    /// If `None`, the underline is drawn in the foreground colour
+    pub underline_colour: Option<Rgb24>,
+    pub italic: bool,
+    pub strikethrough: bool,
+    pub dim: bool,
+    pub reverse: bool,
+    pub blink: bool,
     pub foreground_colour: Rgb24,
     pub background_colour: Rgb24,
+    /// If true, terminals draw the character in their default foreground colour rather than
+    /// `foreground_colour`, which other frontends draw
+    pub default_foreground: bool,
+    /// If true, terminals leave the background in their default background colour rather than
+    /// `background_colour`, which other frontends draw
+    pub default_background: bool,
+    /// Look up the link's target with `Buffer::hyperlink_target`
+    pub hyperlink: Option<Hyperlink>,
     foreground_depth: i8,
     background_depth: i8,
 }
 
 impl BufferCell {
-    fn set_character(&mut self, character: char, depth: i8) {
+    /// The foreground and background colours to display after applying the `reverse` and `dim`
+    /// attributes, for frontends which can't render these attributes natively.
+    pub fn display_colours(&self) -> (Rgb24, Rgb24) {
+        let (foreground, background) = if self.reverse {
+            (self.background_colour, self.foreground_colour)
+        } else {
+            (self.foreground_colour, self.background_colour)
+        };
+        if self.dim {
+            (foreground.linear_interpolate(background, 128), background)
+        } else {
+            (foreground, background)
+        }
+    }
+    fn set_character(&mut self, character: Grapheme, depth: i8) -> Option<Grapheme> {
         if depth >= self.foreground_depth {
+            let previous = self.character;
             self.character = character;
             self.foreground_depth = depth;
+            Some(previous)
+        } else {
+            None
+        }
+    }
+    fn set_hyperlink(&mut self, hyperlink: Option<Hyperlink>, depth: i8) {
+        if depth >= self.foreground_depth {
+            self.hyperlink = hyperlink;
+            self.foreground_depth = depth;
         }
     }
     fn set_bold(&mut self, bold: bool, depth: i8) {
@@ -30,31 +75,114 @@ impl BufferCell {
             self.foreground_depth = depth;
         }
     }
+    fn set_underline_kind(&mut self, underline_kind: UnderlineKind, depth: i8) {
+        if depth >= self.foreground_depth {
+            self.underline_kind = underline_kind;
+            self.foreground_depth = depth;
+        }
+    }
+    fn set_underline_colour(&mut self, underline_colour: Rgb24, depth: i8) {
+        if depth >= self.foreground_depth {
+            self.underline_colour = Some(underline_colour);
+            self.foreground_depth = depth;
+        }
+    }
+    fn set_italic(&mut self, italic: bool, depth: i8) {
+        if depth >= self.foreground_depth {
+            self.italic = italic;
+            self.foreground_depth = depth;
+        }
+    }
+    fn set_strikethrough(&mut self, strikethrough: bool, depth: i8) {
+        if depth >= self.foreground_depth {
+            self.strikethrough = strikethrough;
+            self.foreground_depth = depth;
+        }
+    }
+    fn set_dim(&mut self, dim: bool, depth: i8) {
+        if depth >= self.foreground_depth {
+            self.dim = dim;
+            self.foreground_depth = depth;
+        }
+    }
+    fn set_reverse(&mut self, reverse: bool, depth: i8) {
+        if depth >= self.foreground_depth {
+            self.reverse = reverse;
+            self.foreground_depth = depth;
+        }
+    }
+    fn set_blink(&mut self, blink: bool, depth: i8) {
+        if depth >= self.foreground_depth {
+            self.blink = blink;
+            self.foreground_depth = depth;
+        }
+    }
     fn set_foreground_colour(&mut self, colour: Rgb24, depth: i8) {
         if depth >= self.foreground_depth {
             self.foreground_colour = colour;
+            self.default_foreground = false;
             self.foreground_depth = depth;
         }
     }
     fn set_background_colour(&mut self, colour: Rgb24, depth: i8) {
         if depth >= self.background_depth {
             self.background_colour = colour;
+            self.default_background = false;
+            self.background_depth = depth;
+        }
+    }
+    fn set_default_foreground(&mut self, default_foreground: bool, depth: i8) {
+        if depth >= self.foreground_depth {
+            if default_foreground {
+                self.foreground_colour = DEFAULT_FOREGROUND_FALLBACK;
+            }
+            self.default_foreground = default_foreground;
+            self.foreground_depth = depth;
+        }
+    }
+    fn set_default_background(&mut self, default_background: bool, depth: i8) {
+        if depth >= self.background_depth {
+            if default_background {
+                self.background_colour = DEFAULT_BACKGROUND_FALLBACK;
+            }
+            self.default_background = default_background;
             self.background_depth = depth;
         }
     }
 }
 
 const BLACK: Rgb24 = Rgb24::new_grey(0);
+// the colours drawn in place of the default colours by frontends which have no default colours
+const DEFAULT_FOREGROUND_FALLBACK: Rgb24 = Rgb24::new_grey(255);
+const DEFAULT_BACKGROUND_FALLBACK: Rgb24 = BLACK;
 const BLANK_CELL: BufferCell = BufferCell {
-    character: ' ',
+    character: Grapheme::from_char(' '),
     bold: false,
     underline: false,
+    underline_kind: UnderlineKind::Single,
+    underline_colour: None,
+    italic: false,
+    strikethrough: false,
+    dim: false,
+    reverse: false,
+    blink: false,
     foreground_colour: BLACK,
     background_colour: BLACK,
+    default_foreground: false,
+    default_background: false,
+    hyperlink: None,
     foreground_depth: 0,
     background_depth: 0,
 };
 
+const BLANK_CELL_DEFAULT_COLOURS: BufferCell = BufferCell {
+    foreground_colour: DEFAULT_FOREGROUND_FALLBACK,
+    background_colour: DEFAULT_BACKGROUND_FALLBACK,
+    default_foreground: true,
+    default_background: true,
+    ..BLANK_CELL
+};
+
 pub type BufferIter<'a> = grid_2d::GridIter<'a, BufferCell>;
 pub type BufferEnumerate<'a> = grid_2d::GridEnumerate<'a, BufferCell>;
 pub type BufferRows<'a> = grid_2d::GridRows<'a, BufferCell>;
@@ -62,26 +190,74 @@ pub type BufferRows<'a> = grid_2d::GridRows<'a, BufferCell>;
 #[derive(Debug, Clone)]
 pub struct Buffer {
     grid: grid_2d::Grid<BufferCell>,
+    blank_cell: BufferCell,
+    cursor: Option<Cursor>,
+    hyperlinks: HyperlinkTargets,
+    images: Vec<ImagePlacement>,
 }
 
 impl Buffer {
     pub fn new(size: Size) -> Self {
         let grid = grid_2d::Grid::new_copy(size, BLANK_CELL);
-        Self { grid }
+        Self {
+            grid,
+            blank_cell: BLANK_CELL,
+            cursor: None,
+            hyperlinks: HyperlinkTargets::new(),
+            images: Vec::new(),
+        }
     }
 
     pub fn size(&self) -> Size {
         self.grid.size()
     }
 
+    /// Blank cells (those not drawn to since the buffer was cleared) are black by default. If
+    /// `default_colours` is true, they use the terminal's default colours instead, leaving the
+    /// user's terminal theme visible wherever the app doesn't draw. Takes effect when the buffer is
+    /// next cleared.
+    pub fn set_blank_default_colours(&mut self, default_colours: bool) {
+        self.blank_cell = if default_colours {
+            BLANK_CELL_DEFAULT_COLOURS
+        } else {
+            BLANK_CELL
+        };
+    }
+
     pub fn resize(&mut self, size: Size) {
-        self.grid = grid_2d::Grid::new_copy(size, BLANK_CELL);
+        self.grid = grid_2d::Grid::new_copy(size, self.blank_cell);
+        self.cursor = None;
+        self.hyperlinks.clear();
+        self.images.clear();
     }
 
+    /// Blank every cell, hide the cursor, remove all images and forget the targets of hyperlinks
     pub fn clear(&mut self) {
         for cell in self.grid.iter_mut() {
-            *cell = BLANK_CELL;
+            *cell = self.blank_cell;
         }
+        self.cursor = None;
+        self.hyperlinks.clear();
+        self.images.clear();
+    }
+
+    /// The cursor requested since the buffer was last cleared, if any
+    pub fn cursor(&self) -> Option<Cursor> {
+        self.cursor
+    }
+
+    /// The images drawn since the buffer was last cleared, in the order they were drawn
+    pub fn images(&self) -> &[ImagePlacement] {
+        &self.images
+    }
+
+    pub fn get(&self, coord: Coord) -> Option<&BufferCell> {
+        self.grid.get(coord)
+    }
+
+    /// The target of a hyperlink registered since the buffer was last cleared
+    pub fn hyperlink_target(&self, hyperlink: Hyperlink) -> Option<&str> {
+        self.hyperlinks.get(hyperlink)
     }
 
     pub fn enumerate(&self) -> BufferEnumerate {
@@ -95,14 +271,76 @@ impl Buffer {
     pub fn rows(&self) -> BufferRows {
         self.grid.rows()
     }
+
+    /// Replace the background colour of each cell with the result of calling `f` with the cell's
+    /// coordinate and current background colour
+    pub fn map_background_colours<F>(&mut self, mut f: F)
+    where
+        F: FnMut(Coord, Rgb24) -> Rgb24,
+    {
+        for (coord, cell) in self.grid.enumerate_mut() {
+            cell.background_colour = f(coord, cell.background_colour);
+        }
+    }
+
+    fn replace_with_space(&mut self, coord: Coord) {
+        if let Some(cell) = self.grid.get_mut(coord) {
+            cell.character = BLANK_CELL.character;
+        }
+    }
+
+    /// Keep double-width graphemes and their continuations paired up after the character at
+    /// `coord` changes from `previous` to `current`. Halves of double-width graphemes which are
+    /// left without their partner are replaced with spaces.
+    fn fix_double_width(&mut self, coord: Coord, depth: i8, previous: Grapheme, current: Grapheme) {
+        let left = coord - Coord::new(1, 0);
+        let right = coord + Coord::new(1, 0);
+        if previous.is_continuation() {
+            self.replace_with_space(left);
+        }
+        if previous.width() == 2 {
+            if let Some(true) = self.grid.get(right).map(|c| c.character.is_continuation()) {
+                self.replace_with_space(right);
+            }
+        }
+        if current.width() == 2 {
+            let cell = *self.grid.get_checked(coord);
+            match self.grid.get_mut(right) {
+                Some(right_cell) if right_cell.foreground_depth <= depth => {
+                    let right_previous = right_cell.character;
+                    *right_cell = BufferCell {
+                        character: Grapheme::CONTINUATION,
+                        background_depth: right_cell.background_depth.max(depth),
+                        ..cell
+                    };
+                    if right_previous.width() == 2 {
+                        self.replace_with_space(right + Coord::new(1, 0));
+                    }
+                }
+                // there's no room for the right half of the grapheme
+                _ => self.replace_with_space(coord),
+            }
+        }
+    }
 }
 
 impl Frame for Buffer {
     fn set_cell_absolute(&mut self, coord: Coord, depth: i8, view_cell: ViewCell) {
+        let mut changed_character = None;
+        // links to targets which weren't registered with this buffer can't be followed
+        let hyperlinks = &self.hyperlinks;
+        let hyperlink = view_cell
+            .hyperlink()
+            .filter(|&hyperlink| hyperlinks.contains(hyperlink));
         if let Some(cell) = self.grid.get_mut(coord) {
             if cell.foreground_depth <= depth || cell.background_depth <= depth {
                 if let Some(character) = view_cell.character() {
-                    cell.set_character(character, depth);
+                    cell.set_hyperlink(hyperlink, depth);
+                    changed_character = cell
+                        .set_character(character, depth)
+                        .map(|previous| (previous, character));
+                } else if let Some(hyperlink) = hyperlink {
+                    cell.set_hyperlink(Some(hyperlink), depth);
                 }
                 if let Some(bold) = view_cell.bold() {
                     cell.set_bold(bold, depth);
@@ -110,12 +348,44 @@ impl Frame for Buffer {
                 if let Some(underline) = view_cell.underline() {
                     cell.set_underline(underline, depth);
                 }
+                if let Some(underline_kind) = view_cell.underline_kind() {
+                    cell.set_underline_kind(underline_kind, depth);
+                }
+                if let Some(underline_colour) = view_cell.underline_colour() {
+                    cell.set_underline_colour(underline_colour, depth);
+                }
+                if let Some(italic) = view_cell.italic() {
+                    cell.set_italic(italic, depth);
+                }
+                if let Some(strikethrough) = view_cell.strikethrough() {
+                    cell.set_strikethrough(strikethrough, depth);
+                }
+                if let Some(dim) = view_cell.dim() {
+                    cell.set_dim(dim, depth);
+                }
+                if let Some(reverse) = view_cell.reverse() {
+                    cell.set_reverse(reverse, depth);
+                }
+                if let Some(blink) = view_cell.blink() {
+                    cell.set_blink(blink, depth);
+                }
                 if let Some(foreground) = view_cell.foreground() {
                     cell.set_foreground_colour(foreground, depth);
                 }
                 if let Some(background) = view_cell.background() {
                     cell.set_background_colour(background, depth);
                 }
+                if let Some(default_foreground) = view_cell.default_foreground() {
+                    cell.set_default_foreground(default_foreground, depth);
+                }
+                if let Some(default_background) = view_cell.default_background() {
+                    cell.set_default_background(default_background, depth);
+                }
+            }
+        }
+        if let Some((previous, current)) = changed_character {
+            if previous.width() != 1 || current.width() != 1 {
+                self.fix_double_width(coord, depth, previous, current);
             }
         }
     }
@@ -133,8 +403,128 @@ impl Frame for Buffer {
                 let blended_background_colour =
                     blend.blend(current_background_colour, rgb24, alpha);
                 cell.background_colour = blended_background_colour;
+                // blending with the default background blends with the colour drawn in its place
+                cell.default_background = false;
                 cell.background_depth = depth;
             }
         }
     }
+    fn set_cursor_absolute(&mut self, cursor: Cursor) {
+        if cursor.coord.is_valid(self.size()) {
+            self.cursor = Some(cursor);
+        }
+    }
+    fn hyperlink(&mut self, target: &str) -> Hyperlink {
+        self.hyperlinks.insert(target)
+    }
+    fn draw_image_absolute(&mut self, placement: ImagePlacement) {
+        if placement.fits_within(self.size()) {
+            self.images.push(placement);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{CursorShape, Image, ImageView, View, ViewContext};
+
+    fn row_string(buffer: &Buffer) -> String {
+        buffer
+            .iter()
+            .map(|cell| {
+                if cell.character.is_continuation() {
+                    "_".to_string()
+                } else {
+                    cell.character.to_string()
+                }
+            })
+            .collect()
+    }
+
+    fn set(buffer: &mut Buffer, x: i32, depth: i8, s: &str) {
+        let view_cell = ViewCell::new().with_grapheme(Grapheme::new(s).unwrap());
+        buffer.set_cell_absolute(Coord::new(x, 0), depth, view_cell);
+    }
+
+    #[test]
+    fn double_width() {
+        let mut buffer = Buffer::new(Size::new(5, 1));
+        set(&mut buffer, 0, 0, "日");
+        set(&mut buffer, 2, 0, "e\u{301}");
+        assert_eq!(row_string(&buffer), "日_e\u{301}  ");
+        // overwriting the continuation breaks the double-width grapheme
+        set(&mut buffer, 1, 0, "a");
+        assert_eq!(row_string(&buffer), " ae\u{301}  ");
+        // overlapping double-width graphemes
+        set(&mut buffer, 2, 0, "本");
+        set(&mut buffer, 1, 0, "日");
+        assert_eq!(row_string(&buffer), " 日_  ");
+        // no room in the last column
+        set(&mut buffer, 4, 0, "日");
+        assert_eq!(row_string(&buffer), " 日_  ");
+        // the right half is occluded by a deeper cell
+        set(&mut buffer, 4, 1, "x");
+        set(&mut buffer, 3, 0, "日");
+        assert_eq!(row_string(&buffer), " 日_ x");
+    }
+
+    #[test]
+    fn display_colours() {
+        let mut buffer = Buffer::new(Size::new(1, 1));
+        let foreground = Rgb24::new(255, 255, 255);
+        let background = Rgb24::new(0, 0, 255);
+        let view_cell = ViewCell::new()
+            .with_foreground(foreground)
+            .with_background(background)
+            .with_reverse(true);
+        buffer.set_cell_absolute(Coord::new(0, 0), 0, view_cell);
+        let cell = *buffer.get(Coord::new(0, 0)).unwrap();
+        assert_eq!(cell.display_colours(), (background, foreground));
+        buffer.set_cell_absolute(Coord::new(0, 0), 0, view_cell.with_dim(true));
+        let cell = *buffer.get(Coord::new(0, 0)).unwrap();
+        assert_eq!(
+            cell.display_colours(),
+            (background.linear_interpolate(foreground, 128), foreground)
+        );
+    }
+
+    #[test]
+    fn cursor() {
+        let mut buffer = Buffer::new(Size::new(4, 3));
+        let context = ViewContext::default_with_size(buffer.size()).add_offset(Coord::new(1, 1));
+        assert_eq!(buffer.cursor(), None);
+        let cursor = Cursor::new(Coord::new(1, 0), CursorShape::Bar).with_blink(true);
+        buffer.set_cursor_relative(cursor, context);
+        assert_eq!(buffer.cursor(), Some(cursor.with_coord(Coord::new(2, 1))));
+        // outside the buffer, so the previous request is kept
+        buffer.set_cursor_relative(cursor.with_coord(Coord::new(3, 0)), context);
+        assert_eq!(buffer.cursor(), Some(cursor.with_coord(Coord::new(2, 1))));
+        buffer.clear();
+        assert_eq!(buffer.cursor(), None);
+    }
+
+    #[test]
+    fn images() {
+        let mut buffer = Buffer::new(Size::new(4, 3));
+        let context = ViewContext::default_with_size(buffer.size()).add_offset(Coord::new(1, 1));
+        let image = Image::from_fn(Size::new(8, 8), |_| [255, 0, 0, 255]);
+        let mut view = ImageView::new(Size::new(2, 2));
+        assert_eq!(
+            view.view_size(&image, context, &mut buffer),
+            Size::new(2, 2)
+        );
+        // too big to fit in the context, so it's ignored
+        buffer.draw_image_relative(&image, Coord::new(1, 1), Size::new(2, 2), context);
+        assert_eq!(
+            buffer.images(),
+            &[ImagePlacement::new(
+                image,
+                Coord::new(1, 1),
+                Size::new(2, 2)
+            )]
+        );
+        buffer.clear();
+        assert!(buffer.images().is_empty());
+    }
 }
@@ -0,0 +1,38 @@
+use super::Coord;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CursorShape {
+    Block,
+    /// A vertical line at the left edge of the cell
+    Bar,
+    Underline,
+}
+
+/// A request to show the text cursor (e.g. the caret of a text input) during a frame. The cursor
+/// is hidden during frames where it isn't requested. If it's requested more than once in a
+/// frame, the last request is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cursor {
+    pub coord: Coord,
+    pub shape: CursorShape,
+    pub blink: bool,
+}
+
+impl Cursor {
+    pub const fn new(coord: Coord, shape: CursorShape) -> Self {
+        Self {
+            coord,
+            shape,
+            blink: false,
+        }
+    }
+    pub const fn with_coord(self, coord: Coord) -> Self {
+        Self { coord, ..self }
+    }
+    pub const fn with_shape(self, shape: CursorShape) -> Self {
+        Self { shape, ..self }
+    }
+    pub const fn with_blink(self, blink: bool) -> Self {
+        Self { blink, ..self }
+    }
+}
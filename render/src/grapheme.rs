@@ -0,0 +1,163 @@
+use std::fmt;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const MAX_BYTES: usize = 31;
+
+/// A single user-perceived character (extended grapheme cluster), such as a letter followed by
+/// combining accents, or a multi-codepoint emoji. Stored inline so it can live in `Copy` cells.
+/// Clusters longer than 31 bytes are truncated to the longest prefix of whole chars that fits.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Grapheme {
+    bytes: [u8; MAX_BYTES],
+    len: u8,
+}
+
+impl Grapheme {
+    /// Placed in the cell to the right of a double-width grapheme. Renders as nothing.
+    pub(crate) const CONTINUATION: Self = Self {
+        bytes: [0; MAX_BYTES],
+        len: 0,
+    };
+
+    pub const fn from_char(ch: char) -> Self {
+        let code = ch as u32;
+        let mut bytes = [0; MAX_BYTES];
+        let len = if code < 0x80 {
+            bytes[0] = code as u8;
+            1
+        } else if code < 0x800 {
+            bytes[0] = 0xC0 | (code >> 6) as u8;
+            bytes[1] = 0x80 | (code & 0x3F) as u8;
+            2
+        } else if code < 0x10000 {
+            bytes[0] = 0xE0 | (code >> 12) as u8;
+            bytes[1] = 0x80 | ((code >> 6) & 0x3F) as u8;
+            bytes[2] = 0x80 | (code & 0x3F) as u8;
+            3
+        } else {
+            bytes[0] = 0xF0 | (code >> 18) as u8;
+            bytes[1] = 0x80 | ((code >> 12) & 0x3F) as u8;
+            bytes[2] = 0x80 | ((code >> 6) & 0x3F) as u8;
+            bytes[3] = 0x80 | (code & 0x3F) as u8;
+            4
+        };
+        Self { bytes, len }
+    }
+
+    /// Returns `None` if `s` is empty. Only the first grapheme cluster of `s` is kept.
+    pub fn new(s: &str) -> Option<Self> {
+        s.graphemes(true).next().map(Self::from_cluster)
+    }
+
+    fn from_cluster(cluster: &str) -> Self {
+        let mut bytes = [0; MAX_BYTES];
+        let mut len = 0;
+        for ch in cluster.chars() {
+            let ch_len = ch.len_utf8();
+            if len + ch_len > MAX_BYTES {
+                break;
+            }
+            ch.encode_utf8(&mut bytes[len..]);
+            len += ch_len;
+        }
+        Self {
+            bytes,
+            len: len as u8,
+        }
+    }
+
+    /// Split a string into graphemes.
+    pub fn iter_str(s: &str) -> impl Iterator<Item = Self> + '_ {
+        s.graphemes(true).map(Self::from_cluster)
+    }
+
+    pub fn as_str(&self) -> &str {
+        // bytes are only ever populated from valid utf8 on char boundaries
+        std::str::from_utf8(&self.bytes[0..self.len as usize]).unwrap()
+    }
+
+    pub fn chars(&self) -> std::str::Chars<'_> {
+        self.as_str().chars()
+    }
+
+    /// The first char of the grapheme. This is the base character for clusters made up of a
+    /// character followed by combining marks.
+    pub fn first_char(&self) -> Option<char> {
+        self.chars().next()
+    }
+
+    pub fn is_continuation(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of terminal columns occupied by the grapheme: 0 for the continuation of a
+    /// double-width grapheme, and otherwise 1 or 2.
+    pub fn width(&self) -> u32 {
+        if self.is_continuation() {
+            0
+        } else {
+            (self.as_str().width() as u32).clamp(1, 2)
+        }
+    }
+}
+
+impl From<char> for Grapheme {
+    fn from(ch: char) -> Self {
+        Self::from_char(ch)
+    }
+}
+
+impl PartialEq<char> for Grapheme {
+    fn eq(&self, other: &char) -> bool {
+        let mut chars = self.chars();
+        chars.next() == Some(*other) && chars.next().is_none()
+    }
+}
+
+impl fmt::Debug for Grapheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Grapheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serialize")]
+impl serde::Serialize for Grapheme {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serialize")]
+impl<'de> serde::Deserialize<'de> for Grapheme {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor;
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Grapheme;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a non-empty string")
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Grapheme::new(s).ok_or_else(|| E::custom("empty grapheme"))
+            }
+        }
+        deserializer.deserialize_str(Visitor)
+    }
+}
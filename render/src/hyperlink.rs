@@ -0,0 +1,81 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// A handle to the target of a hyperlink (usually a URL), returned by `Frame::hyperlink`. Handles
+/// to the same target are equal, so a style holding one can be kept between frames, but frames
+/// only know the targets registered with them since they were last cleared. A link must be
+/// registered with each frame it's drawn in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Hyperlink {
+    id: u64,
+}
+
+impl Hyperlink {
+    pub(crate) fn new(target: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        target.hash(&mut hasher);
+        Self {
+            id: hasher.finish(),
+        }
+    }
+
+    /// A number identifying the hyperlink's target, which is the same for every handle to that
+    /// target
+    pub const fn id(self) -> u64 {
+        self.id
+    }
+}
+
+/// The targets of the hyperlinks registered with a frame
+#[derive(Debug, Clone, Default)]
+pub struct HyperlinkTargets {
+    targets: HashMap<Hyperlink, String>,
+}
+
+impl HyperlinkTargets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a target, returning the handle to it
+    pub fn insert(&mut self, target: &str) -> Hyperlink {
+        let hyperlink = Hyperlink::new(target);
+        self.targets
+            .entry(hyperlink)
+            .or_insert_with(|| target.to_string());
+        hyperlink
+    }
+
+    pub fn get(&self, hyperlink: Hyperlink) -> Option<&str> {
+        self.targets.get(&hyperlink).map(String::as_str)
+    }
+
+    pub fn contains(&self, hyperlink: Hyperlink) -> bool {
+        self.targets.contains_key(&hyperlink)
+    }
+
+    pub fn clear(&mut self) {
+        self.targets.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn targets() {
+        let mut targets = HyperlinkTargets::new();
+        let a = targets.insert("https://example.com/a");
+        let b = targets.insert("https://example.com/b");
+        assert_ne!(a, b);
+        assert_eq!(a, targets.insert("https://example.com/a"));
+        assert_eq!(targets.get(a), Some("https://example.com/a"));
+        assert_eq!(targets.get(b), Some("https://example.com/b"));
+        targets.clear();
+        assert_eq!(targets.get(a), None);
+        // handles don't depend on what else has been registered
+        assert_eq!(HyperlinkTargets::new().insert("https://example.com/b"), b);
+    }
+}
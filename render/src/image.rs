@@ -0,0 +1,168 @@
+use super::{ColModify, Coord, Frame, Size, View, ViewContext};
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+// 0 is avoided as some image protocols treat it as "no id"
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// An RGBA image which can be drawn over a rectangle of cells. Cloning an image is cheap, as
+/// clones share their pixels. Each image (as opposed to each clone) has a unique id, which
+/// frontends use to avoid sending the same image to the display more than once.
+#[derive(Clone)]
+pub struct Image {
+    id: u32,
+    size: Size,
+    rgba: Arc<[u8]>,
+}
+
+impl Image {
+    /// Create an image from its pixels in row-major order, 4 bytes (red, green, blue, alpha) per
+    /// pixel. Panics if the number of bytes doesn't match the size.
+    pub fn from_rgba_bytes(size: Size, rgba: Vec<u8>) -> Self {
+        assert_eq!(
+            rgba.len(),
+            size.count() * 4,
+            "expected 4 bytes for each pixel of a {}x{} image",
+            size.width(),
+            size.height()
+        );
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            size,
+            rgba: rgba.into(),
+        }
+    }
+
+    /// Create an image by calling `f` with the coordinate of each pixel, which returns the pixel's
+    /// red, green, blue and alpha components
+    pub fn from_fn<F>(size: Size, mut f: F) -> Self
+    where
+        F: FnMut(Coord) -> [u8; 4],
+    {
+        let mut rgba = Vec::with_capacity(size.count() * 4);
+        for y in 0..size.height() {
+            for x in 0..size.width() {
+                rgba.extend_from_slice(&f(Coord::new(x as i32, y as i32)));
+            }
+        }
+        Self::from_rgba_bytes(size, rgba)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The size of the image in pixels
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// The pixels in row-major order, 4 bytes (red, green, blue, alpha) per pixel
+    pub fn rgba_bytes(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    /// The red, green, blue and alpha components of the pixel at `coord`
+    pub fn get(&self, coord: Coord) -> Option<[u8; 4]> {
+        if coord.is_valid(self.size) {
+            let index = (coord.y as usize * self.size.width() as usize + coord.x as usize) * 4;
+            let pixel = &self.rgba[index..(index + 4)];
+            Some([pixel[0], pixel[1], pixel[2], pixel[3]])
+        } else {
+            None
+        }
+    }
+}
+
+impl PartialEq for Image {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Image {}
+
+impl fmt::Debug for Image {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Image")
+            .field("id", &self.id)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+/// An image drawn during a frame, scaled to cover `size` cells with its top-left corner at
+/// `coord`. Images are drawn over the cells they cover.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImagePlacement {
+    pub image: Image,
+    pub coord: Coord,
+    pub size: Size,
+}
+
+impl ImagePlacement {
+    pub fn new(image: Image, coord: Coord, size: Size) -> Self {
+        Self { image, coord, size }
+    }
+
+    /// Returns true iff the image covers the cell at `coord`
+    pub fn covers(&self, coord: Coord) -> bool {
+        (coord - self.coord).is_valid(self.size)
+    }
+
+    /// Returns true iff the whole image lies within a grid of the given size
+    pub fn fits_within(&self, size: Size) -> bool {
+        self.coord.is_valid(size)
+            && self.size.width() > 0
+            && self.size.height() > 0
+            && (self.coord + self.size.to_coord().unwrap() - Coord::new(1, 1)).is_valid(size)
+    }
+}
+
+/// Draws an image over a rectangle of `size` cells at the top-left of its context
+#[derive(Debug, Clone, Copy)]
+pub struct ImageView {
+    pub size: Size,
+}
+
+impl ImageView {
+    pub const fn new(size: Size) -> Self {
+        Self { size }
+    }
+}
+
+impl<'a> View<&'a Image> for ImageView {
+    fn view<F: Frame, C: ColModify>(
+        &mut self,
+        image: &'a Image,
+        context: ViewContext<C>,
+        frame: &mut F,
+    ) {
+        frame.draw_image_relative(image, Coord::new(0, 0), self.size, context);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn images() {
+        let image = Image::from_fn(Size::new(2, 3), |coord| {
+            [coord.x as u8, coord.y as u8, 0, 255]
+        });
+        assert_eq!(image.get(Coord::new(1, 2)), Some([1, 2, 0, 255]));
+        assert_eq!(image.get(Coord::new(2, 0)), None);
+        assert_eq!(image.clone(), image);
+        assert_ne!(
+            Image::from_rgba_bytes(Size::new(2, 3), image.rgba_bytes().to_vec()),
+            image
+        );
+        let placement = ImagePlacement::new(image, Coord::new(1, 1), Size::new(2, 2));
+        assert!(placement.covers(Coord::new(2, 2)));
+        assert!(!placement.covers(Coord::new(3, 1)));
+        assert!(placement.fits_within(Size::new(3, 3)));
+        assert!(!placement.fits_within(Size::new(3, 2)));
+    }
+}
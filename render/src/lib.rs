@@ -2,6 +2,10 @@ mod blend;
 mod buffer;
 mod col_modify;
 mod context;
+mod cursor;
+mod grapheme;
+mod hyperlink;
+mod image;
 mod view;
 mod view_cell;
 
@@ -9,7 +13,11 @@ pub use blend::*;
 pub use buffer::*;
 pub use col_modify::*;
 pub use context::*;
+pub use cursor::*;
+pub use grapheme::*;
 pub use grid_2d::{self, Coord, Size};
+pub use hyperlink::*;
+pub use image::*;
 pub use rgb24::*;
 pub use view::*;
 pub use view_cell::*;
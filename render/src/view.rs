@@ -1,6 +1,9 @@
 use super::{blend_mode, Blend, Coord, Rgb24, Size};
 use crate::col_modify::ColModify;
 use crate::context::*;
+use crate::cursor::*;
+use crate::hyperlink::Hyperlink;
+use crate::image::*;
 use crate::view_cell::*;
 
 fn set_cell_relative_to_draw<F: ?Sized + Frame, C: ColModify>(
@@ -21,6 +24,9 @@ fn set_cell_relative_to_draw<F: ?Sized + Frame, C: ColModify>(
                 background: context
                     .col_modify
                     .background(relative_cell.style.background),
+                underline_colour: relative_cell.style.underline_colour.and_then(
+                    |underline_colour| context.col_modify.foreground(Some(underline_colour)),
+                ),
                 ..relative_cell.style
             },
             ..relative_cell
@@ -53,6 +59,34 @@ fn blend_cell_background_relative_to_draw<F: ?Sized + Frame, C: ColModify, B: Bl
     }
 }
 
+fn set_cursor_relative_to_draw<F: ?Sized + Frame, C: ColModify>(
+    frame: &mut F,
+    relative_cursor: Cursor,
+    context: ViewContext<C>,
+) {
+    if relative_cursor.coord.is_valid(context.size) {
+        frame.set_cursor_absolute(
+            relative_cursor.with_coord(relative_cursor.coord + context.offset),
+        );
+    }
+}
+
+fn draw_image_relative_to_draw<F: ?Sized + Frame, C: ColModify>(
+    frame: &mut F,
+    image: &Image,
+    relative_coord: Coord,
+    size: Size,
+    context: ViewContext<C>,
+) {
+    let relative_placement = ImagePlacement::new(image.clone(), relative_coord, size);
+    if relative_placement.fits_within(context.size) {
+        frame.draw_image_absolute(ImagePlacement {
+            coord: relative_coord + context.offset,
+            ..relative_placement
+        });
+    }
+}
+
 fn set_cell_relative_to_measure_size<F: ?Sized + Frame, C: ColModify>(
     frame: &mut F,
     relative_coord: Coord,
@@ -125,6 +159,43 @@ pub trait Frame {
         alpha: u8,
         blend: B,
     );
+    /// Show the cursor at a coordinate relative to `context` during this frame. Cursors outside
+    /// the bounds of `context` are ignored.
+    fn set_cursor_relative<C: ColModify>(
+        &mut self,
+        relative_cursor: Cursor,
+        context: ViewContext<C>,
+    ) {
+        set_cursor_relative_to_draw(self, relative_cursor, context);
+    }
+    /// Show the cursor during this frame. Frames which have nowhere to show a cursor (e.g. when
+    /// measuring the size of a view) ignore cursor requests.
+    fn set_cursor_absolute(&mut self, absolute_cursor: Cursor) {
+        let _ = absolute_cursor;
+    }
+    /// Register the target of a hyperlink with this frame, returning a handle to it for styling
+    /// the cells which link to it (see `Style::with_hyperlink`). Frames which don't keep the cells
+    /// drawn into them (e.g. when measuring the size of a view) needn't remember the target.
+    fn hyperlink(&mut self, target: &str) -> Hyperlink {
+        Hyperlink::new(target)
+    }
+    /// Draw `image` over a rectangle of `size` cells whose top-left corner is at a coordinate
+    /// relative to `context`. Images which don't fit entirely within the bounds of `context` are
+    /// ignored.
+    fn draw_image_relative<C: ColModify>(
+        &mut self,
+        image: &Image,
+        relative_coord: Coord,
+        size: Size,
+        context: ViewContext<C>,
+    ) {
+        draw_image_relative_to_draw(self, image, relative_coord, size, context);
+    }
+    /// Draw an image over the cells it covers during this frame. Frames which can't show images
+    /// ignore them.
+    fn draw_image_absolute(&mut self, absolute_placement: ImagePlacement) {
+        let _ = absolute_placement;
+    }
 }
 
 struct MeasureBounds {
@@ -188,6 +259,11 @@ impl Frame for MeasureBounds {
     ) {
         self.set_max(absolute_coord);
     }
+    fn draw_image_absolute(&mut self, absolute_placement: ImagePlacement) {
+        if let Ok(size) = absolute_placement.size.to_coord() {
+            self.set_max(absolute_placement.coord + size - Coord::new(1, 1));
+        }
+    }
 }
 
 pub struct MeasureBoundsAndDraw<'a, D> {
@@ -316,6 +392,35 @@ where
             blend,
         );
     }
+    fn set_cursor_relative<C: ColModify>(
+        &mut self,
+        relative_cursor: Cursor,
+        context: ViewContext<C>,
+    ) {
+        self.draw.set_cursor_relative(relative_cursor, context);
+    }
+    fn set_cursor_absolute(&mut self, absolute_cursor: Cursor) {
+        self.draw.set_cursor_absolute(absolute_cursor);
+    }
+    fn hyperlink(&mut self, target: &str) -> Hyperlink {
+        self.draw.hyperlink(target)
+    }
+    fn draw_image_relative<C: ColModify>(
+        &mut self,
+        image: &Image,
+        relative_coord: Coord,
+        size: Size,
+        context: ViewContext<C>,
+    ) {
+        self.draw
+            .draw_image_relative(image, relative_coord, size, context);
+        self.measure_bounds
+            .draw_image_relative(image, relative_coord, size, context);
+    }
+    fn draw_image_absolute(&mut self, absolute_placement: ImagePlacement) {
+        self.draw.draw_image_absolute(absolute_placement.clone());
+        self.measure_bounds.draw_image_absolute(absolute_placement);
+    }
 }
 
 pub trait View<T> {
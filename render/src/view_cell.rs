@@ -1,14 +1,48 @@
+use crate::grapheme::Grapheme;
+use crate::hyperlink::Hyperlink;
 use rgb24::Rgb24;
 #[cfg(feature = "serialize")]
 use serde::{Deserialize, Serialize};
 
+/// The shape of an underline. Frontends which can't draw a particular kind of underline fall
+/// back to a single straight underline.
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum UnderlineKind {
+    #[default]
+    Single,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Style {
     pub bold: Option<bool>,
     pub underline: Option<bool>,
+    pub underline_kind: Option<UnderlineKind>,
+    /// If unset, underlines are drawn in the foreground colour
+    pub underline_colour: Option<Rgb24>,
+    pub italic: Option<bool>,
+    pub strikethrough: Option<bool>,
+    pub dim: Option<bool>,
+    pub reverse: Option<bool>,
+    pub blink: Option<bool>,
     pub foreground: Option<Rgb24>,
     pub background: Option<Rgb24>,
+    /// Draw in the terminal's default foreground colour (SGR 39) rather than `foreground`, to match
+    /// the user's terminal theme. Frontends which have no default colour draw white.
+    pub default_foreground: Option<bool>,
+    /// Leave the background transparent, showing the terminal's default background colour (SGR 49)
+    /// rather than `background`. Frontends which have no default colour draw black.
+    pub default_background: Option<bool>,
+    /// The link belongs to the cell's character. Drawing a character over a cell replaces its
+    /// link with this one, or removes the link if this is unset. Handles are only meaningful to
+    /// the frame they're registered with, so they aren't serialized.
+    #[cfg_attr(feature = "serialize", serde(skip))]
+    pub hyperlink: Option<Hyperlink>,
 }
 
 impl Default for Style {
@@ -22,8 +56,18 @@ impl Style {
         Self {
             bold: None,
             underline: None,
+            underline_kind: None,
+            underline_colour: None,
+            italic: None,
+            strikethrough: None,
+            dim: None,
+            reverse: None,
+            blink: None,
             foreground: None,
             background: None,
+            default_foreground: None,
+            default_background: None,
+            hyperlink: None,
         }
     }
     pub const fn with_bold(self, bold: bool) -> Self {
@@ -38,6 +82,48 @@ impl Style {
             ..self
         }
     }
+    pub const fn with_underline_kind(self, underline_kind: UnderlineKind) -> Self {
+        Self {
+            underline_kind: Some(underline_kind),
+            ..self
+        }
+    }
+    pub const fn with_underline_colour(self, underline_colour: Rgb24) -> Self {
+        Self {
+            underline_colour: Some(underline_colour),
+            ..self
+        }
+    }
+    pub const fn with_italic(self, italic: bool) -> Self {
+        Self {
+            italic: Some(italic),
+            ..self
+        }
+    }
+    pub const fn with_strikethrough(self, strikethrough: bool) -> Self {
+        Self {
+            strikethrough: Some(strikethrough),
+            ..self
+        }
+    }
+    pub const fn with_dim(self, dim: bool) -> Self {
+        Self {
+            dim: Some(dim),
+            ..self
+        }
+    }
+    pub const fn with_reverse(self, reverse: bool) -> Self {
+        Self {
+            reverse: Some(reverse),
+            ..self
+        }
+    }
+    pub const fn with_blink(self, blink: bool) -> Self {
+        Self {
+            blink: Some(blink),
+            ..self
+        }
+    }
     pub const fn with_foreground(self, foreground: Rgb24) -> Self {
         Self {
             foreground: Some(foreground),
@@ -50,6 +136,24 @@ impl Style {
             ..self
         }
     }
+    pub const fn with_default_foreground(self, default_foreground: bool) -> Self {
+        Self {
+            default_foreground: Some(default_foreground),
+            ..self
+        }
+    }
+    pub const fn with_default_background(self, default_background: bool) -> Self {
+        Self {
+            default_background: Some(default_background),
+            ..self
+        }
+    }
+    pub const fn with_hyperlink(self, hyperlink: Hyperlink) -> Self {
+        Self {
+            hyperlink: Some(hyperlink),
+            ..self
+        }
+    }
     pub const fn without_bold(self) -> Self {
         Self { bold: None, ..self }
     }
@@ -59,6 +163,45 @@ impl Style {
             ..self
         }
     }
+    pub const fn without_underline_kind(self) -> Self {
+        Self {
+            underline_kind: None,
+            ..self
+        }
+    }
+    pub const fn without_underline_colour(self) -> Self {
+        Self {
+            underline_colour: None,
+            ..self
+        }
+    }
+    pub const fn without_italic(self) -> Self {
+        Self {
+            italic: None,
+            ..self
+        }
+    }
+    pub const fn without_strikethrough(self) -> Self {
+        Self {
+            strikethrough: None,
+            ..self
+        }
+    }
+    pub const fn without_dim(self) -> Self {
+        Self { dim: None, ..self }
+    }
+    pub const fn without_reverse(self) -> Self {
+        Self {
+            reverse: None,
+            ..self
+        }
+    }
+    pub const fn without_blink(self) -> Self {
+        Self {
+            blink: None,
+            ..self
+        }
+    }
     pub const fn without_foreground(self) -> Self {
         Self {
             foreground: None,
@@ -71,12 +214,40 @@ impl Style {
             ..self
         }
     }
+    pub const fn without_default_foreground(self) -> Self {
+        Self {
+            default_foreground: None,
+            ..self
+        }
+    }
+    pub const fn without_default_background(self) -> Self {
+        Self {
+            default_background: None,
+            ..self
+        }
+    }
+    pub const fn without_hyperlink(self) -> Self {
+        Self {
+            hyperlink: None,
+            ..self
+        }
+    }
     pub fn coalesce(self, other: Self) -> Self {
         Self {
             bold: (self.bold.or(other.bold)),
             underline: (self.underline.or(other.underline)),
+            underline_kind: (self.underline_kind.or(other.underline_kind)),
+            underline_colour: (self.underline_colour.or(other.underline_colour)),
+            italic: (self.italic.or(other.italic)),
+            strikethrough: (self.strikethrough.or(other.strikethrough)),
+            dim: (self.dim.or(other.dim)),
+            reverse: (self.reverse.or(other.reverse)),
+            blink: (self.blink.or(other.blink)),
             foreground: (self.foreground.or(other.foreground)),
             background: (self.background.or(other.background)),
+            default_foreground: (self.default_foreground.or(other.default_foreground)),
+            default_background: (self.default_background.or(other.default_background)),
+            hyperlink: (self.hyperlink.or(other.hyperlink)),
         }
     }
 }
@@ -84,7 +255,7 @@ impl Style {
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ViewCell {
-    pub character: Option<char>,
+    pub character: Option<Grapheme>,
     pub style: Style,
 }
 
@@ -95,7 +266,7 @@ impl ViewCell {
             style: Style::new(),
         }
     }
-    pub const fn character(&self) -> Option<char> {
+    pub const fn character(&self) -> Option<Grapheme> {
         self.character
     }
     pub const fn bold(&self) -> Option<bool> {
@@ -104,15 +275,51 @@ impl ViewCell {
     pub const fn underline(&self) -> Option<bool> {
         self.style.underline
     }
+    pub const fn underline_kind(&self) -> Option<UnderlineKind> {
+        self.style.underline_kind
+    }
+    pub const fn underline_colour(&self) -> Option<Rgb24> {
+        self.style.underline_colour
+    }
+    pub const fn italic(&self) -> Option<bool> {
+        self.style.italic
+    }
+    pub const fn strikethrough(&self) -> Option<bool> {
+        self.style.strikethrough
+    }
+    pub const fn dim(&self) -> Option<bool> {
+        self.style.dim
+    }
+    pub const fn reverse(&self) -> Option<bool> {
+        self.style.reverse
+    }
+    pub const fn blink(&self) -> Option<bool> {
+        self.style.blink
+    }
     pub const fn foreground(&self) -> Option<Rgb24> {
         self.style.foreground
     }
     pub const fn background(&self) -> Option<Rgb24> {
         self.style.background
     }
+    pub const fn default_foreground(&self) -> Option<bool> {
+        self.style.default_foreground
+    }
+    pub const fn default_background(&self) -> Option<bool> {
+        self.style.default_background
+    }
+    pub const fn hyperlink(&self) -> Option<Hyperlink> {
+        self.style.hyperlink
+    }
     pub const fn with_character(self, character: char) -> Self {
         Self {
-            character: Some(character),
+            character: Some(Grapheme::from_char(character)),
+            ..self
+        }
+    }
+    pub const fn with_grapheme(self, grapheme: Grapheme) -> Self {
+        Self {
+            character: Some(grapheme),
             ..self
         }
     }
@@ -128,6 +335,48 @@ impl ViewCell {
             ..self
         }
     }
+    pub const fn with_underline_kind(self, underline_kind: UnderlineKind) -> Self {
+        Self {
+            style: self.style.with_underline_kind(underline_kind),
+            ..self
+        }
+    }
+    pub const fn with_underline_colour(self, underline_colour: Rgb24) -> Self {
+        Self {
+            style: self.style.with_underline_colour(underline_colour),
+            ..self
+        }
+    }
+    pub const fn with_italic(self, italic: bool) -> Self {
+        Self {
+            style: self.style.with_italic(italic),
+            ..self
+        }
+    }
+    pub const fn with_strikethrough(self, strikethrough: bool) -> Self {
+        Self {
+            style: self.style.with_strikethrough(strikethrough),
+            ..self
+        }
+    }
+    pub const fn with_dim(self, dim: bool) -> Self {
+        Self {
+            style: self.style.with_dim(dim),
+            ..self
+        }
+    }
+    pub const fn with_reverse(self, reverse: bool) -> Self {
+        Self {
+            style: self.style.with_reverse(reverse),
+            ..self
+        }
+    }
+    pub const fn with_blink(self, blink: bool) -> Self {
+        Self {
+            style: self.style.with_blink(blink),
+            ..self
+        }
+    }
     pub const fn with_foreground(self, foreground: Rgb24) -> Self {
         Self {
             style: self.style.with_foreground(foreground),
@@ -140,6 +389,24 @@ impl ViewCell {
             ..self
         }
     }
+    pub const fn with_default_foreground(self, default_foreground: bool) -> Self {
+        Self {
+            style: self.style.with_default_foreground(default_foreground),
+            ..self
+        }
+    }
+    pub const fn with_default_background(self, default_background: bool) -> Self {
+        Self {
+            style: self.style.with_default_background(default_background),
+            ..self
+        }
+    }
+    pub const fn with_hyperlink(self, hyperlink: Hyperlink) -> Self {
+        Self {
+            style: self.style.with_hyperlink(hyperlink),
+            ..self
+        }
+    }
     pub const fn without_character(self) -> Self {
         Self {
             character: None,
@@ -158,6 +425,48 @@ impl ViewCell {
             ..self
         }
     }
+    pub const fn without_underline_kind(self) -> Self {
+        Self {
+            style: self.style.without_underline_kind(),
+            ..self
+        }
+    }
+    pub const fn without_underline_colour(self) -> Self {
+        Self {
+            style: self.style.without_underline_colour(),
+            ..self
+        }
+    }
+    pub const fn without_italic(self) -> Self {
+        Self {
+            style: self.style.without_italic(),
+            ..self
+        }
+    }
+    pub const fn without_strikethrough(self) -> Self {
+        Self {
+            style: self.style.without_strikethrough(),
+            ..self
+        }
+    }
+    pub const fn without_dim(self) -> Self {
+        Self {
+            style: self.style.without_dim(),
+            ..self
+        }
+    }
+    pub const fn without_reverse(self) -> Self {
+        Self {
+            style: self.style.without_reverse(),
+            ..self
+        }
+    }
+    pub const fn without_blink(self) -> Self {
+        Self {
+            style: self.style.without_blink(),
+            ..self
+        }
+    }
     pub const fn without_foreground(self) -> Self {
         Self {
             style: self.style.without_foreground(),
@@ -170,6 +479,24 @@ impl ViewCell {
             ..self
         }
     }
+    pub const fn without_default_foreground(self) -> Self {
+        Self {
+            style: self.style.without_default_foreground(),
+            ..self
+        }
+    }
+    pub const fn without_default_background(self) -> Self {
+        Self {
+            style: self.style.without_default_background(),
+            ..self
+        }
+    }
+    pub const fn without_hyperlink(self) -> Self {
+        Self {
+            style: self.style.without_hyperlink(),
+            ..self
+        }
+    }
     pub const fn with_style(self, style: Style) -> Self {
         Self { style, ..self }
     }
@@ -0,0 +1,495 @@
+pub use chargrid_ansi_terminal;
+use chargrid_ansi_terminal::{Context, StreamSize, Streams};
+pub use chargrid_app;
+use chargrid_app::App;
+pub use chargrid_render;
+use chargrid_render::Size;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod protocol;
+
+const DEFAULT_TERM: &str = "xterm-256color";
+const DEFAULT_WIDTH: u32 = 80;
+const DEFAULT_HEIGHT: u32 = 24;
+const DEFAULT_NEGOTIATION_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Options applying to every connection
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// The name of the terminfo entry describing clients' terminals. Defaults to "xterm-256color".
+    pub term: String,
+    /// The size assumed for clients which don't report their window size. Defaults to 80x24.
+    pub default_size: Size,
+    /// How long to wait for a client to report its window size before starting the app
+    pub negotiation_timeout: Duration,
+    /// Options for each connection's terminal context
+    pub context: chargrid_ansi_terminal::Config,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            term: DEFAULT_TERM.to_string(),
+            default_size: Size::new(DEFAULT_WIDTH, DEFAULT_HEIGHT),
+            negotiation_timeout: DEFAULT_NEGOTIATION_TIMEOUT,
+            context: chargrid_ansi_terminal::Config::default(),
+        }
+    }
+}
+
+/// Writes to a client, escaping the data. It's shared with the thread reading from the client,
+/// which replies to the client's negotiation, so that replies aren't interleaved with output.
+#[derive(Clone)]
+struct TelnetWriter {
+    stream: Arc<Mutex<TcpStream>>,
+    escaped: Vec<u8>,
+}
+
+impl TelnetWriter {
+    fn write_raw(&self, bytes: &[u8]) -> io::Result<()> {
+        let mut stream = self.stream.lock().unwrap_or_else(PoisonError::into_inner);
+        stream.write_all(bytes)
+    }
+}
+
+impl Write for TelnetWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.escaped.clear();
+        protocol::escape(buf, &mut self.escaped);
+        self.write_raw(&self.escaped)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads input from a client, with telnet commands removed. Window size reports update `size`.
+struct TelnetReader {
+    stream: TcpStream,
+    decoder: protocol::Decoder,
+    writer: TelnetWriter,
+    size: StreamSize,
+    window_size_reported: bool,
+    /// Decoded input which hasn't been read yet
+    pending: Vec<u8>,
+}
+
+impl TelnetReader {
+    /// Read and decode the next chunk of bytes from the client. Returns false if the connection
+    /// was closed.
+    fn fill(&mut self) -> io::Result<bool> {
+        let mut buf = [0; 1024];
+        let len = self.stream.read(&mut buf)?;
+        if len == 0 {
+            return Ok(false);
+        }
+        let decoded = self.decoder.decode(&buf[..len]);
+        if !decoded.replies.is_empty() {
+            self.writer.write_raw(&decoded.replies)?;
+        }
+        if let Some(size) = decoded.window_size {
+            self.size.set(size);
+            self.window_size_reported = true;
+        }
+        self.pending.extend_from_slice(&decoded.data);
+        Ok(true)
+    }
+
+    /// Wait up to `timeout` for the client to report its window size
+    fn wait_for_window_size(&mut self, timeout: Duration) -> io::Result<()> {
+        let deadline = Instant::now() + timeout;
+        while !self.window_size_reported {
+            let remaining = match deadline.checked_duration_since(Instant::now()) {
+                Some(remaining) if remaining > Duration::from_secs(0) => remaining,
+                _ => break,
+            };
+            self.stream.set_read_timeout(Some(remaining))?;
+            match self.fill() {
+                Ok(true) => (),
+                Ok(false) => break,
+                Err(error)
+                    if error.kind() == io::ErrorKind::WouldBlock
+                        || error.kind() == io::ErrorKind::TimedOut =>
+                {
+                    break
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        self.stream.set_read_timeout(None)
+    }
+}
+
+impl Read for TelnetReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pending.is_empty() {
+            if !self.fill()? {
+                return Ok(0);
+            }
+        }
+        let len = buf.len().min(self.pending.len());
+        buf[..len].copy_from_slice(&self.pending[..len]);
+        self.pending.drain(..len);
+        Ok(len)
+    }
+}
+
+/// Run an app for a single client until the app exits or the client disconnects. The app is
+/// created by calling `new_app` with the connection's context, once the client's window size is
+/// known. The connection is closed before this returns.
+pub fn serve_connection<F, A>(
+    stream: TcpStream,
+    config: &Config,
+    new_app: F,
+) -> chargrid_app::Result<()>
+where
+    F: FnOnce(&Context) -> A,
+    A: App + 'static,
+{
+    let result = run_connection(&stream, config, new_app);
+    // ends the thread which reads from the client
+    let _ = stream.shutdown(Shutdown::Both);
+    result
+}
+
+fn run_connection<F, A>(stream: &TcpStream, config: &Config, new_app: F) -> chargrid_app::Result<()>
+where
+    F: FnOnce(&Context) -> A,
+    A: App + 'static,
+{
+    stream
+        .set_nodelay(true)
+        .map_err(chargrid_app::Error::frontend)?;
+    let writer = TelnetWriter {
+        stream: Arc::new(Mutex::new(
+            stream.try_clone().map_err(chargrid_app::Error::frontend)?,
+        )),
+        escaped: Vec::new(),
+    };
+    writer
+        .write_raw(&protocol::initial_negotiation())
+        .map_err(chargrid_app::Error::frontend)?;
+    let size = StreamSize::new(config.default_size);
+    let mut reader = TelnetReader {
+        stream: stream.try_clone().map_err(chargrid_app::Error::frontend)?,
+        decoder: protocol::Decoder::new(),
+        writer: writer.clone(),
+        size: size.clone(),
+        window_size_reported: false,
+        pending: Vec::new(),
+    };
+    reader
+        .wait_for_window_size(config.negotiation_timeout)
+        .map_err(chargrid_app::Error::frontend)?;
+    let streams = Streams {
+        input: reader,
+        output: writer,
+        size,
+        term: Some(config.term.clone()),
+    };
+    let context = Context::new_with_streams(streams, config.context.clone())?;
+    let app = new_app(&context);
+    let col_encode = context.col_encode();
+    context.try_run_app(app, col_encode)
+}
+
+/// Accepts telnet connections, running a separate instance of an app for each client
+pub struct Server {
+    listener: TcpListener,
+    config: Config,
+}
+
+impl Server {
+    /// Listen for connections on `addr`. Telnet is unencrypted, so this would usually be a
+    /// loopback address (e.g. "127.0.0.1:2323").
+    pub fn bind<S: ToSocketAddrs>(addr: S) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            config: Config::default(),
+        })
+    }
+
+    pub fn with_config(self, config: Config) -> Self {
+        Self { config, ..self }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accept connections, serving each client on its own thread. Failing to accept a connection
+    /// is logged and doesn't stop the server. Each client gets its own app, created by calling
+    /// `new_app` on the client's thread, so apps needn't be `Send`. State shared between clients
+    /// can be captured by `new_app`.
+    pub fn run<F, A>(self, new_app: F) -> io::Result<()>
+    where
+        F: Fn(&Context) -> A + Send + Sync + 'static,
+        A: App + 'static,
+    {
+        let config = Arc::new(self.config);
+        let new_app = Arc::new(new_app);
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(error) => {
+                    log::warn!("failed to accept connection: {}", error);
+                    continue;
+                }
+            };
+            let config = Arc::clone(&config);
+            let new_app = Arc::clone(&new_app);
+            thread::spawn(move || {
+                let peer = stream.peer_addr();
+                let result = serve_connection(stream, &config, |context| new_app(context));
+                if let (Err(error), Ok(peer)) = (result, peer) {
+                    log::info!("connection from {} ended: {}", peer, error);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::protocol::*;
+    use super::*;
+    use chargrid_app::{ColModify, ControlFlow, Frame, ViewContext};
+    use chargrid_input::{Input, KeyboardInput};
+    use chargrid_render::{Coord, ViewCell};
+
+    const READ_TIMEOUT: Duration = Duration::from_secs(5);
+    // the response to the primary device attributes query which ends initialisation
+    const DEVICE_ATTRIBUTES: &[u8] = b"\x1b[?62c";
+
+    /// Draws the window size, and the most recent line of text entered. Exits on "q".
+    struct LineEcho {
+        size: Size,
+        line: String,
+        entered: String,
+    }
+
+    impl LineEcho {
+        fn new(context: &Context) -> Self {
+            Self {
+                size: context.size().unwrap(),
+                line: String::new(),
+                entered: String::new(),
+            }
+        }
+    }
+
+    impl App for LineEcho {
+        fn on_input(&mut self, input: Input) -> Option<ControlFlow> {
+            match input {
+                Input::Keyboard(KeyboardInput::Char('q')) => return Some(ControlFlow::Exit),
+                Input::Keyboard(KeyboardInput::Char('\r')) => {
+                    self.entered = std::mem::take(&mut self.line);
+                }
+                Input::Keyboard(KeyboardInput::Char(ch)) => self.line.push(ch),
+                Input::Resize(size) => self.size = size,
+                _ => (),
+            }
+            None
+        }
+        fn on_frame<F, C>(
+            &mut self,
+            _since_last_frame: Duration,
+            view_context: ViewContext<C>,
+            frame: &mut F,
+        ) -> Option<ControlFlow>
+        where
+            F: Frame,
+            C: ColModify,
+        {
+            let size = format!("{}x{}", self.size.width(), self.size.height());
+            let entered = format!("entered:{}", self.entered);
+            for (y, text) in [size, entered].iter().enumerate() {
+                for (x, ch) in text.chars().enumerate() {
+                    let view_cell = ViewCell::new().with_character(ch);
+                    let coord = Coord::new(x as i32, y as i32);
+                    frame.set_cell_relative(coord, 0, view_cell, view_context);
+                }
+            }
+            None
+        }
+    }
+
+    /// A telnet client which agrees to all of the server's options, reports its window size, and
+    /// responds to the terminal queries sent when a context is created
+    struct Client {
+        stream: TcpStream,
+        size: Size,
+        unprocessed: Vec<u8>,
+        received: Vec<u8>,
+        negotiation: Vec<(u8, u8)>,
+        answered_device_attributes: bool,
+    }
+
+    impl Client {
+        fn connect(addr: SocketAddr, size: Size) -> Self {
+            let stream = TcpStream::connect(addr).unwrap();
+            stream.set_read_timeout(Some(READ_TIMEOUT)).unwrap();
+            Self {
+                stream,
+                size,
+                unprocessed: Vec::new(),
+                received: Vec::new(),
+                negotiation: Vec::new(),
+                answered_device_attributes: false,
+            }
+        }
+
+        fn send(&mut self, data: &[u8]) {
+            let mut escaped = Vec::new();
+            escape(data, &mut escaped);
+            self.stream.write_all(&escaped).unwrap();
+        }
+
+        fn send_window_size(&mut self) {
+            let mut bytes = vec![IAC, SB, NAWS];
+            let width = self.size.width() as u16;
+            let height = self.size.height() as u16;
+            escape(
+                &[width.to_be_bytes(), height.to_be_bytes()].concat(),
+                &mut bytes,
+            );
+            bytes.extend_from_slice(&[IAC, SE]);
+            self.stream.write_all(&bytes).unwrap();
+        }
+
+        fn resize(&mut self, size: Size) {
+            self.size = size;
+            self.send_window_size();
+        }
+
+        fn negotiate(&mut self, command: u8, option: u8) {
+            self.negotiation.push((command, option));
+            match command {
+                WILL => self.stream.write_all(&[IAC, DO, option]).unwrap(),
+                DO => {
+                    self.stream.write_all(&[IAC, WILL, option]).unwrap();
+                    if option == NAWS {
+                        self.send_window_size();
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        /// Separate commands from data, leaving incomplete commands unprocessed
+        fn process(&mut self) {
+            let mut i = 0;
+            while i < self.unprocessed.len() {
+                if self.unprocessed[i] != IAC {
+                    self.received.push(self.unprocessed[i]);
+                    i += 1;
+                    continue;
+                }
+                match self.unprocessed.get(i + 1).cloned() {
+                    None => break,
+                    Some(IAC) => {
+                        self.received.push(IAC);
+                        i += 2;
+                    }
+                    Some(command) if (WILL..=DONT).contains(&command) => {
+                        match self.unprocessed.get(i + 2).cloned() {
+                            None => break,
+                            Some(option) => {
+                                self.negotiate(command, option);
+                                i += 3;
+                            }
+                        }
+                    }
+                    Some(_) => i += 2,
+                }
+            }
+            self.unprocessed.drain(..i);
+        }
+
+        fn received(&self) -> String {
+            String::from_utf8_lossy(&self.received).to_string()
+        }
+
+        /// Read until `text` is received, or until the server closes the connection if `text`
+        /// is `None`
+        fn read_until(&mut self, text: Option<&str>) {
+            let mut buf = [0; 1024];
+            loop {
+                if let Some(text) = text {
+                    if self.received().contains(text) {
+                        return;
+                    }
+                }
+                let len = self
+                    .stream
+                    .read(&mut buf)
+                    .unwrap_or_else(|error| panic!("{} (received {:?})", error, self.received()));
+                if len == 0 {
+                    assert!(
+                        text.is_none(),
+                        "connection closed before receiving {:?}",
+                        text
+                    );
+                    return;
+                }
+                self.unprocessed.extend_from_slice(&buf[..len]);
+                self.process();
+                if !self.answered_device_attributes && self.received().contains("\x1b[c") {
+                    self.answered_device_attributes = true;
+                    self.send(DEVICE_ATTRIBUTES);
+                }
+            }
+        }
+    }
+
+    fn spawn_server() -> SocketAddr {
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.run(LineEcho::new));
+        addr
+    }
+
+    #[test]
+    fn negotiation_input_and_resizing() {
+        let addr = spawn_server();
+        let mut client = Client::connect(addr, Size::new(40, 10));
+        client.read_until(Some("40x10"));
+        for &(command, option) in &[(WILL, ECHO), (WILL, SUPPRESS_GO_AHEAD), (DO, NAWS)] {
+            assert!(client.negotiation.contains(&(command, option)));
+        }
+        // enter is sent as CR NUL
+        client.send(b"hello\r\0");
+        client.read_until(Some("hello"));
+        client.resize(Size::new(50, 12));
+        client.read_until(Some("50x12"));
+        client.send(b"q");
+        client.read_until(None);
+    }
+
+    #[test]
+    fn clients_have_separate_apps() {
+        let addr = spawn_server();
+        let mut first = Client::connect(addr, Size::new(40, 10));
+        let mut second = Client::connect(addr, Size::new(30, 8));
+        first.read_until(Some("40x10"));
+        second.read_until(Some("30x8"));
+        first.send(b"first\r\0");
+        second.send(b"second\r\0");
+        first.read_until(Some("first"));
+        second.read_until(Some("second"));
+        assert!(!first.received().contains("second"));
+        assert!(!second.received().contains("first"));
+        first.send(b"q");
+        first.read_until(None);
+        // the second client is unaffected by the first leaving
+        second.send(b"again\r\0");
+        second.read_until(Some("again"));
+    }
+}
@@ -0,0 +1,225 @@
+//! The parts of the telnet protocol (RFC 854) needed to run a full-screen app: negotiating
+//! character-at-a-time input without local echo, binary transmission, and window size reports
+//! (RFC 1073).
+
+use chargrid_render::Size;
+
+pub const IAC: u8 = 255;
+pub const DONT: u8 = 254;
+pub const DO: u8 = 253;
+pub const WONT: u8 = 252;
+pub const WILL: u8 = 251;
+pub const SB: u8 = 250;
+pub const SE: u8 = 240;
+
+pub const BINARY: u8 = 0;
+pub const ECHO: u8 = 1;
+pub const SUPPRESS_GO_AHEAD: u8 = 3;
+pub const NAWS: u8 = 31;
+
+const CR: u8 = b'\r';
+const LF: u8 = b'\n';
+const NUL: u8 = 0;
+
+/// Options which the server offers to perform
+const SERVER_OPTIONS: &[u8] = &[ECHO, SUPPRESS_GO_AHEAD, BINARY];
+/// Options which the server asks the client to perform
+const CLIENT_OPTIONS: &[u8] = &[SUPPRESS_GO_AHEAD, BINARY, NAWS];
+
+/// Subnegotiation beyond this length is discarded. This is one more than the length of a window
+/// size report (the option and 2 bytes each for width and height), so longer subnegotiation isn't
+/// mistaken for one.
+const MAX_SUBNEGOTIATION_LEN: usize = 6;
+
+/// Sent when a client connects. The server offers to echo input, which stops the client from
+/// echoing locally (the server doesn't actually echo anything, as the app draws the screen), and
+/// asks the client to report its window size.
+pub fn initial_negotiation() -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for &option in SERVER_OPTIONS {
+        bytes.extend_from_slice(&[IAC, WILL, option]);
+    }
+    for &option in CLIENT_OPTIONS {
+        bytes.extend_from_slice(&[IAC, DO, option]);
+    }
+    bytes
+}
+
+/// Escape data sent to the client
+pub fn escape(data: &[u8], out: &mut Vec<u8>) {
+    for &byte in data {
+        if byte == IAC {
+            out.push(IAC);
+        }
+        out.push(byte);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum State {
+    #[default]
+    Data,
+    /// After a carriage return, which may be followed by a NUL or line feed that isn't part of
+    /// the input
+    CarriageReturn,
+    Command,
+    Negotiation(u8),
+    Subnegotiation,
+    SubnegotiationCommand,
+}
+
+/// The result of decoding bytes received from a client
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Decoded {
+    /// Input with telnet commands removed
+    pub data: Vec<u8>,
+    /// Responses to the client's negotiation
+    pub replies: Vec<u8>,
+    /// The most recent window size reported by the client
+    pub window_size: Option<Size>,
+}
+
+/// Separates telnet commands from the input they're interleaved with. Commands can be split
+/// across reads, so the decoder keeps the state of any incomplete command.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    state: State,
+    subnegotiation: Vec<u8>,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn decode(&mut self, bytes: &[u8]) -> Decoded {
+        let mut decoded = Decoded::default();
+        for &byte in bytes {
+            self.state = match (self.state, byte) {
+                (State::Data, IAC) | (State::CarriageReturn, IAC) => State::Command,
+                // enter is sent as CR NUL or CR LF, but apps expect a lone CR
+                (State::CarriageReturn, NUL) | (State::CarriageReturn, LF) => State::Data,
+                (State::Data, CR) | (State::CarriageReturn, CR) => {
+                    decoded.data.push(CR);
+                    State::CarriageReturn
+                }
+                (State::Data, byte) | (State::CarriageReturn, byte) => {
+                    decoded.data.push(byte);
+                    State::Data
+                }
+                (State::Command, IAC) => {
+                    decoded.data.push(IAC);
+                    State::Data
+                }
+                (State::Command, SB) => {
+                    self.subnegotiation.clear();
+                    State::Subnegotiation
+                }
+                (State::Command, WILL)
+                | (State::Command, WONT)
+                | (State::Command, DO)
+                | (State::Command, DONT) => State::Negotiation(byte),
+                // other commands (e.g. NOP or go ahead) have no effect
+                (State::Command, _) => State::Data,
+                (State::Negotiation(command), option) => {
+                    Self::reply(command, option, &mut decoded.replies);
+                    State::Data
+                }
+                (State::Subnegotiation, IAC) => State::SubnegotiationCommand,
+                (State::Subnegotiation, byte) | (State::SubnegotiationCommand, byte @ IAC) => {
+                    if self.subnegotiation.len() < MAX_SUBNEGOTIATION_LEN {
+                        self.subnegotiation.push(byte);
+                    }
+                    State::Subnegotiation
+                }
+                (State::SubnegotiationCommand, SE) => {
+                    if let Some(size) = self.window_size() {
+                        decoded.window_size = Some(size);
+                    }
+                    State::Data
+                }
+                // malformed subnegotiation
+                (State::SubnegotiationCommand, _) => State::Data,
+            };
+        }
+        decoded
+    }
+
+    /// Refuse options the server doesn't support. Requests to enable supported options were
+    /// already made in `initial_negotiation`, so the client's agreement needs no reply, and
+    /// refusals are never replied to, so negotiation can't loop.
+    fn reply(command: u8, option: u8, replies: &mut Vec<u8>) {
+        match command {
+            WILL if !CLIENT_OPTIONS.contains(&option) => {
+                replies.extend_from_slice(&[IAC, DONT, option])
+            }
+            DO if !SERVER_OPTIONS.contains(&option) => {
+                replies.extend_from_slice(&[IAC, WONT, option])
+            }
+            _ => (),
+        }
+    }
+
+    fn window_size(&self) -> Option<Size> {
+        match self.subnegotiation.as_slice() {
+            &[NAWS, width_hi, width_lo, height_hi, height_lo] => {
+                let width = u16::from_be_bytes([width_hi, width_lo]) as u32;
+                let height = u16::from_be_bytes([height_hi, height_lo]) as u32;
+                // clients which don't know their size report 0
+                if width == 0 || height == 0 {
+                    None
+                } else {
+                    Some(Size::new(width, height))
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_and_commands() {
+        let mut decoder = Decoder::new();
+        let decoded = decoder.decode(&[b'a', IAC, IAC, b'b', IAC, DO, ECHO, IAC, DO, 39, b'c']);
+        assert_eq!(decoded.data, vec![b'a', IAC, b'b', b'c']);
+        assert_eq!(decoded.replies, vec![IAC, WONT, 39]);
+        assert_eq!(decoded.window_size, None);
+    }
+
+    #[test]
+    fn carriage_return() {
+        let mut decoder = Decoder::new();
+        let decoded = decoder.decode(b"a\r\0b\r\nc\r");
+        assert_eq!(decoded.data, b"a\rb\rc\r");
+        // the NUL arrives in the next read
+        assert_eq!(decoder.decode(b"\0d").data, b"d");
+    }
+
+    #[test]
+    fn window_size_split_across_reads() {
+        let mut decoder = Decoder::new();
+        let first = decoder.decode(&[b'x', IAC, SB, NAWS, 0]);
+        assert_eq!(first.data, b"x");
+        assert_eq!(first.window_size, None);
+        // a width of 255 is escaped
+        let second = decoder.decode(&[IAC, IAC, 0, 24, IAC, SE, b'y']);
+        assert_eq!(second.data, b"y");
+        assert_eq!(second.window_size, Some(Size::new(255, 24)));
+    }
+
+    #[test]
+    fn long_subnegotiation() {
+        let mut decoder = Decoder::new();
+        let mut bytes = vec![IAC, SB, NAWS, 0, 80, 0, 24];
+        bytes.extend_from_slice(&[IAC; 1000]);
+        bytes.extend_from_slice(&[IAC, SE, b'z']);
+        let decoded = decoder.decode(&bytes);
+        assert_eq!(decoded.data, b"z");
+        assert_eq!(decoded.window_size, None);
+        assert_eq!(decoder.subnegotiation.len(), MAX_SUBNEGOTIATION_LEN);
+    }
+}
@@ -1,44 +1,50 @@
-use chargrid_render::{grid_2d::Grid, Blend, Coord, Frame, Rgb24, Size, ViewCell};
+use chargrid_render::{
+    Blend, Buffer, BufferCell, Coord, Cursor, Frame, Hyperlink, ImagePlacement, Rgb24, Size,
+    ViewCell,
+};
+use std::path::Path;
 
-struct Cell {
-    view_cell: Option<ViewCell>,
-    depth: i8,
-}
-
-impl Cell {
-    fn new() -> Self {
-        Self {
-            view_cell: None,
-            depth: 0,
-        }
-    }
-}
+pub mod snapshot;
 
+/// A frame which records the fully-styled contents of each cell, resolving depth and background
+/// blending the same way as the frontends do.
 pub struct TestGrid {
-    grid: Grid<Cell>,
+    buffer: Buffer,
 }
 
 impl TestGrid {
     pub fn new(size: Size) -> Self {
         Self {
-            grid: Grid::new_fn(size, |_| Cell::new()),
+            buffer: Buffer::new(size),
         }
     }
+    pub fn size(&self) -> Size {
+        self.buffer.size()
+    }
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+    pub fn get(&self, coord: Coord) -> Option<&BufferCell> {
+        self.buffer.get(coord)
+    }
+    pub fn cursor(&self) -> Option<Cursor> {
+        self.buffer.cursor()
+    }
+    pub fn images(&self) -> &[ImagePlacement] {
+        self.buffer.images()
+    }
     pub fn string_rows(&self) -> Vec<String> {
-        let mut rows = Vec::new();
-        for y in 0..self.grid.height() {
-            let mut string = String::new();
-            for x in 0..self.grid.width() {
-                let cell = self.grid.get_checked(Coord::new(x as i32, y as i32));
-                if let Some(view_cell) = cell.view_cell.as_ref() {
-                    string.push(view_cell.character.unwrap_or(' '));
-                } else {
-                    string.push(' ');
-                }
-            }
-            rows.push(string);
-        }
-        rows
+        self.buffer
+            .rows()
+            .map(|row| row.iter().map(|cell| cell.character.as_str()).collect())
+            .collect()
+    }
+    pub fn snapshot(&self) -> String {
+        snapshot::render(&self.buffer)
+    }
+    /// Compare the contents of the grid against a golden file. See `snapshot::assert_matches`.
+    pub fn assert_snapshot<P: AsRef<Path>>(&self, path: P) {
+        snapshot::assert_matches(&self.buffer, path)
     }
 }
 
@@ -49,20 +55,74 @@ impl Frame for TestGrid {
         absolute_depth: i8,
         absolute_cell: ViewCell,
     ) {
-        if let Some(cell) = self.grid.get_mut(absolute_coord) {
-            if absolute_depth >= cell.depth {
-                cell.depth = absolute_depth;
-                cell.view_cell = Some(absolute_cell);
-            }
-        }
+        self.buffer
+            .set_cell_absolute(absolute_coord, absolute_depth, absolute_cell);
     }
     fn blend_cell_background_absolute<B: Blend>(
         &mut self,
-        _absolute_coord: Coord,
-        _absolute_depth: i8,
-        _rgb24: Rgb24,
-        _alpha: u8,
-        _blend: B,
+        absolute_coord: Coord,
+        absolute_depth: i8,
+        rgb24: Rgb24,
+        alpha: u8,
+        blend: B,
     ) {
+        self.buffer.blend_cell_background_absolute(
+            absolute_coord,
+            absolute_depth,
+            rgb24,
+            alpha,
+            blend,
+        );
+    }
+    fn set_cursor_absolute(&mut self, absolute_cursor: Cursor) {
+        self.buffer.set_cursor_absolute(absolute_cursor);
+    }
+    fn hyperlink(&mut self, target: &str) -> Hyperlink {
+        self.buffer.hyperlink(target)
+    }
+    fn draw_image_absolute(&mut self, absolute_placement: ImagePlacement) {
+        self.buffer.draw_image_absolute(absolute_placement);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chargrid_render::{blend_mode, ColModifyIdentity, Style, ViewContext};
+
+    #[test]
+    fn styles_and_blending() {
+        let mut test_grid = TestGrid::new(Size::new(3, 2));
+        let context = ViewContext::default_with_size(test_grid.size());
+        let style = Style::new()
+            .with_foreground(Rgb24::new(255, 0, 0))
+            .with_background(Rgb24::new(0, 0, 255))
+            .with_bold(true);
+        for (i, ch) in "abc".chars().enumerate() {
+            let view_cell = ViewCell::new().with_character(ch).with_style(style);
+            test_grid.set_cell_relative(Coord::new(i as i32, 0), 0, view_cell, context);
+        }
+        test_grid.set_cell_relative(
+            Coord::new(1, 1),
+            0,
+            ViewCell::new().with_character('d').with_underline(true),
+            context,
+        );
+        test_grid.blend_cell_background_relative::<ColModifyIdentity, _>(
+            Coord::new(2, 0),
+            1,
+            Rgb24::new(0, 255, 0),
+            128,
+            blend_mode::LinearInterpolate,
+            context,
+        );
+        assert_eq!(
+            test_grid.get(Coord::new(2, 0)).unwrap().background_colour,
+            Rgb24::new(0, 0, 255).linear_interpolate(Rgb24::new(0, 255, 0), 128),
+        );
+        test_grid.assert_snapshot(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/snapshots/styles_and_blending.txt"
+        ));
     }
 }
@@ -0,0 +1,168 @@
+//! Golden-file snapshots of rendered buffers.
+//!
+//! A snapshot is a text file with three sections. The "characters" section contains the
+//! character in each cell, and the "styles" section contains a key for the style of each cell.
+//! Each row of these sections is surrounded by `|` so trailing whitespace is preserved. The
+//! "legend" section maps each style key to a description of the style.
+//!
+//! ```text
+//! characters:
+//! |abc|
+//! | d |
+//! styles:
+//! |aab|
+//! |cdc|
+//! legend:
+//! a: fg=#ff0000 bg=#0000ff bold
+//! ...
+//! ```
+use chargrid_render::{Buffer, BufferCell, Rgb24, UnderlineKind};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Set this environment variable to any value to (over)write golden files with the actual
+/// contents rather than comparing against them.
+pub const UPDATE_ENV_VAR: &str = "CHARGRID_UPDATE_SNAPSHOTS";
+
+const ROW_DELIMITER: char = '|';
+
+fn style_key(index: usize) -> char {
+    const KEYS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    KEYS.chars().nth(index).unwrap_or_else(|| {
+        // fall back to characters from the latin extended blocks once ascii is exhausted
+        std::char::from_u32(0x100 + (index - KEYS.len()) as u32).expect("too many styles")
+    })
+}
+
+fn write_rgb24(string: &mut String, Rgb24 { r, g, b }: Rgb24) {
+    write!(string, "#{:02x}{:02x}{:02x}", r, g, b).unwrap();
+}
+
+fn describe_style(cell: &BufferCell) -> String {
+    let mut description = String::new();
+    description.push_str("fg=");
+    write_rgb24(&mut description, cell.foreground_colour);
+    description.push_str(" bg=");
+    write_rgb24(&mut description, cell.background_colour);
+    if cell.bold {
+        description.push_str(" bold");
+    }
+    if cell.underline {
+        description.push_str(" underline");
+        match cell.underline_kind {
+            UnderlineKind::Single => (),
+            UnderlineKind::Double => description.push_str("=double"),
+            UnderlineKind::Curly => description.push_str("=curly"),
+            UnderlineKind::Dotted => description.push_str("=dotted"),
+            UnderlineKind::Dashed => description.push_str("=dashed"),
+        }
+        if let Some(underline_colour) = cell.underline_colour {
+            description.push_str(" underline_colour=");
+            write_rgb24(&mut description, underline_colour);
+        }
+    }
+    if cell.italic {
+        description.push_str(" italic");
+    }
+    if cell.strikethrough {
+        description.push_str(" strikethrough");
+    }
+    if cell.dim {
+        description.push_str(" dim");
+    }
+    if cell.reverse {
+        description.push_str(" reverse");
+    }
+    if cell.blink {
+        description.push_str(" blink");
+    }
+    description
+}
+
+/// Render the contents of a buffer in the snapshot format.
+pub fn render(buffer: &Buffer) -> String {
+    let mut styles: Vec<String> = Vec::new();
+    let mut characters_section = String::new();
+    let mut styles_section = String::new();
+    for row in buffer.rows() {
+        characters_section.push(ROW_DELIMITER);
+        styles_section.push(ROW_DELIMITER);
+        for cell in row {
+            characters_section.push_str(cell.character.as_str());
+            let description = describe_style(cell);
+            let index = match styles.iter().position(|s| *s == description) {
+                Some(index) => index,
+                None => {
+                    styles.push(description);
+                    styles.len() - 1
+                }
+            };
+            styles_section.push(style_key(index));
+        }
+        characters_section.push(ROW_DELIMITER);
+        characters_section.push('\n');
+        styles_section.push(ROW_DELIMITER);
+        styles_section.push('\n');
+    }
+    let mut snapshot = String::new();
+    snapshot.push_str("characters:\n");
+    snapshot.push_str(&characters_section);
+    snapshot.push_str("styles:\n");
+    snapshot.push_str(&styles_section);
+    snapshot.push_str("legend:\n");
+    for (index, description) in styles.iter().enumerate() {
+        writeln!(snapshot, "{}: {}", style_key(index), description).unwrap();
+    }
+    snapshot
+}
+
+/// Describe the lines which differ between two snapshots.
+pub fn diff(expected: &str, actual: &str) -> String {
+    let expected_lines = expected.lines().collect::<Vec<_>>();
+    let actual_lines = actual.lines().collect::<Vec<_>>();
+    let num_lines = expected_lines.len().max(actual_lines.len());
+    let mut diff = String::new();
+    for i in 0..num_lines {
+        let expected_line = expected_lines.get(i);
+        let actual_line = actual_lines.get(i);
+        if expected_line != actual_line {
+            writeln!(diff, "line {}:", i + 1).unwrap();
+            writeln!(diff, "- {}", expected_line.unwrap_or(&"<missing>")).unwrap();
+            writeln!(diff, "+ {}", actual_line.unwrap_or(&"<missing>")).unwrap();
+        }
+    }
+    diff
+}
+
+/// Compare the contents of a buffer against the golden file at `path`, panicking with a diff if
+/// they differ. If the `CHARGRID_UPDATE_SNAPSHOTS` environment variable is set, the golden file
+/// is written instead.
+pub fn assert_matches<P: AsRef<Path>>(buffer: &Buffer, path: P) {
+    let path = path.as_ref();
+    let actual = render(buffer);
+    if std::env::var_os(UPDATE_ENV_VAR).is_some() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(path, actual).unwrap();
+        return;
+    }
+    let expected = match fs::read_to_string(path) {
+        Ok(expected) => expected,
+        Err(error) => panic!(
+            "failed to read snapshot {} ({}), rerun with {} set to create it",
+            path.display(),
+            error,
+            UPDATE_ENV_VAR
+        ),
+    };
+    if expected != actual {
+        panic!(
+            "snapshot {} does not match, rerun with {} set to update it\n{}",
+            path.display(),
+            UPDATE_ENV_VAR,
+            diff(&expected, &actual)
+        );
+    }
+}
@@ -59,9 +59,9 @@ where
     fn view<F: Frame, C: ColModify>(&mut self, parts: I, context: ViewContext<C>, frame: &mut F) {
         self.wrap.clear();
         for part in parts {
-            for character in part.text.chars() {
+            for grapheme in Grapheme::iter_str(part.text) {
                 self.wrap
-                    .process_character(character, part.style, context, frame);
+                    .process_grapheme(grapheme, part.style, context, frame);
             }
         }
         self.wrap.flush(context, frame);
@@ -108,9 +108,9 @@ where
     ) {
         self.wrap.clear();
         let part: RichTextPart = part.into();
-        for character in part.text.chars() {
+        for grapheme in Grapheme::iter_str(part.text) {
             self.wrap
-                .process_character(character, part.style, context, frame);
+                .process_grapheme(grapheme, part.style, context, frame);
         }
         self.wrap.flush(context, frame);
     }
@@ -135,3 +135,26 @@ impl<'a> View<RichTextPart<'a>> for RichStringViewSingleLine {
         RichStringView::new(wrap::None::new()).view(part, context, frame);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hyperlinks() {
+        let mut test_grid = chargrid_test_grid::TestGrid::new(Size::new(8, 1));
+        let link = test_grid.hyperlink("https://example.com");
+        let parts = [
+            RichTextPart::new("see ", Style::new()),
+            RichTextPart::new("here", Style::new().with_hyperlink(link)),
+        ];
+        let context = ViewContext::default_with_size(Size::new(8, 1));
+        RichTextViewSingleLine.view(parts.iter().cloned(), context, &mut test_grid);
+        assert_eq!(test_grid.string_rows(), &["see here".to_string()]);
+        let links = (0..8)
+            .map(|x| test_grid.get(Coord::new(x, 0)).unwrap().hyperlink)
+            .collect::<Vec<_>>();
+        assert_eq!(&links[..4], &[None; 4]);
+        assert_eq!(&links[4..], &[Some(link); 4]);
+    }
+}
@@ -26,9 +26,9 @@ where
         self.wrap.clear();
         for part in parts {
             let part = part.as_ref();
-            for character in part.chars() {
+            for grapheme in Grapheme::iter_str(part) {
                 self.wrap
-                    .process_character(character, self.style, context, frame);
+                    .process_grapheme(grapheme, self.style, context, frame);
             }
         }
         self.wrap.flush(context, frame);
@@ -57,9 +57,9 @@ where
     fn view<F: Frame, C: ColModify>(&mut self, part: S, context: ViewContext<C>, frame: &mut F) {
         self.wrap.clear();
         let part = part.as_ref();
-        for character in part.chars() {
+        for grapheme in Grapheme::iter_str(part) {
             self.wrap
-                .process_character(character, self.style, context, frame);
+                .process_grapheme(grapheme, self.style, context, frame);
         }
         self.wrap.flush(context, frame);
     }
@@ -130,4 +130,29 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn wrap_double_width_graphemes() {
+        let context = ViewContext::default_with_size(Size::new(5, 3));
+        let text = &["\u{4f60}\u{597d} e\u{301}\u{4e16}\u{754c}"];
+        let mut test_grid = chargrid_test_grid::TestGrid::new(Size::new(5, 2));
+        let mut text_view = TextView::new_default_style(wrap::Word::new());
+        text_view.view(text, context, &mut test_grid);
+        assert_eq!(
+            test_grid.string_rows(),
+            &["\u{4f60}\u{597d} ", "e\u{301}\u{4e16}\u{754c}"]
+        );
+        let mut test_grid = chargrid_test_grid::TestGrid::new(Size::new(5, 2));
+        let mut text_view = TextView::new_default_style(wrap::Char::new());
+        text_view.view(text, context, &mut test_grid);
+        assert_eq!(
+            test_grid.string_rows(),
+            &["\u{4f60}\u{597d} ", "e\u{301}\u{4e16}\u{754c}"]
+        );
+        let mut test_grid = chargrid_test_grid::TestGrid::new(Size::new(3, 2));
+        let context = ViewContext::default_with_size(Size::new(3, 2));
+        let mut text_view = TextView::new_default_style(wrap::Char::new());
+        text_view.view(&["a\u{4f60}\u{597d}"], context, &mut test_grid);
+        assert_eq!(test_grid.string_rows(), &["a\u{4f60}", "\u{597d} "]);
+    }
 }
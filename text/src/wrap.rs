@@ -6,9 +6,9 @@ pub trait Wrap: private_wrap::Sealed {
     #[doc(hidden)]
     fn clear(&mut self);
     #[doc(hidden)]
-    fn process_character<F: Frame, C: ColModify>(
+    fn process_grapheme<F: Frame, C: ColModify>(
         &mut self,
-        character: char,
+        grapheme: Grapheme,
         style: Style,
         context: ViewContext<C>,
         frame: &mut F,
@@ -33,6 +33,7 @@ pub struct None {
 pub struct Word {
     cursor: Coord,
     current_word_buffer: Vec<ViewCell>,
+    current_word_width: u32,
 }
 
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
@@ -54,6 +55,7 @@ impl Word {
         Self {
             cursor: Coord::new(0, 0),
             current_word_buffer: Vec::new(),
+            current_word_width: 0,
         }
     }
 }
@@ -88,26 +90,26 @@ impl Wrap for None {
     fn clear(&mut self) {
         self.cursor = Coord::new(0, 0);
     }
-    fn process_character<F: Frame, C: ColModify>(
+    fn process_grapheme<F: Frame, C: ColModify>(
         &mut self,
-        character: char,
+        grapheme: Grapheme,
         style: Style,
         context: ViewContext<C>,
         frame: &mut F,
     ) {
-        match character {
-            '\n' => {
+        match grapheme.as_str() {
+            "\n" | "\r\n" => {
                 self.cursor.x = 0;
                 self.cursor.y += 1;
             }
-            '\r' => self.cursor.x = 0,
-            other => {
+            "\r" => self.cursor.x = 0,
+            _ => {
                 let view_cell = ViewCell {
-                    character: Some(other),
+                    character: Some(grapheme),
                     style,
                 };
                 frame.set_cell_relative(self.cursor, 0, view_cell, context);
-                self.cursor += Coord::new(1, 0);
+                self.cursor.x += grapheme.width() as i32;
             }
         }
     }
@@ -120,11 +122,12 @@ impl Wrap for Word {
     fn clear(&mut self) {
         self.cursor = Coord::new(0, 0);
         self.current_word_buffer.clear();
+        self.current_word_width = 0;
     }
 
-    fn process_character<F: Frame, C: ColModify>(
+    fn process_grapheme<F: Frame, C: ColModify>(
         &mut self,
-        character: char,
+        grapheme: Grapheme,
         style: Style,
         context: ViewContext<C>,
         frame: &mut F,
@@ -132,21 +135,21 @@ impl Wrap for Word {
         if context.size.width() == 0 {
             return;
         }
-        match character {
-            '\n' => {
+        match grapheme.as_str() {
+            "\n" | "\r\n" => {
                 self.flush(context, frame);
                 self.cursor.x = 0;
                 self.cursor.y += 1;
             }
-            '\r' => {
+            "\r" => {
                 self.flush(context, frame);
                 self.cursor.x = 0;
             }
-            ' ' => {
+            " " => {
                 self.flush(context, frame);
                 if self.cursor.x != 0 {
                     let view_cell = ViewCell {
-                        character: Some(' '),
+                        character: Some(Grapheme::from_char(' ')),
                         style,
                     };
                     frame.set_cell_relative(self.cursor, 0, view_cell, context);
@@ -158,19 +161,27 @@ impl Wrap for Word {
                     }
                 }
             }
-            other => {
+            _ => {
+                let grapheme_width = grapheme.width();
+                if self.cursor.x as u32 + self.current_word_width + grapheme_width
+                    > context.size.width()
+                {
+                    // a double-width grapheme would overhang the end of the line
+                    if self.cursor.x == 0 {
+                        self.flush(context, frame);
+                    }
+                    if self.cursor.x != 0 {
+                        self.cursor.x = 0;
+                        self.cursor.y += 1;
+                    }
+                }
                 let view_cell = ViewCell {
-                    character: Some(other),
+                    character: Some(grapheme),
                     style,
                 };
                 self.current_word_buffer.push(view_cell);
-                assert!(
-                    self.cursor.x as u32 + self.current_word_buffer.len() as u32
-                        <= context.size.width()
-                );
-                if self.cursor.x as u32 + self.current_word_buffer.len() as u32
-                    == context.size.width()
-                {
+                self.current_word_width += grapheme_width;
+                if self.cursor.x as u32 + self.current_word_width >= context.size.width() {
                     if self.cursor.x == 0 {
                         self.flush(context, frame);
                     } else {
@@ -183,16 +194,17 @@ impl Wrap for Word {
     }
 
     fn flush<F: Frame, C: ColModify>(&mut self, context: ViewContext<C>, frame: &mut F) {
+        self.current_word_width = 0;
         if context.size.width() == 0 {
             self.current_word_buffer.clear();
             return;
         }
         for view_cell in self.current_word_buffer.drain(..) {
             frame.set_cell_relative(self.cursor, 0, view_cell, context);
-            self.cursor.x += 1;
+            self.cursor.x += view_cell.character.map_or(1, |g| g.width()) as i32;
         }
-        assert!(self.cursor.x as u32 <= context.size.width());
-        if self.cursor.x as u32 == context.size.width() {
+        // a double-width grapheme in a single-column context can overhang the end of the line
+        if self.cursor.x as u32 >= context.size.width() {
             self.cursor.x = 0;
             self.cursor.y += 1;
         }
@@ -208,26 +220,32 @@ impl Wrap for Char {
         self.cursor = Coord::new(0, 0);
     }
 
-    fn process_character<F: Frame, C: ColModify>(
+    fn process_grapheme<F: Frame, C: ColModify>(
         &mut self,
-        character: char,
+        grapheme: Grapheme,
         style: Style,
         context: ViewContext<C>,
         frame: &mut F,
     ) {
-        match character {
-            '\n' => {
+        match grapheme.as_str() {
+            "\n" | "\r\n" => {
                 self.cursor.x = 0;
                 self.cursor.y += 1;
             }
-            '\r' => self.cursor.x = 0,
-            other => {
+            "\r" => self.cursor.x = 0,
+            _ => {
+                let grapheme_width = grapheme.width() as i32;
+                if self.cursor.x > 0 && self.cursor.x + grapheme_width > context.size.width() as i32
+                {
+                    self.cursor.x = 0;
+                    self.cursor.y += 1;
+                }
                 let view_cell = ViewCell {
-                    character: Some(other),
+                    character: Some(grapheme),
                     style,
                 };
                 frame.set_cell_relative(self.cursor, 0, view_cell, context);
-                self.cursor += Coord::new(1, 0);
+                self.cursor.x += grapheme_width;
                 if self.cursor.x >= context.size.width() as i32 {
                     self.cursor.x = 0;
                     self.cursor.y += 1;
@@ -102,6 +102,7 @@ fn keyboard_input_from_js_event_key_press(key_code: u8, shift: bool) -> Option<K
     Some(keyboard_input)
 }
 
-pub fn from_js_event_key_press(key_code: u8, shift: bool) -> Option<Input> {
-    keyboard_input_from_js_event_key_press(key_code, shift).map(Input::Keyboard)
+pub fn from_js_event_key_press(key_code: u8, modifiers: KeyModifiers) -> Option<Input> {
+    keyboard_input_from_js_event_key_press(key_code, modifiers.shift())
+        .map(|key| Input::keyboard_with_modifiers(key, modifiers))
 }
@@ -1,13 +1,17 @@
 mod input;
 
-use chargrid_app::App;
+use chargrid_app::{App, Clipboard, ControlFlow};
 #[cfg(feature = "gamepad")]
 use chargrid_gamepad::GamepadContext;
+use chargrid_graphical_common::visible_cursor;
 pub use chargrid_input;
 pub use chargrid_input::{Input, MouseInput};
-use chargrid_input::{MouseButton, ScrollDirection};
+use chargrid_input::{KeyModifiers, MouseButton, ScrollDirection};
 pub use chargrid_render;
-use chargrid_render::{Buffer, Rgb24, ViewContext};
+use chargrid_render::{
+    Buffer, ColModify, CursorShape, Frame, Grapheme, Hyperlink, ImagePlacement, Rgb24,
+    UnderlineKind, ViewContext,
+};
 use grid_2d::Coord;
 pub use grid_2d::Size;
 use js_sys::Function;
@@ -15,8 +19,38 @@ use std::cell::RefCell;
 use std::rc::Rc;
 pub use std::time::Duration;
 use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsCast;
-use web_sys::{Element, HtmlElement, KeyboardEvent, MouseEvent, Node, WheelEvent};
+use wasm_bindgen::{Clamped, JsCast};
+use web_sys::{
+    CanvasRenderingContext2d, DataTransfer, Element, HtmlCanvasElement, HtmlElement, ImageData,
+    KeyboardEvent, MouseEvent, Node, WheelEvent,
+};
+
+const BLINK_STYLE: &str = "@keyframes chargrid-blink { 50% { color: transparent; } }";
+const BLINK_ANIMATION: &str = "chargrid-blink 1s step-end infinite";
+// bar and underline cursors are drawn as inset box shadows with these offsets
+const CURSOR_BAR_OFFSET: &str = "0.15em 0";
+const CURSOR_UNDERLINE_OFFSET: &str = "0 -0.15em";
+
+/// Writes to the clipboard with `navigator.clipboard.writeText`, if the browser supports it.
+/// Browsers only allow this shortly after the user interacts with the page.
+fn write_clipboard(text: &str) {
+    let window = web_sys::window().unwrap();
+    let write_text = js_sys::Reflect::get(&window, &"navigator".into())
+        .and_then(|navigator| js_sys::Reflect::get(&navigator, &"clipboard".into()))
+        .and_then(|clipboard| {
+            js_sys::Reflect::get(&clipboard, &"writeText".into())
+                .map(|write_text| (clipboard, write_text))
+        });
+    if let Ok((clipboard, write_text)) = write_text {
+        if let Some(write_text) = write_text.dyn_ref::<Function>() {
+            let _ = write_text.call1(&clipboard, &text.into());
+        }
+    }
+}
+
+fn js_error(value: JsValue) -> chargrid_app::Error {
+    chargrid_app::Error::frontend(value.as_string().unwrap_or_else(|| format!("{:?}", value)))
+}
 
 fn rgb24_to_web_colour(Rgb24 { r, g, b }: Rgb24) -> String {
     format!("rgb({},{},{})", r, g, b)
@@ -24,11 +58,19 @@ fn rgb24_to_web_colour(Rgb24 { r, g, b }: Rgb24) -> String {
 
 struct ElementCell {
     element: HtmlElement,
-    character: char,
+    character: Grapheme,
     bold: bool,
     underline: bool,
+    underline_kind: UnderlineKind,
+    underline_colour: Option<Rgb24>,
+    italic: bool,
+    strikethrough: bool,
+    blink: bool,
     foreground_colour: Rgb24,
     background_colour: Rgb24,
+    /// The shape and colour of a bar or underline cursor drawn over the cell
+    cursor: Option<(CursorShape, Rgb24)>,
+    hyperlink: Option<Hyperlink>,
 }
 
 impl ElementCell {
@@ -41,17 +83,62 @@ impl ElementCell {
         element_style
             .set_property("background-color", "rgb(0,0,0)")
             .unwrap();
+        // cells are anchors so they can link somewhere, but they're only underlined on request
+        element_style
+            .set_property("text-decoration-line", "none")
+            .unwrap();
         Self {
             element,
-            character: ' ',
+            character: Grapheme::from_char(' '),
             bold: false,
             underline: false,
+            underline_kind: UnderlineKind::Single,
+            underline_colour: None,
+            italic: false,
+            strikethrough: false,
+            blink: false,
             foreground_colour: Rgb24::new_grey(0),
             background_colour: Rgb24::new_grey(0),
+            cursor: None,
+            hyperlink: None,
         }
     }
 }
 
+/// An image drawn on a canvas positioned over the cells it covers
+struct ElementImage {
+    placement: ImagePlacement,
+    canvas: HtmlCanvasElement,
+}
+
+impl ElementImage {
+    fn new(placement: ImagePlacement) -> Result<Self, JsValue> {
+        let document = web_sys::window().unwrap().document().unwrap();
+        let canvas = document
+            .create_element("canvas")?
+            .dyn_into::<HtmlCanvasElement>()?;
+        let image = &placement.image;
+        canvas.set_width(image.size().width());
+        canvas.set_height(image.size().height());
+        let canvas_context = canvas
+            .get_context("2d")?
+            .ok_or("no 2d context")?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+        let image_data = ImageData::new_with_u8_clamped_array_and_sh(
+            Clamped(image.rgba_bytes()),
+            image.size().width(),
+            image.size().height(),
+        )?;
+        canvas_context.put_image_data(&image_data, 0., 0.)?;
+        let canvas_style = canvas.style();
+        canvas_style.set_property("position", "absolute")?;
+        canvas_style.set_property("image-rendering", "pixelated")?;
+        // clicks go to the cells beneath the image
+        canvas_style.set_property("pointer-events", "none")?;
+        Ok(Self { placement, canvas })
+    }
+}
+
 #[derive(Debug)]
 struct ElementDisplayInfo {
     container_x: f64,
@@ -72,6 +159,8 @@ pub struct Context {
     element_grid: grid_2d::Grid<ElementCell>,
     buffer: Buffer,
     container_element: Element,
+    images: Vec<ElementImage>,
+    clipboard: Clipboard,
     #[cfg(feature = "gamepad")]
     gamepad: GamepadContext,
 }
@@ -106,9 +195,16 @@ impl Context {
             .unwrap()
             .dyn_into::<Node>()
             .unwrap();
+        let blink_style_element = document.create_element("style").unwrap();
+        blink_style_element.set_inner_html(BLINK_STYLE);
+        document
+            .head()
+            .unwrap()
+            .append_child(&blink_style_element)
+            .unwrap();
         let element_grid = grid_2d::Grid::new_fn(size, |_| {
             let element = document
-                .create_element("span")
+                .create_element("a")
                 .unwrap()
                 .dyn_into::<HtmlElement>()
                 .unwrap();
@@ -135,52 +231,203 @@ impl Context {
                 .unwrap();
         }
         let buffer = Buffer::new(size);
+        let container_element = document.get_element_by_id(container).unwrap();
+        if let Some(container_element) = container_element.dyn_ref::<HtmlElement>() {
+            // images are positioned relative to the container
+            let container_style = container_element.style();
+            if container_style
+                .get_property_value("position")
+                .unwrap()
+                .is_empty()
+            {
+                container_style
+                    .set_property("position", "relative")
+                    .unwrap();
+            }
+        }
         Self {
             element_grid,
             buffer,
-            container_element: document.get_element_by_id(container).unwrap(),
+            container_element,
+            images: Vec::new(),
+            clipboard: Clipboard::new(),
             #[cfg(feature = "gamepad")]
             gamepad: GamepadContext::new(),
         }
     }
 
-    fn render_internal(&mut self) {
-        for (chargrid_cell, element_cell) in self.buffer.iter().zip(self.element_grid.iter_mut()) {
+    /// Add canvases for new images and remove those of images which are no longer drawn
+    fn render_images(&mut self) {
+        let placements = self.buffer.images();
+        self.images.retain(|image| {
+            let keep = placements.contains(&image.placement);
+            if !keep {
+                image.canvas.remove();
+            }
+            keep
+        });
+        for placement in placements {
+            if self
+                .images
+                .iter()
+                .any(|image| image.placement == *placement)
+            {
+                continue;
+            }
+            let image = match ElementImage::new(placement.clone()) {
+                Ok(image) => image,
+                Err(error) => {
+                    web_sys::console::error_1(&error);
+                    continue;
+                }
+            };
+            self.container_element.append_child(&image.canvas).unwrap();
+            self.images.push(image);
+        }
+        // cells are laid out by the browser, so the canvases are placed to match the cells
+        for image in &self.images {
+            let placement = &image.placement;
+            let bottom_right =
+                placement.coord + placement.size.to_coord().unwrap() - Coord::new(1, 1);
+            let top_left_cell = &self.element_grid.get_checked(placement.coord).element;
+            let bottom_right_cell = &self.element_grid.get_checked(bottom_right).element;
+            let left = top_left_cell.offset_left();
+            let top = top_left_cell.offset_top();
+            let width = bottom_right_cell.offset_left() + bottom_right_cell.offset_width() - left;
+            let height = bottom_right_cell.offset_top() + bottom_right_cell.offset_height() - top;
+            let canvas_style = image.canvas.style();
+            for &(property, value) in &[
+                ("left", left),
+                ("top", top),
+                ("width", width),
+                ("height", height),
+            ] {
+                canvas_style
+                    .set_property(property, &format!("{}px", value))
+                    .unwrap();
+            }
+        }
+    }
+
+    fn render_internal(&mut self, since_start: Duration) {
+        self.render_images();
+        let cursor = visible_cursor(&self.buffer, since_start);
+        for ((coord, chargrid_cell), element_cell) in
+            self.buffer.enumerate().zip(self.element_grid.iter_mut())
+        {
+            let cursor_shape = cursor
+                .filter(|cursor| cursor.coord == coord)
+                .map(|cursor| cursor.shape);
             if element_cell.character != chargrid_cell.character {
                 element_cell.character = chargrid_cell.character;
-                let string = match chargrid_cell.character {
-                    ' ' => "&nbsp;".to_string(),
-                    other => other.to_string(),
+                let string = match chargrid_cell.character.as_str() {
+                    " " => "&nbsp;",
+                    other => other,
                 };
-                element_cell.element.set_inner_html(&string);
+                element_cell.element.set_inner_html(string);
             }
             let element_style = element_cell.element.style();
-            if element_cell.foreground_colour != chargrid_cell.foreground_colour {
-                element_cell.foreground_colour = chargrid_cell.foreground_colour;
+            let (foreground_colour, background_colour) = match cursor_shape {
+                Some(CursorShape::Block) => {
+                    let (foreground_colour, background_colour) = chargrid_cell.display_colours();
+                    (background_colour, foreground_colour)
+                }
+                _ => chargrid_cell.display_colours(),
+            };
+            if element_cell.foreground_colour != foreground_colour {
+                element_cell.foreground_colour = foreground_colour;
                 element_style
-                    .set_property(
-                        "color",
-                        &rgb24_to_web_colour(chargrid_cell.foreground_colour),
-                    )
+                    .set_property("color", &rgb24_to_web_colour(foreground_colour))
                     .unwrap();
             }
-            if element_cell.background_colour != chargrid_cell.background_colour {
-                element_cell.background_colour = chargrid_cell.background_colour;
+            if element_cell.background_colour != background_colour {
+                element_cell.background_colour = background_colour;
                 element_style
-                    .set_property(
-                        "background-color",
-                        &rgb24_to_web_colour(chargrid_cell.background_colour),
-                    )
+                    .set_property("background-color", &rgb24_to_web_colour(background_colour))
                     .unwrap();
             }
-            if element_cell.underline != chargrid_cell.underline {
+            let cursor = match cursor_shape {
+                Some(CursorShape::Block) | None => None,
+                Some(shape) => Some((shape, foreground_colour)),
+            };
+            if element_cell.cursor != cursor {
+                element_cell.cursor = cursor;
+                match cursor {
+                    Some((shape, colour)) => {
+                        let offset = match shape {
+                            CursorShape::Underline => CURSOR_UNDERLINE_OFFSET,
+                            _ => CURSOR_BAR_OFFSET,
+                        };
+                        let box_shadow =
+                            format!("inset {} 0 {}", offset, rgb24_to_web_colour(colour));
+                        element_style
+                            .set_property("box-shadow", &box_shadow)
+                            .unwrap();
+                    }
+                    None => {
+                        element_style.remove_property("box-shadow").unwrap();
+                    }
+                }
+            }
+            if element_cell.underline != chargrid_cell.underline
+                || element_cell.strikethrough != chargrid_cell.strikethrough
+            {
                 element_cell.underline = chargrid_cell.underline;
-                if chargrid_cell.underline {
+                element_cell.strikethrough = chargrid_cell.strikethrough;
+                let text_decoration = match (chargrid_cell.underline, chargrid_cell.strikethrough) {
+                    (false, false) => "none",
+                    (true, false) => "underline",
+                    (false, true) => "line-through",
+                    (true, true) => "underline line-through",
+                };
+                element_style
+                    .set_property("text-decoration-line", text_decoration)
+                    .unwrap();
+            }
+            // css applies the same style and colour to underlines and strikethroughs
+            if element_cell.underline_kind != chargrid_cell.underline_kind {
+                element_cell.underline_kind = chargrid_cell.underline_kind;
+                let text_decoration_style = match chargrid_cell.underline_kind {
+                    UnderlineKind::Single => "solid",
+                    UnderlineKind::Double => "double",
+                    UnderlineKind::Curly => "wavy",
+                    UnderlineKind::Dotted => "dotted",
+                    UnderlineKind::Dashed => "dashed",
+                };
+                element_style
+                    .set_property("text-decoration-style", text_decoration_style)
+                    .unwrap();
+            }
+            if element_cell.underline_colour != chargrid_cell.underline_colour {
+                element_cell.underline_colour = chargrid_cell.underline_colour;
+                if let Some(underline_colour) = chargrid_cell.underline_colour {
                     element_style
-                        .set_property("text-decoration", "underline")
+                        .set_property(
+                            "text-decoration-color",
+                            &rgb24_to_web_colour(underline_colour),
+                        )
                         .unwrap();
                 } else {
-                    element_style.remove_property("text-decoration").unwrap();
+                    element_style
+                        .remove_property("text-decoration-color")
+                        .unwrap();
+                }
+            }
+            if element_cell.hyperlink != chargrid_cell.hyperlink {
+                element_cell.hyperlink = chargrid_cell.hyperlink;
+                let element = &element_cell.element;
+                let buffer = &self.buffer;
+                if let Some(target) = chargrid_cell
+                    .hyperlink
+                    .and_then(|hyperlink| buffer.hyperlink_target(hyperlink))
+                {
+                    element.set_attribute("href", target).unwrap();
+                    element.set_attribute("target", "_blank").unwrap();
+                    element.set_attribute("rel", "noopener").unwrap();
+                } else {
+                    element.remove_attribute("href").unwrap();
+                    element.remove_attribute("target").unwrap();
+                    element.remove_attribute("rel").unwrap();
                 }
             }
             if element_cell.bold != chargrid_cell.bold {
@@ -191,39 +438,130 @@ impl Context {
                     element_style.remove_property("font-weight").unwrap();
                 }
             }
+            if element_cell.italic != chargrid_cell.italic {
+                element_cell.italic = chargrid_cell.italic;
+                if chargrid_cell.italic {
+                    element_style.set_property("font-style", "italic").unwrap();
+                } else {
+                    element_style.remove_property("font-style").unwrap();
+                }
+            }
+            if element_cell.blink != chargrid_cell.blink {
+                element_cell.blink = chargrid_cell.blink;
+                if chargrid_cell.blink {
+                    element_style
+                        .set_property("animation", BLINK_ANIMATION)
+                        .unwrap();
+                } else {
+                    element_style.remove_property("animation").unwrap();
+                }
+            }
         }
     }
 
+    /// Returns a handle for writing to the system clipboard, which can be passed to an app
+    pub fn clipboard(&self) -> Clipboard {
+        self.clipboard.clone()
+    }
+
+    /// Start running the app. Panics if the app can't be started. See `try_run_app` for a version
+    /// which returns an error instead.
     pub fn run_app<A>(self, app: A)
     where
         A: App + 'static,
     {
-        let app = Rc::new(RefCell::new(app));
+        if let Err(error) = self.try_run_app(app) {
+            panic!("{}", error);
+        }
+    }
+
+    /// Start running the app, returning an error if it can't be started. This returns as soon as
+    /// the app is running, so if the app later exits with `ControlFlow::ExitWithError`, the error
+    /// is written to the browser console.
+    pub fn try_run_app<A>(self, app: A) -> chargrid_app::Result<()>
+    where
+        A: App + 'static,
+    {
+        let app = Rc::new(RefCell::new(RunningApp { app, exited: false }));
         let context = Rc::new(RefCell::new(self));
-        run_app_frame(app.clone(), context.clone());
-        run_app_input(app, context);
+        run_app_frame(app.clone(), context.clone()).map_err(js_error)?;
+        run_app_input(app, context).map_err(js_error)
     }
 }
 
-fn run_app_frame<A: App + 'static>(app: Rc<RefCell<A>>, context: Rc<RefCell<Context>>) {
-    let window = web_sys::window().unwrap();
-    let performance = window.performance().unwrap();
+/// An app which stops receiving input and frames once it exits
+struct RunningApp<A: App> {
+    app: A,
+    exited: bool,
+}
+
+impl<A: App> RunningApp<A> {
+    fn exit(&mut self, control_flow: ControlFlow) {
+        self.exited = true;
+        if let Err(error) = control_flow.into_result() {
+            web_sys::console::error_1(&error.to_string().into());
+        }
+    }
+
+    fn on_input(&mut self, input: Input) {
+        if self.exited {
+            return;
+        }
+        if let Some(control_flow) = self.app.on_input(input) {
+            self.exit(control_flow);
+        }
+    }
+
+    fn on_frame<F, C>(
+        &mut self,
+        since_last_frame: Duration,
+        view_context: ViewContext<C>,
+        frame: &mut F,
+    ) where
+        F: Frame,
+        C: ColModify,
+    {
+        if self.exited {
+            return;
+        }
+        if let Some(control_flow) = self.app.on_frame(since_last_frame, view_context, frame) {
+            self.exit(control_flow);
+        }
+    }
+}
+
+fn run_app_frame<A: App + 'static>(
+    app: Rc<RefCell<RunningApp<A>>>,
+    context: Rc<RefCell<Context>>,
+) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or("no window")?;
+    let performance = window.performance().ok_or("no performance")?;
     let f: Rc<RefCell<Option<Closure<_>>>> = Rc::new(RefCell::new(None));
     let g = f.clone();
-    let mut last_frame_time_stamp = performance.now();
+    let start_time_stamp = performance.now();
+    let mut last_frame_time_stamp = start_time_stamp;
     *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
         let frame_time_stamp = performance.now();
         let since_last_frame = frame_time_stamp - last_frame_time_stamp;
         last_frame_time_stamp = frame_time_stamp;
         let mut context = context.borrow_mut();
+        let mut app = app.borrow_mut();
         context.buffer.clear();
         let view_context = ViewContext::default_with_size(context.buffer.size());
-        app.borrow_mut().on_frame(
+        app.on_frame(
             Duration::from_millis(since_last_frame as u64),
             view_context,
             &mut context.buffer,
         );
-        context.render_internal();
+        if app.exited {
+            // stop requesting frames
+            return;
+        }
+        let since_start = frame_time_stamp - start_time_stamp;
+        context.render_internal(Duration::from_millis(since_start as u64));
+        if let Some(text) = context.clipboard.take_text() {
+            write_clipboard(&text);
+        }
         window
             .request_animation_frame(f.borrow().as_ref().unwrap().as_ref().unchecked_ref())
             .unwrap();
@@ -233,8 +571,8 @@ fn run_app_frame<A: App + 'static>(app: Rc<RefCell<A>>, context: Rc<RefCell<Cont
         .unwrap()
         .as_ref()
         .unchecked_ref::<Function>()
-        .call0(&JsValue::NULL)
-        .unwrap();
+        .call0(&JsValue::NULL)?;
+    Ok(())
 }
 
 mod buttons {
@@ -267,20 +605,62 @@ mod button {
     }
 }
 
-fn run_app_input<A: App + 'static>(app: Rc<RefCell<A>>, context: Rc<RefCell<Context>>) {
-    let window = web_sys::window().unwrap();
+fn run_app_input<A: App + 'static>(
+    app: Rc<RefCell<RunningApp<A>>>,
+    context: Rc<RefCell<Context>>,
+) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or("no window")?;
     let handle_keydown = {
         let app = app.clone();
         Closure::wrap(Box::new(move |event: JsValue| {
             let keyboard_event = event.unchecked_ref::<KeyboardEvent>();
-            if let Some(input) = input::from_js_event_key_press(
-                keyboard_event.key_code() as u8,
-                keyboard_event.shift_key(),
-            ) {
+            let mut modifiers = KeyModifiers::NONE;
+            if keyboard_event.shift_key() {
+                modifiers |= KeyModifiers::SHIFT;
+            }
+            if keyboard_event.alt_key() {
+                modifiers |= KeyModifiers::ALT;
+            }
+            if keyboard_event.ctrl_key() {
+                modifiers |= KeyModifiers::CTRL;
+            }
+            if keyboard_event.meta_key() {
+                modifiers |= KeyModifiers::SUPER;
+            }
+            if let Some(input) =
+                input::from_js_event_key_press(keyboard_event.key_code() as u8, modifiers)
+            {
                 app.borrow_mut().on_input(input);
             }
         }) as Box<dyn FnMut(JsValue)>)
     };
+    let handle_paste = {
+        let app = app.clone();
+        Closure::wrap(Box::new(move |event: JsValue| {
+            // ClipboardEvent is only exposed by web_sys as an unstable api
+            let data_transfer = js_sys::Reflect::get(&event, &"clipboardData".into());
+            if let Ok(data_transfer) = data_transfer {
+                if let Some(data_transfer) = data_transfer.dyn_ref::<DataTransfer>() {
+                    if let Ok(text) = data_transfer.get_data("text") {
+                        event.unchecked_ref::<web_sys::Event>().prevent_default();
+                        app.borrow_mut().on_input(Input::Paste(text));
+                    }
+                }
+            }
+        }) as Box<dyn FnMut(JsValue)>)
+    };
+    let handle_focus = {
+        let app = app.clone();
+        Closure::wrap(Box::new(move |_event: JsValue| {
+            app.borrow_mut().on_input(Input::FocusGained);
+        }) as Box<dyn FnMut(JsValue)>)
+    };
+    let handle_blur = {
+        let app = app.clone();
+        Closure::wrap(Box::new(move |_event: JsValue| {
+            app.borrow_mut().on_input(Input::FocusLost);
+        }) as Box<dyn FnMut(JsValue)>)
+    };
     let handle_mouse_move = {
         let app = app.clone();
         let context = context.clone();
@@ -390,24 +770,27 @@ fn run_app_input<A: App + 'static>(app: Rc<RefCell<A>>, context: Rc<RefCell<Cont
             }));
         }
     }) as Box<dyn FnMut(JsValue)>);
-    window
-        .add_event_listener_with_callback("keydown", handle_keydown.as_ref().unchecked_ref())
-        .unwrap();
-    window
-        .add_event_listener_with_callback("mousemove", handle_mouse_move.as_ref().unchecked_ref())
-        .unwrap();
-    window
-        .add_event_listener_with_callback("mousedown", handle_mouse_down.as_ref().unchecked_ref())
-        .unwrap();
-    window
-        .add_event_listener_with_callback("mouseup", handle_mouse_up.as_ref().unchecked_ref())
-        .unwrap();
-    window
-        .add_event_listener_with_callback("wheel", handle_wheel.as_ref().unchecked_ref())
-        .unwrap();
+    window.add_event_listener_with_callback("keydown", handle_keydown.as_ref().unchecked_ref())?;
+    window.add_event_listener_with_callback("paste", handle_paste.as_ref().unchecked_ref())?;
+    window.add_event_listener_with_callback("focus", handle_focus.as_ref().unchecked_ref())?;
+    window.add_event_listener_with_callback("blur", handle_blur.as_ref().unchecked_ref())?;
+    window.add_event_listener_with_callback(
+        "mousemove",
+        handle_mouse_move.as_ref().unchecked_ref(),
+    )?;
+    window.add_event_listener_with_callback(
+        "mousedown",
+        handle_mouse_down.as_ref().unchecked_ref(),
+    )?;
+    window.add_event_listener_with_callback("mouseup", handle_mouse_up.as_ref().unchecked_ref())?;
+    window.add_event_listener_with_callback("wheel", handle_wheel.as_ref().unchecked_ref())?;
     handle_keydown.forget();
+    handle_paste.forget();
+    handle_focus.forget();
+    handle_blur.forget();
     handle_mouse_move.forget();
     handle_mouse_down.forget();
     handle_mouse_up.forget();
     handle_wheel.forget();
+    Ok(())
 }
@@ -0,0 +1,50 @@
+// Draws images over rectangles of cells. Each instance is a single image, scaled to cover its
+// rectangle.
+
+[[block]]
+struct Globals {
+    cell_size_relative_to_window: vec2<f32>;
+    offset_to_centre: vec2<f32>;
+    grid_width: u32;
+    underline_width_cell_ratio: f32;
+    underline_top_offset_cell_ratio: f32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> globals: Globals;
+
+[[group(1), binding(0)]]
+var image_texture: texture_2d<f32>;
+
+[[group(1), binding(1)]]
+var image_sampler: sampler;
+
+struct VertexInput {
+    [[location(0)]] coord: vec2<f32>;
+    [[location(1)]] size: vec2<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] texture_coord: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(
+    [[builtin(vertex_index)]] vertex_index: u32,
+    in: VertexInput,
+) -> VertexOutput {
+    // The same corner selection as the cell shader, with a rectangle of cells in place of a cell.
+    let corner_offset = vec2<f32>(f32((22u >> vertex_index) & 1u), f32((52u >> vertex_index) & 1u));
+    let cell_size = globals.cell_size_relative_to_window;
+    let absolute = vec2<f32>(-1.0, -1.0) + ((in.coord + (corner_offset * in.size)) * cell_size) + globals.offset_to_centre;
+    var out: VertexOutput;
+    out.position = vec4<f32>(absolute.x, -absolute.y, 0.0, 1.0);
+    out.texture_coord = corner_offset;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return textureSample(image_texture, image_sampler, in.texture_coord);
+}
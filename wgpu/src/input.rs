@@ -1,7 +1,7 @@
 use crate::Dimensions;
 use chargrid_input::{
-    keys, Input, KeyboardInput, MouseButton as ChargridMouseButton, MouseButton, MouseInput,
-    ScrollDirection,
+    keys, Input, KeyModifiers, KeyboardInput, MouseButton as ChargridMouseButton, MouseButton,
+    MouseInput, ScrollDirection,
 };
 use chargrid_render::Coord;
 use winit::dpi::{LogicalPosition, PhysicalSize};
@@ -13,6 +13,10 @@ use winit::event::{
 pub enum Event {
     Input(Input),
     Resize(PhysicalSize<u32>),
+    /// The key combination for pasting was pressed. The pasted text must be read from the
+    /// clipboard, as winit doesn't report it. If the clipboard can't be read, the key press is
+    /// delivered as input instead.
+    Paste(Option<Input>),
 }
 
 macro_rules! convert_char_shift {
@@ -77,6 +81,9 @@ fn convert_keycode_keyboard_input(code: VirtualKeyCode, shift: bool) -> Option<K
         VirtualKeyCode::Down => KeyboardInput::Down,
         VirtualKeyCode::Escape => keys::ESCAPE,
         VirtualKeyCode::Return => keys::RETURN,
+        VirtualKeyCode::Tab => keys::TAB,
+        VirtualKeyCode::Back => keys::BACKSPACE,
+        VirtualKeyCode::Delete => KeyboardInput::Delete,
         VirtualKeyCode::At => KeyboardInput::Char('@'),
         VirtualKeyCode::Plus => KeyboardInput::Char('+'),
         VirtualKeyCode::Minus => KeyboardInput::Char('-'),
@@ -119,8 +126,36 @@ fn convert_keycode_keyboard_input(code: VirtualKeyCode, shift: bool) -> Option<K
     Some(keyboard_input)
 }
 
+fn convert_modifiers(keymod: ModifiersState) -> KeyModifiers {
+    let mut modifiers = KeyModifiers::NONE;
+    if keymod.shift() {
+        modifiers |= KeyModifiers::SHIFT;
+    }
+    if keymod.alt() {
+        modifiers |= KeyModifiers::ALT;
+    }
+    if keymod.ctrl() {
+        modifiers |= KeyModifiers::CTRL;
+    }
+    if keymod.logo() {
+        modifiers |= KeyModifiers::SUPER;
+    }
+    modifiers
+}
+
 fn convert_keycode(code: VirtualKeyCode, keymod: ModifiersState) -> Option<Input> {
-    convert_keycode_keyboard_input(code, keymod.shift()).map(Input::Keyboard)
+    convert_keycode_keyboard_input(code, keymod.shift())
+        .map(|key| Input::keyboard_with_modifiers(key, convert_modifiers(keymod)))
+}
+
+fn is_paste(code: VirtualKeyCode, keymod: ModifiersState) -> bool {
+    let paste_modifier = if cfg!(target_os = "macos") {
+        keymod.logo()
+    } else {
+        keymod.ctrl()
+    };
+    (code == VirtualKeyCode::V && paste_modifier)
+        || (code == VirtualKeyCode::Insert && keymod.shift())
 }
 
 fn convert_char(ch: char) -> Option<Event> {
@@ -153,11 +188,17 @@ pub fn convert_event(
             *scale_factor = new_scale_factor;
             Some(Event::Resize(*new_inner_size))
         }
+        WindowEvent::Focused(true) => Some(Event::Input(Input::FocusGained)),
+        WindowEvent::Focused(false) => Some(Event::Input(Input::FocusLost)),
         WindowEvent::ReceivedCharacter(ch) => convert_char(ch),
         WindowEvent::KeyboardInput { input, .. } => {
             if let ElementState::Pressed = input.state {
                 if let Some(virtual_keycode) = input.virtual_keycode {
-                    if let Some(input) = convert_keycode(virtual_keycode, modifier_state) {
+                    let input = convert_keycode(virtual_keycode, modifier_state);
+                    if is_paste(virtual_keycode, modifier_state) {
+                        return Some(Event::Paste(input));
+                    }
+                    if let Some(input) = input {
                         return Some(Event::Input(input));
                     }
                 }
@@ -0,0 +1,132 @@
+// Draws the background of each cell, along with underlines, strikethroughs, and bar and
+// underline cursors.
+// Text is drawn separately with wgpu_glyph.
+
+let UNDERLINE_NONE: u32 = 0u;
+let UNDERLINE_SINGLE: u32 = 1u;
+let UNDERLINE_DOUBLE: u32 = 2u;
+let UNDERLINE_CURLY: u32 = 3u;
+let UNDERLINE_DOTTED: u32 = 4u;
+let UNDERLINE_DASHED: u32 = 5u;
+
+let CURSOR_NONE: u32 = 0u;
+let CURSOR_BAR: u32 = 1u;
+let CURSOR_UNDERLINE: u32 = 2u;
+
+let TAU: f32 = 6.283185307179586;
+
+[[block]]
+struct Globals {
+    cell_size_relative_to_window: vec2<f32>;
+    offset_to_centre: vec2<f32>;
+    grid_width: u32;
+    underline_width_cell_ratio: f32;
+    underline_top_offset_cell_ratio: f32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> globals: Globals;
+
+struct VertexInput {
+    [[location(0)]] background_colour: vec3<f32>;
+    [[location(1)]] foreground_colour: vec3<f32>;
+    [[location(2)]] underline_colour: vec3<f32>;
+    [[location(3)]] underline: u32;
+    [[location(4)]] strikethrough: u32;
+    [[location(5)]] cursor: u32;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] background_colour: vec3<f32>;
+    [[location(1), interpolate(flat)]] foreground_colour: vec3<f32>;
+    [[location(2), interpolate(flat)]] underline_colour: vec3<f32>;
+    [[location(3)]] cell_ratio: vec2<f32>;
+    [[location(4), interpolate(flat)]] underline: u32;
+    [[location(5), interpolate(flat)]] strikethrough: u32;
+    // Reading uniforms in the fragment shader gives incorrect values on some opengl
+    // implementations, so they are passed through from the vertex shader instead.
+    [[location(6), interpolate(flat)]] underline_top_offset_and_width_cell_ratio: vec2<f32>;
+    [[location(7), interpolate(flat)]] cursor: u32;
+};
+
+[[stage(vertex)]]
+fn vs_main(
+    [[builtin(vertex_index)]] vertex_index: u32,
+    [[builtin(instance_index)]] instance_index: u32,
+    in: VertexInput,
+) -> VertexOutput {
+    // The magic numbers have the binary representation such that the shift and
+    // mask operations choose the appropriate cell corner for a vertex index.
+    let corner_offset = vec2<f32>(f32((22u >> vertex_index) & 1u), f32((52u >> vertex_index) & 1u));
+    let coord = vec2<f32>(f32(instance_index % globals.grid_width), f32(instance_index / globals.grid_width));
+    let cell_size = globals.cell_size_relative_to_window;
+    let absolute = vec2<f32>(-1.0, -1.0) + (coord * cell_size) + (corner_offset * cell_size) + globals.offset_to_centre;
+    var out: VertexOutput;
+    out.position = vec4<f32>(absolute.x, -absolute.y, 0.0, 1.0);
+    out.background_colour = in.background_colour;
+    out.foreground_colour = in.foreground_colour;
+    out.underline_colour = in.underline_colour;
+    out.cell_ratio = corner_offset;
+    out.underline = in.underline;
+    out.strikethrough = in.strikethrough;
+    out.cursor = in.cursor;
+    out.underline_top_offset_and_width_cell_ratio = vec2<f32>(globals.underline_top_offset_cell_ratio, globals.underline_width_cell_ratio);
+    return out;
+}
+
+fn in_band(y: f32, top: f32, width: f32) -> bool {
+    return y >= top && y < top + width;
+}
+
+fn in_underline(kind: u32, cell_ratio: vec2<f32>, top: f32, width: f32) -> bool {
+    let x = cell_ratio.x;
+    let y = cell_ratio.y;
+    if (kind == UNDERLINE_SINGLE) {
+        return y >= top && y >= top + width;
+    }
+    if (kind == UNDERLINE_DOUBLE) {
+        return in_band(y, top, width) || in_band(y, top - (width * 2.0), width);
+    }
+    if (kind == UNDERLINE_CURLY) {
+        // one period of a sine wave per cell
+        let centre = top + (width / 2.0) + (width * sin(x * TAU));
+        return abs(y - centre) < (width / 2.0);
+    }
+    if (kind == UNDERLINE_DOTTED) {
+        return in_band(y, top, width) && fract(x * 4.0) < 0.5;
+    }
+    if (kind == UNDERLINE_DASHED) {
+        return in_band(y, top, width) && fract(x * 2.0) < 0.7;
+    }
+    return false;
+}
+
+// Cursors are twice as thick as underlines so they can be told apart. Cells are roughly twice as
+// tall as they are wide, so bars are as thick as underlines relative to the cell's width.
+fn in_cursor(cursor: u32, cell_ratio: vec2<f32>, width: f32) -> bool {
+    if (cursor == CURSOR_BAR) {
+        return cell_ratio.x < width * 4.0;
+    }
+    if (cursor == CURSOR_UNDERLINE) {
+        return cell_ratio.y >= 1.0 - (width * 2.0);
+    }
+    return false;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let top = in.underline_top_offset_and_width_cell_ratio.x;
+    let width = in.underline_top_offset_and_width_cell_ratio.y;
+    if (in_cursor(in.cursor, in.cell_ratio, width)) {
+        return vec4<f32>(in.foreground_colour, 1.0);
+    }
+    if (in_underline(in.underline, in.cell_ratio, top, width)) {
+        return vec4<f32>(in.underline_colour, 1.0);
+    }
+    // strikethroughs are as thick as underlines, and centred vertically in the cell
+    if (in.strikethrough != 0u && in_band(in.cell_ratio.y, 0.5 - (width / 2.0), width)) {
+        return vec4<f32>(in.foreground_colour, 1.0);
+    }
+    return vec4<f32>(in.background_colour, 1.0);
+}
@@ -1,9 +1,12 @@
-use crate::{input, Config, Dimensions, FontBytes};
-use chargrid_app::{App, ControlFlow};
+use crate::{blink_hidden, hyperlink_click, input, visible_cursor, Config, Dimensions, FontBytes};
+use chargrid_app::{App, Clipboard};
 #[cfg(feature = "gamepad")]
 use chargrid_gamepad::GamepadContext;
-use chargrid_render::ViewContext;
+use chargrid_render::{Cursor, CursorShape, ImagePlacement, Rgb24, UnderlineKind, ViewContext};
 use grid_2d::{Coord, Grid, Size};
+use std::collections::HashMap;
+use std::fmt;
+use std::process;
 use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
@@ -31,21 +34,63 @@ fn rgba_to_srgb([r, g, b, a]: [f32; 4]) -> [f32; 4] {
     ]
 }
 
-fn font_bytes_to_fonts(FontBytes { normal, bold }: FontBytes) -> Vec<ab_glyph::FontVec> {
-    vec![
-        ab_glyph::FontVec::try_from_vec(normal).unwrap(),
-        ab_glyph::FontVec::try_from_vec(bold).unwrap(),
-    ]
+fn font_bytes_to_fonts(
+    FontBytes {
+        normal,
+        bold,
+        italic,
+        bold_italic,
+    }: FontBytes,
+) -> Result<Vec<ab_glyph::FontVec>, ab_glyph::InvalidFont> {
+    let italic = italic.unwrap_or_else(|| normal.clone());
+    let bold_italic = bold_italic.unwrap_or_else(|| bold.clone());
+    Ok(vec![
+        ab_glyph::FontVec::try_from_vec(normal)?,
+        ab_glyph::FontVec::try_from_vec(bold)?,
+        ab_glyph::FontVec::try_from_vec(italic)?,
+        ab_glyph::FontVec::try_from_vec(bold_italic)?,
+    ])
 }
 
 const FONT_ID_NORMAL: wgpu_glyph::FontId = wgpu_glyph::FontId(0);
 const FONT_ID_BOLD: wgpu_glyph::FontId = wgpu_glyph::FontId(1);
+const FONT_ID_ITALIC: wgpu_glyph::FontId = wgpu_glyph::FontId(2);
+const FONT_ID_BOLD_ITALIC: wgpu_glyph::FontId = wgpu_glyph::FontId(3);
+
+const SHADER_SOURCE: &str = include_str!("./shader.wgsl");
+const IMAGE_SHADER_SOURCE: &str = include_str!("./image_shader.wgsl");
 
 #[derive(Debug)]
 pub enum ContextBuildError {
     FailedToBuildWindow(winit::error::OsError),
     FailedToRequestGraphicsAdapter,
     FailedToRequestDevice(wgpu::RequestDeviceError),
+    NoCompatibleTextureFormat,
+    InvalidFont(ab_glyph::InvalidFont),
+}
+
+impl fmt::Display for ContextBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::FailedToBuildWindow(e) => write!(f, "failed to build window: {}", e),
+            Self::FailedToRequestGraphicsAdapter => {
+                write!(f, "no suitable graphics adapter was found")
+            }
+            Self::FailedToRequestDevice(e) => write!(f, "failed to request device: {}", e),
+            Self::NoCompatibleTextureFormat => {
+                write!(f, "no texture format is compatible with the window")
+            }
+            Self::InvalidFont(e) => write!(f, "invalid font: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ContextBuildError {}
+
+impl From<ContextBuildError> for chargrid_app::Error {
+    fn from(e: ContextBuildError) -> Self {
+        chargrid_app::Error::frontend(e)
+    }
 }
 
 const fn dimensions_from_logical_size(size: winit::dpi::LogicalSize<f64>) -> Dimensions<f64> {
@@ -87,6 +132,10 @@ struct WgpuContext {
     render_buffer: chargrid_render::Buffer,
     glyph_brush: wgpu_glyph::GlyphBrush<(), ab_glyph::FontVec>,
     global_uniforms_buffer: wgpu::Buffer,
+    image_render_pipeline: wgpu::RenderPipeline,
+    image_bind_group_layout: wgpu::BindGroupLayout,
+    image_sampler: wgpu::Sampler,
+    image_textures: HashMap<u32, ImageTexture>,
     window_size: winit::dpi::LogicalSize<f64>,
     scale_factor: f64,
     modifier_state: winit::event::ModifiersState,
@@ -97,7 +146,10 @@ struct WgpuContext {
 struct BackgroundCellInstance {
     background_colour: [f32; 3],
     foreground_colour: [f32; 3],
+    underline_colour: [f32; 3],
     underline: u32,
+    strikethrough: u32,
+    cursor: u32,
 }
 
 impl Default for BackgroundCellInstance {
@@ -105,11 +157,79 @@ impl Default for BackgroundCellInstance {
         Self {
             background_colour: [0.; 3],
             foreground_colour: [1.; 3],
-            underline: 0,
+            underline_colour: [1.; 3],
+            underline: UNDERLINE_NONE,
+            strikethrough: 0,
+            cursor: CURSOR_NONE,
+        }
+    }
+}
+
+// Underline kinds as understood by the shader
+const UNDERLINE_NONE: u32 = 0;
+const UNDERLINE_SINGLE: u32 = 1;
+const UNDERLINE_DOUBLE: u32 = 2;
+const UNDERLINE_CURLY: u32 = 3;
+const UNDERLINE_DOTTED: u32 = 4;
+const UNDERLINE_DASHED: u32 = 5;
+
+// Cursors drawn by the shader. Block cursors are drawn by swapping the cell's colours instead.
+const CURSOR_NONE: u32 = 0;
+const CURSOR_BAR: u32 = 1;
+const CURSOR_UNDERLINE: u32 = 2;
+
+fn underline_code(buffer_cell: &chargrid_render::BufferCell) -> u32 {
+    if !buffer_cell.underline {
+        return UNDERLINE_NONE;
+    }
+    match buffer_cell.underline_kind {
+        UnderlineKind::Single => UNDERLINE_SINGLE,
+        UnderlineKind::Double => UNDERLINE_DOUBLE,
+        UnderlineKind::Curly => UNDERLINE_CURLY,
+        UnderlineKind::Dotted => UNDERLINE_DOTTED,
+        UnderlineKind::Dashed => UNDERLINE_DASHED,
+    }
+}
+
+/// The colours to draw a cell with, given the shape of the cursor drawn over it
+fn display_colours_with_cursor(
+    buffer_cell: &chargrid_render::BufferCell,
+    cursor_shape: Option<CursorShape>,
+) -> (Rgb24, Rgb24) {
+    let (foreground_colour, background_colour) = buffer_cell.display_colours();
+    if cursor_shape == Some(CursorShape::Block) {
+        (background_colour, foreground_colour)
+    } else {
+        (foreground_colour, background_colour)
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, zerocopy::AsBytes, zerocopy::FromBytes)]
+struct ImageInstance {
+    coord: [f32; 2],
+    size: [f32; 2],
+}
+
+impl ImageInstance {
+    fn new(placement: &ImagePlacement) -> Self {
+        Self {
+            coord: [placement.coord.x as f32, placement.coord.y as f32],
+            size: [
+                placement.size.width() as f32,
+                placement.size.height() as f32,
+            ],
         }
     }
 }
 
+/// The pixels of an image, uploaded to the gpu the first time it's drawn
+struct ImageTexture {
+    // kept alive for as long as the bind group refers to it
+    _texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, zerocopy::AsBytes, zerocopy::FromBytes)]
 struct GlobalUniforms {
@@ -118,6 +238,8 @@ struct GlobalUniforms {
     grid_width: u32,
     underline_width_cell_ratio: f32,
     underline_top_offset_cell_ratio: f32,
+    // the shader's view of this struct is padded to a multiple of its alignment
+    _padding: u32,
 }
 
 struct Setup {
@@ -141,7 +263,7 @@ async fn request_adapter_for_backend(
         wgpu::Device,
         wgpu::Queue,
     ),
-    String,
+    ContextBuildError,
 > {
     let power_preference = wgpu::PowerPreference::default();
     let instance = wgpu::Instance::new(backend);
@@ -152,7 +274,7 @@ async fn request_adapter_for_backend(
             compatible_surface: Some(&surface),
         })
         .await
-        .ok_or_else(|| "No suitable GPU adapters found on the system!".to_string())?;
+        .ok_or(ContextBuildError::FailedToRequestGraphicsAdapter)?;
     let (device, queue) = adapter
         .request_device(
             &wgpu::DeviceDescriptor {
@@ -163,11 +285,15 @@ async fn request_adapter_for_backend(
             None,
         )
         .await
-        .map_err(|e| format!("Unable to find a suitable GPU adapter! ({:?})", e))?;
+        .map_err(ContextBuildError::FailedToRequestDevice)?;
     Ok((adapter, instance, surface, device, queue))
 }
 
-async fn setup(title: &str, window_dimensions: Dimensions<f64>, resizable: bool) -> Setup {
+async fn setup(
+    title: &str,
+    window_dimensions: Dimensions<f64>,
+    resizable: bool,
+) -> Result<Setup, ContextBuildError> {
     let event_loop = winit::event_loop::EventLoop::new();
     let window_builder = winit::window::WindowBuilder::new().with_title(title);
     let window_builder = {
@@ -179,21 +305,21 @@ async fn setup(title: &str, window_dimensions: Dimensions<f64>, resizable: bool)
             .with_max_inner_size(logical_size)
             .with_resizable(resizable)
     };
-    let window = window_builder.build(&event_loop).unwrap();
+    let window = window_builder
+        .build(&event_loop)
+        .map_err(ContextBuildError::FailedToBuildWindow)?;
     let (adapter, instance, surface, device, queue) =
         match request_adapter_for_backend(wgpu::BackendBit::PRIMARY, &window).await {
             Ok(x) => x,
-            Err(message) => {
+            Err(error) => {
                 log::error!(
                 "Failed to initialize primary backend: {}\nFalling back to secondary backend....",
-                message
+                error
             );
-                request_adapter_for_backend(wgpu::BackendBit::SECONDARY, &window)
-                    .await
-                    .expect("Failed to initialize secondary backend!")
+                request_adapter_for_backend(wgpu::BackendBit::SECONDARY, &window).await?
             }
         };
-    Setup {
+    Ok(Setup {
         window,
         event_loop,
         instance,
@@ -201,23 +327,10 @@ async fn setup(title: &str, window_dimensions: Dimensions<f64>, resizable: bool)
         adapter,
         device,
         queue,
-    }
+    })
 }
 
 impl WgpuContext {
-    fn spirv_slice_to_shader_module_source(spirv_slice: &[u8]) -> wgpu::ShaderSource<'_> {
-        use std::borrow::Cow;
-        assert!(spirv_slice.len() % 4 == 0);
-        let mut buffer = Vec::with_capacity(spirv_slice.len() / 4);
-        let mut chunks = spirv_slice.chunks_exact(4);
-        for chunk in &mut chunks {
-            let mut array: [u8; 4] = Default::default();
-            array.copy_from_slice(chunk);
-            buffer.push(u32::from_le_bytes(array));
-        }
-        assert!(chunks.remainder().is_empty());
-        wgpu::ShaderSource::SpirV(Cow::Owned(buffer))
-    }
     fn new(
         window: &winit::window::Window,
         mut device: wgpu::Device,
@@ -237,7 +350,7 @@ impl WgpuContext {
         let window_size: winit::dpi::LogicalSize<f64> = physical_size.to_logical(scale_factor);
         let swapchain_format = adapter
             .get_swap_chain_preferred_format(&surface)
-            .expect("Failed to find compatible texture format");
+            .ok_or(ContextBuildError::NoCompatibleTextureFormat)?;
         let sc_desc = wgpu::SwapChainDescriptor {
             usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
             format: swapchain_format,
@@ -246,14 +359,9 @@ impl WgpuContext {
             present_mode: wgpu::PresentMode::Mailbox,
         };
         let swap_chain = device.create_swap_chain(&surface, &sc_desc);
-        let vs_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
-            label: None,
-            source: Self::spirv_slice_to_shader_module_source(include_bytes!("./shader.vert.spv")),
-            flags: wgpu::ShaderFlags::VALIDATION,
-        });
-        let fs_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+        let shader_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
             label: None,
-            source: Self::spirv_slice_to_shader_module_source(include_bytes!("./shader.frag.spv")),
+            source: wgpu::ShaderSource::Wgsl(SHADER_SOURCE.into()),
             flags: wgpu::ShaderFlags::VALIDATION,
         });
         let background_cell_instance_buffer = populate_and_finish_buffer(
@@ -312,8 +420,8 @@ impl WgpuContext {
             label: None,
             layout: Some(&pipeline_layout),
             vertex: wgpu::VertexState {
-                module: &vs_module,
-                entry_point: "main",
+                module: &shader_module,
+                entry_point: "vs_main",
                 buffers: &[wgpu::VertexBufferLayout {
                     array_stride: mem::size_of::<BackgroundCellInstance>() as wgpu::BufferAddress,
                     step_mode: wgpu::InputStepMode::Instance,
@@ -329,10 +437,25 @@ impl WgpuContext {
                             shader_location: 1,
                         },
                         wgpu::VertexAttribute {
-                            format: wgpu::VertexFormat::Sint32,
+                            format: wgpu::VertexFormat::Float32x3,
                             offset: 24,
                             shader_location: 2,
                         },
+                        wgpu::VertexAttribute {
+                            format: wgpu::VertexFormat::Uint32,
+                            offset: 36,
+                            shader_location: 3,
+                        },
+                        wgpu::VertexAttribute {
+                            format: wgpu::VertexFormat::Uint32,
+                            offset: 40,
+                            shader_location: 4,
+                        },
+                        wgpu::VertexAttribute {
+                            format: wgpu::VertexFormat::Uint32,
+                            offset: 44,
+                            shader_location: 5,
+                        },
                     ],
                 }],
             },
@@ -340,15 +463,96 @@ impl WgpuContext {
             depth_stencil: None,
             multisample: wgpu::MultisampleState::default(),
             fragment: Some(wgpu::FragmentState {
-                module: &fs_module,
-                entry_point: "main",
+                module: &shader_module,
+                entry_point: "fs_main",
                 targets: &[swapchain_format.into()],
             }),
         });
-        let glyph_brush =
-            wgpu_glyph::GlyphBrushBuilder::using_fonts(font_bytes_to_fonts(font_bytes))
-                .texture_filter_method(wgpu::FilterMode::Nearest)
-                .build(&mut device, swapchain_format);
+        let image_shader_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(IMAGE_SHADER_SOURCE.into()),
+            flags: wgpu::ShaderFlags::VALIDATION,
+        });
+        let image_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler {
+                            filtering: true,
+                            comparison: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let image_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bind_group_layout, &image_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let image_render_pipeline =
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&image_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &image_shader_module,
+                    entry_point: "vs_main",
+                    buffers: &[wgpu::VertexBufferLayout {
+                        array_stride: mem::size_of::<ImageInstance>() as wgpu::BufferAddress,
+                        step_mode: wgpu::InputStepMode::Instance,
+                        attributes: &[
+                            wgpu::VertexAttribute {
+                                format: wgpu::VertexFormat::Float32x2,
+                                offset: 0,
+                                shader_location: 0,
+                            },
+                            wgpu::VertexAttribute {
+                                format: wgpu::VertexFormat::Float32x2,
+                                offset: 8,
+                                shader_location: 1,
+                            },
+                        ],
+                    }],
+                },
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &image_shader_module,
+                    entry_point: "fs_main",
+                    targets: &[wgpu::ColorTargetState {
+                        format: swapchain_format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrite::ALL,
+                    }],
+                }),
+            });
+        // images are scaled up to cover many pixels, so they're not smoothed
+        let image_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let fonts = font_bytes_to_fonts(font_bytes).map_err(ContextBuildError::InvalidFont)?;
+        let glyph_brush = wgpu_glyph::GlyphBrushBuilder::using_fonts(fonts)
+            .texture_filter_method(wgpu::FilterMode::Nearest)
+            .build(&mut device, swapchain_format);
         let modifier_state = winit::event::ModifiersState::default();
         Ok(Self {
             device,
@@ -363,22 +567,121 @@ impl WgpuContext {
             render_buffer,
             glyph_brush,
             global_uniforms_buffer,
+            image_render_pipeline,
+            image_bind_group_layout,
+            image_sampler,
+            image_textures: HashMap::new(),
             window_size,
             scale_factor,
             modifier_state,
         })
     }
-    fn render_background(&mut self) {
-        for (buffer_cell, background_cell_instance) in self
+    /// Upload the pixels of images drawn for the first time this frame, and free those of images
+    /// which are no longer drawn. Returns a buffer with an instance for each image in the frame.
+    fn render_images(&mut self) -> Option<wgpu::Buffer> {
+        let images = self.render_buffer.images();
+        if images.is_empty() {
+            self.image_textures.clear();
+            return None;
+        }
+        self.image_textures
+            .retain(|&id, _| images.iter().any(|placement| placement.image.id() == id));
+        for placement in images {
+            let image = &placement.image;
+            if self.image_textures.contains_key(&image.id()) {
+                continue;
+            }
+            let size = wgpu::Extent3d {
+                width: image.size().width(),
+                height: image.size().height(),
+                depth_or_array_layers: 1,
+            };
+            let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+                label: None,
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                // images are in srgb, which is converted to linear colour when sampled
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+            });
+            self.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                image.rgba_bytes(),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(4 * size.width),
+                    rows_per_image: std::num::NonZeroU32::new(size.height),
+                },
+                size,
+            );
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &self.image_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.image_sampler),
+                    },
+                ],
+            });
+            self.image_textures.insert(
+                image.id(),
+                ImageTexture {
+                    _texture: texture,
+                    bind_group,
+                },
+            );
+        }
+        let instances = images.iter().map(ImageInstance::new).collect::<Vec<_>>();
+        Some(populate_and_finish_buffer(
+            self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: instances.len() as u64 * std::mem::size_of::<ImageInstance>() as u64,
+                usage: wgpu::BufferUsage::VERTEX,
+                mapped_at_creation: true,
+            }),
+            &instances,
+        ))
+    }
+    fn render_background(&mut self, cursor: Option<Cursor>) {
+        for ((coord, buffer_cell), background_cell_instance) in self
             .render_buffer
-            .iter()
+            .enumerate()
             .zip(self.background_cell_instance_data.iter_mut())
         {
+            let cursor_shape = cursor
+                .filter(|cursor| cursor.coord == coord)
+                .map(|cursor| cursor.shape);
+            let (foreground_colour, background_colour) =
+                display_colours_with_cursor(buffer_cell, cursor_shape);
             background_cell_instance.background_colour =
-                rgb_to_srgb(buffer_cell.background_colour.to_f32_rgb());
+                rgb_to_srgb(background_colour.to_f32_rgb());
             background_cell_instance.foreground_colour =
-                rgb_to_srgb(buffer_cell.foreground_colour.to_f32_rgb());
-            background_cell_instance.underline = buffer_cell.underline as u32;
+                rgb_to_srgb(foreground_colour.to_f32_rgb());
+            background_cell_instance.underline_colour = rgb_to_srgb(
+                buffer_cell
+                    .underline_colour
+                    .unwrap_or(foreground_colour)
+                    .to_f32_rgb(),
+            );
+            background_cell_instance.underline = underline_code(buffer_cell);
+            background_cell_instance.strikethrough = buffer_cell.strikethrough as u32;
+            background_cell_instance.cursor = match cursor_shape {
+                Some(CursorShape::Bar) => CURSOR_BAR,
+                Some(CursorShape::Underline) => CURSOR_UNDERLINE,
+                Some(CursorShape::Block) | None => CURSOR_NONE,
+            };
         }
         self.background_cell_instance_buffer = populate_and_finish_buffer(
             self.device.create_buffer(&wgpu::BufferDescriptor {
@@ -512,6 +815,7 @@ impl SizeContext {
             grid_width: self.grid_size().width(),
             underline_width_cell_ratio: self.underline_width as f32,
             underline_top_offset_cell_ratio: self.underline_top_offset as f32,
+            _padding: 0,
         }
     }
 }
@@ -525,6 +829,7 @@ pub struct Context {
     text_buffer: String,
     instance: wgpu::Instance,
     adapter: wgpu::Adapter,
+    clipboard: Clipboard,
     #[cfg(feature = "gamepad")]
     gamepad: GamepadContext,
 }
@@ -573,7 +878,7 @@ impl Context {
             adapter,
             device,
             queue,
-        } = pollster::block_on(setup(title.as_str(), window_dimensions_px, resizable));
+        } = pollster::block_on(setup(title.as_str(), window_dimensions_px, resizable))?;
         let size_context = SizeContext {
             font_source_scale: ab_glyph::PxScale {
                 x: font_scale.width as f32,
@@ -606,6 +911,7 @@ impl Context {
             text_buffer: String::new(),
             instance,
             adapter,
+            clipboard: Clipboard::new(),
             #[cfg(feature = "gamepad")]
             gamepad: GamepadContext::new(),
         })
@@ -620,42 +926,79 @@ impl Context {
             window: self.window.clone(),
         }
     }
-    pub fn run_app<A>(self, mut app: A) -> !
+    /// Returns a handle for writing to the system clipboard, which can be passed to an app
+    pub fn clipboard(&self) -> Clipboard {
+        self.clipboard.clone()
+    }
+
+    /// Run the app until it exits, then exit the process. Panics if rendering fails or the app
+    /// exits with an error. See `try_run_app` for a version which returns errors instead.
+    pub fn run_app<A>(self, app: A) -> !
     where
         A: App + 'static,
     {
+        match self.try_run_app(app) {
+            Ok(()) => process::exit(0),
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Run the app until it exits, returning an error if rendering fails or the app exits with
+    /// `ControlFlow::ExitWithError`. The window is closed before this returns. Unlike `run_app`,
+    /// this doesn't exit the process, but it's not supported on all platforms which winit
+    /// supports (see `winit::platform::run_return`).
+    pub fn try_run_app<A>(self, mut app: A) -> chargrid_app::Result<()>
+    where
+        A: App + 'static,
+    {
+        use winit::platform::run_return::EventLoopExtRunReturn;
         let Self {
             window,
-            event_loop,
+            mut event_loop,
             mut wgpu_context,
             size_context,
             mut input_context,
             mut text_buffer,
             instance,
             adapter,
+            clipboard,
             #[cfg(feature = "gamepad")]
             mut gamepad,
         } = self;
+        let mut system_clipboard = match arboard::Clipboard::new() {
+            Ok(system_clipboard) => Some(system_clipboard),
+            Err(error) => {
+                log::warn!("failed to access the system clipboard: {}", error);
+                None
+            }
+        };
+        let start_instant = Instant::now();
         let mut frame_instant = Instant::now();
         let mut last_update_inst = Instant::now();
-        let mut exited = false;
+        let mut exit: Option<chargrid_app::Result<()>> = None;
         log::info!("Entering main event loop");
         let mut current_window_dimensions = size_context.native_window_dimensions;
         let mut staging_belt = wgpu::util::StagingBelt::new(1024);
         let executor = async_executor::LocalExecutor::new();
-        event_loop.run(move |event, _, control_flow| {
-            let _ = (&instance, &adapter); // force ownership by the closure
-            if exited {
+        event_loop.run_return(|event, _, control_flow| {
+            let _ = (&instance, &adapter); // keep these alive while the event loop runs
+            if exit.is_some() {
                 *control_flow = winit::event_loop::ControlFlow::Exit;
                 return;
             } else {
                 *control_flow = winit::event_loop::ControlFlow::Poll;
             };
+            if let Some(text) = clipboard.take_text() {
+                if let Some(system_clipboard) = system_clipboard.as_mut() {
+                    if let Err(error) = system_clipboard.set_text(text) {
+                        log::warn!("failed to write to the system clipboard: {}", error);
+                    }
+                }
+            }
             #[cfg(feature = "gamepad")]
             for input in gamepad.drain_input() {
-                if let Some(ControlFlow::Exit) = app.on_input(chargrid_input::Input::Gamepad(input))
-                {
-                    exited = true;
+                if let Some(control_flow) = app.on_input(chargrid_input::Input::Gamepad(input)) {
+                    exit = Some(control_flow.into_result());
                     return;
                 }
             }
@@ -680,9 +1023,26 @@ impl Context {
                         ) {
                             match event {
                                 input::Event::Input(input) => {
-                                    if let Some(ControlFlow::Exit) = app.on_input(input) {
-                                        exited = true;
-                                        return;
+                                    let hyperlink_click =
+                                        hyperlink_click(&wgpu_context.render_buffer, &input);
+                                    for input in std::iter::once(input).chain(hyperlink_click) {
+                                        if let Some(control_flow) = app.on_input(input) {
+                                            exit = Some(control_flow.into_result());
+                                            return;
+                                        }
+                                    }
+                                }
+                                input::Event::Paste(key_input) => {
+                                    let text =
+                                        system_clipboard.as_mut().and_then(|system_clipboard| {
+                                            system_clipboard.get_text().ok()
+                                        });
+                                    let input =
+                                        text.map(chargrid_input::Input::Paste).or(key_input);
+                                    if let Some(input) = input {
+                                        if let Some(control_flow) = app.on_input(input) {
+                                            exit = Some(control_flow.into_result());
+                                        }
                                     }
                                 }
                                 input::Event::Resize(size) => {
@@ -700,15 +1060,18 @@ impl Context {
                     let view_context =
                         ViewContext::default_with_size(wgpu_context.render_buffer.size());
                     wgpu_context.render_buffer.clear();
-                    if let Some(ControlFlow::Exit) = app.on_frame(
+                    if let Some(control_flow) = app.on_frame(
                         frame_duration,
                         view_context,
                         &mut wgpu_context.render_buffer,
                     ) {
-                        exited = true;
+                        exit = Some(control_flow.into_result());
                         return;
                     }
-                    wgpu_context.render_background();
+                    let cursor =
+                        visible_cursor(&wgpu_context.render_buffer, start_instant.elapsed());
+                    wgpu_context.render_background(cursor);
+                    let image_instance_buffer = wgpu_context.render_images();
                     if let Ok(frame) = wgpu_context.swap_chain.get_current_frame() {
                         let mut encoder = wgpu_context.device.create_command_encoder(
                             &wgpu::CommandEncoderDescriptor { label: None },
@@ -742,32 +1105,37 @@ impl Context {
                         text_buffer.clear();
                         for row in wgpu_context.render_buffer.rows() {
                             for cell in row {
-                                text_buffer.push(cell.character);
+                                // continuations of double-width graphemes contribute no text
+                                text_buffer.push_str(cell.character.as_str());
                             }
                         }
                         let mut section = wgpu_glyph::Section::default().with_screen_position((
                             offset_to_centre.width as f32,
                             offset_to_centre.height as f32,
                         ));
+                        let hide_blinking = blink_hidden(start_instant.elapsed());
                         let mut char_start = 0;
-                        for (ch, (coord, cell)) in text_buffer
-                            .chars()
-                            .zip(wgpu_context.render_buffer.enumerate())
-                        {
-                            let char_end = char_start + ch.len_utf8();
+                        for (coord, cell) in wgpu_context.render_buffer.enumerate() {
+                            let char_end = char_start + cell.character.as_str().len();
                             let str_slice = &text_buffer[char_start..char_end];
-                            let font_id = if cell.bold {
-                                FONT_ID_BOLD
-                            } else {
-                                FONT_ID_NORMAL
+                            let font_id = match (cell.bold, cell.italic) {
+                                (false, false) => FONT_ID_NORMAL,
+                                (true, false) => FONT_ID_BOLD,
+                                (false, true) => FONT_ID_ITALIC,
+                                (true, true) => FONT_ID_BOLD_ITALIC,
                             };
+                            let cursor_shape = cursor
+                                .filter(|cursor| cursor.coord == coord)
+                                .map(|cursor| cursor.shape);
+                            let (foreground_colour, _) =
+                                display_colours_with_cursor(cell, cursor_shape);
+                            let alpha = if cell.blink && hide_blinking { 0. } else { 1. };
+                            let colour = rgba_to_srgb(foreground_colour.to_f32_rgba(alpha));
                             section = section.add_text(
                                 wgpu_glyph::Text::new(str_slice)
                                     .with_scale(font_scale)
                                     .with_font_id(font_id)
-                                    .with_color(rgba_to_srgb(
-                                        cell.foreground_colour.to_f32_rgba(1.),
-                                    )),
+                                    .with_color(colour),
                             );
                             char_start = char_end;
                             if coord.x as u32 == wgpu_context.render_buffer.size().width() - 1 {
@@ -776,17 +1144,44 @@ impl Context {
                             }
                         }
                         wgpu_context.glyph_brush.queue(section);
-                        wgpu_context
-                            .glyph_brush
-                            .draw_queued(
-                                &wgpu_context.device,
-                                &mut staging_belt,
-                                &mut encoder,
-                                &frame.output.view,
-                                wgpu_context.window_size.width as u32,
-                                wgpu_context.window_size.height as u32,
-                            )
-                            .unwrap();
+                        if let Err(message) = wgpu_context.glyph_brush.draw_queued(
+                            &wgpu_context.device,
+                            &mut staging_belt,
+                            &mut encoder,
+                            &frame.output.view,
+                            wgpu_context.window_size.width as u32,
+                            wgpu_context.window_size.height as u32,
+                        ) {
+                            exit = Some(Err(chargrid_app::Error::frontend(message)));
+                            return;
+                        }
+                        // images are drawn over the text of the cells they cover
+                        if let Some(image_instance_buffer) = image_instance_buffer.as_ref() {
+                            let mut render_pass =
+                                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                                    label: None,
+                                    color_attachments: &[wgpu::RenderPassColorAttachment {
+                                        view: &frame.output.view,
+                                        resolve_target: None,
+                                        ops: wgpu::Operations {
+                                            load: wgpu::LoadOp::Load,
+                                            store: true,
+                                        },
+                                    }],
+                                    depth_stencil_attachment: None,
+                                });
+                            render_pass.set_pipeline(&wgpu_context.image_render_pipeline);
+                            render_pass.set_bind_group(0, &wgpu_context.bind_group, &[]);
+                            render_pass.set_vertex_buffer(0, image_instance_buffer.slice(..));
+                            for (i, placement) in
+                                wgpu_context.render_buffer.images().iter().enumerate()
+                            {
+                                let image_texture =
+                                    &wgpu_context.image_textures[&placement.image.id()];
+                                render_pass.set_bind_group(1, &image_texture.bind_group, &[]);
+                                render_pass.draw(0..6, (i as u32)..(i as u32 + 1));
+                            }
+                        }
                         staging_belt.finish();
                         wgpu_context.queue.submit(std::iter::once(encoder.finish()));
                         executor.spawn(staging_belt.recall()).detach();
@@ -811,6 +1206,34 @@ impl Context {
                 }
                 _ => (),
             }
-        })
+        });
+        exit.unwrap_or(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn image_shader_is_valid() {
+        let module = naga::front::wgsl::parse_str(IMAGE_SHADER_SOURCE).unwrap();
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .unwrap();
+    }
+
+    #[test]
+    fn shader_is_valid() {
+        let module = naga::front::wgsl::parse_str(SHADER_SOURCE).unwrap();
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .unwrap();
     }
 }